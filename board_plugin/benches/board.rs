@@ -0,0 +1,154 @@
+//! Board generation and play state on large boards.
+//!
+//! `cargo bench -p board_plugin --bench board`
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use board_plugin::components::Coordinates;
+use board_plugin::resources::cells::CellSet;
+use board_plugin::resources::tile_map::TileMap;
+use board_plugin::resources::BoardOptions;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, SamplingMode};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Expert density, about one tile in five holds a mine
+const DENSITY: f32 = 0.206;
+
+/// Mines of the 1000x1000 boards both generations are compared on. The
+/// nested one recounts the whole board for every mine, which takes about
+/// 40 minutes per board at expert density.
+const SPARSE_MINES: u16 = 100;
+
+fn options(size: u16, density: f32) -> BoardOptions {
+    BoardOptions {
+        map_size: (size, size),
+        bomb_count: (size as f32 * size as f32 * density).min(u16::MAX as f32) as u16,
+        seed: Some(7),
+        ..Default::default()
+    }
+}
+
+/// The generation this crate used to ship: every mine placement recounts
+/// the neighbors of the whole board
+fn nested_generation(size: u16, bomb_count: u16, seed: u64) -> Vec<Vec<u8>> {
+    const MINE: u8 = u8::MAX;
+    let size = size as usize;
+    let mut map = vec![vec![0u8; size]; size];
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut bombs_left = bomb_count;
+    while bombs_left > 0 {
+        let (x, y) = (rng.gen_range(0..size), rng.gen_range(0..size));
+        if map[y][x] != MINE {
+            map[y][x] = MINE;
+            bombs_left -= 1;
+        }
+        for y in 0..size {
+            for x in 0..size {
+                if map[y][x] == MINE {
+                    continue;
+                }
+                let mut count = 0;
+                for dy in -1isize..=1 {
+                    for dx in -1isize..=1 {
+                        let (nx, ny) = (x as isize + dx, y as isize + dy);
+                        if (dx, dy) != (0, 0)
+                            && (0..size as isize).contains(&nx)
+                            && (0..size as isize).contains(&ny)
+                            && map[ny as usize][nx as usize] == MINE
+                        {
+                            count += 1;
+                        }
+                    }
+                }
+                map[y][x] = count;
+            }
+        }
+    }
+    map
+}
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    group.sample_size(10);
+    for size in [50u16, 100] {
+        let options = options(size, DENSITY);
+        group.bench_with_input(BenchmarkId::new("nested", size), &options, |b, options| {
+            b.iter(|| nested_generation(size, options.bomb_count, 7))
+        });
+    }
+    for size in [50u16, 100, 1000] {
+        let options = options(size, DENSITY);
+        group.bench_with_input(BenchmarkId::new("flat", size), &options, |b, options| {
+            b.iter(|| TileMap::from_options(black_box(options)))
+        });
+    }
+    group.finish();
+
+    // Flat sampling runs each of the 10 samples once instead of up to 10
+    // times, the nested baseline takes about a second per board
+    let mut group = c.benchmark_group("generation_sparse");
+    group.sample_size(10);
+    group.sampling_mode(SamplingMode::Flat);
+    group.measurement_time(Duration::from_secs(15));
+    let options = BoardOptions {
+        bomb_count: SPARSE_MINES,
+        ..options(1000, DENSITY)
+    };
+    group.bench_with_input(BenchmarkId::new("nested", 1000), &options, |b, options| {
+        b.iter(|| nested_generation(1000, options.bomb_count, 7))
+    });
+    group.bench_with_input(BenchmarkId::new("flat", 1000), &options, |b, options| {
+        b.iter(|| TileMap::from_options(black_box(options)))
+    });
+    group.finish();
+}
+
+fn flood_fill(c: &mut Criterion) {
+    // Sparse enough for the opening to cover most of the board
+    let tile_map = TileMap::from_options(&options(1000, 0.01));
+    let start = tile_map.first_empty().unwrap();
+    let mut group = c.benchmark_group("flood_fill");
+    group.sample_size(10);
+    group.bench_function("1000", |b| {
+        b.iter(|| tile_map.flood_fill(black_box(start), |_| false))
+    });
+    group.finish();
+}
+
+fn discovered(c: &mut Criterion) {
+    let cells: Vec<Coordinates> = (0..1000u16)
+        .flat_map(|y| (0..1000u16).map(move |x| Coordinates { x, y }))
+        .collect();
+    let mut group = c.benchmark_group("discovered");
+    group.sample_size(10);
+    group.bench_function("hash_set", |b| {
+        b.iter(|| {
+            let mut set = HashSet::new();
+            for coordinates in cells.iter() {
+                set.insert(*coordinates);
+            }
+            cells
+                .iter()
+                .filter(|coordinates| set.contains(coordinates))
+                .count()
+        })
+    });
+    group.bench_function("cell_set", |b| {
+        b.iter(|| {
+            let mut set = CellSet::new(1000, 1000);
+            for coordinates in cells.iter() {
+                set.insert(*coordinates);
+            }
+            cells
+                .iter()
+                .filter(|coordinates| set.contains(coordinates))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, generation, flood_fill, discovered);
+criterion_main!(benches);
@@ -2,9 +2,12 @@ use std::fmt::{self, Display, Formatter};
 use std::ops::{Add, Sub};
 
 use bevy::prelude::Component;
+use serde::{Deserialize, Serialize};
 
 #[cfg_attr(feature="debug", derive(bevy_inspector_egui::InspectorOptions))]
-#[derive(Debug, Default, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Component)]
+#[derive(
+    Debug, Default, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Component, Serialize, Deserialize,
+)]
 pub struct Coordinates {
     pub x: u16,
     pub y: u16,
@@ -21,13 +24,14 @@ impl Add for Coordinates {
     }
 }
 
-impl Add<(i8, i8)> for Coordinates {
-    type Output = Self;
-
-    fn add(self, (x, y): (i8, i8)) -> Self::Output {
-        let x = ((self.x as i16) + x as i16) as u16;
-        let y = ((self.y as i16) + y as i16) as u16;
-        Self { x, y }
+impl Coordinates {
+    /// Coordinates moved by `(dx, dy)`, `None` past the bottom or left edge
+    /// instead of wrapping around
+    pub fn offset(self, dx: i16, dy: i16) -> Option<Self> {
+        Some(Self {
+            x: self.x.checked_add_signed(dx)?,
+            y: self.y.checked_add_signed(dy)?,
+        })
     }
 }
 
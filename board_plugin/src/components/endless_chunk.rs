@@ -0,0 +1,9 @@
+use bevy::{prelude::Component, reflect::Reflect};
+use serde::{Deserialize, Serialize};
+
+use crate::resources::endless::ChunkCoordinates;
+
+/// Spawned chunk of the endless world, holding its tiles as children
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::InspectorOptions))]
+#[derive(Debug, Default, Copy, Clone, Component, Reflect, Serialize, Deserialize)]
+pub struct EndlessChunk(pub ChunkCoordinates);
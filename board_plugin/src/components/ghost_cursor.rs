@@ -0,0 +1,7 @@
+use bevy::{prelude::Component, reflect::Reflect};
+use serde::{Deserialize, Serialize};
+
+/// Recorded mouse position shown during replay playback
+#[cfg_attr(feature = "debug", derive(bevy_inspector_egui::InspectorOptions))]
+#[derive(Debug, Default, Copy, Clone, Component, Reflect, Serialize, Deserialize)]
+pub struct GhostCursor;
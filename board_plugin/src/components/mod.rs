@@ -1,13 +1,41 @@
+pub use board_background::*;
 pub use bomb::*;
 pub use bomb_neighbor::*;
 pub use coordinates::*;
 pub use covered::*;
+pub use end_screen::*;
+pub use endless_chunk::*;
+pub use endless_hud::*;
 pub use tile_cover::*;
 pub use flag::*;
+pub use ghost_cursor::*;
+pub use hint_display::*;
+pub use hint_highlight::*;
+pub use lives_display::*;
+pub use playback_controls::*;
+pub use player::*;
+pub use player_cursor::*;
+pub use share_code_dialog::*;
+pub use stats_screen::*;
+pub use wrap_hint::*;
 
 mod tile_cover;
+mod board_background;
 mod bomb;
 mod bomb_neighbor;
 mod coordinates;
 mod covered;
+mod end_screen;
+mod endless_chunk;
+mod endless_hud;
 mod flag;
+mod ghost_cursor;
+mod hint_display;
+mod hint_highlight;
+mod lives_display;
+mod playback_controls;
+mod player;
+mod player_cursor;
+mod share_code_dialog;
+mod stats_screen;
+mod wrap_hint;
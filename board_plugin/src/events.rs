@@ -1,5 +1,8 @@
 use bevy::prelude::Entity;
 
+use crate::components::{Coordinates, PlayerId};
+use crate::resources::versus::VersusOutcome;
+
 #[derive(Debug, Copy, Clone)]
 pub struct TileDiscoverEvent(pub Entity);
 
@@ -8,3 +11,21 @@ pub struct BombExplosionEvent;
 
 #[derive(Debug, Copy, Clone)]
 pub struct TileMarkEvent(pub Entity);
+
+/// Reveals the covered neighbors of a revealed number once all of its mines
+/// are flagged
+#[derive(Debug, Copy, Clone)]
+pub struct TileChordEvent(pub Entity);
+
+#[derive(Debug, Copy, Clone)]
+pub struct VersusEndEvent(pub VersusOutcome);
+
+#[derive(Debug, Copy, Clone)]
+pub struct BoardCompletedEvent;
+
+/// Tile hovered by a remote player, `None` once their cursor leaves the board
+#[derive(Debug, Copy, Clone)]
+pub struct PlayerCursorEvent {
+    pub player: PlayerId,
+    pub coordinates: Option<Coordinates>,
+}
@@ -1,41 +1,77 @@
 mod bounds;
 pub mod components;
-mod events;
+pub mod events;
+pub mod net;
 mod queue;
+pub mod replay;
 pub mod resources;
+pub mod solver;
+pub mod storage;
 mod systems;
 
+use bevy::ecs::system::EntityCommands;
 use bevy::log;
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 
 use resources::board::Board;
+use resources::clicks::ClickCounter;
+use resources::daily::{DailyChallenge, DailyHistory};
+use resources::game_summary::GameSummary;
+use resources::generator::MineGenerators;
+use resources::hints::{HintCounter, HintSearch};
+use resources::history::UndoHistory;
+use resources::share_code::{ShareCode, ShareCodeInput};
+use resources::stats::GameStats;
 use resources::tile::Tile;
 use resources::tile_map::TileMap;
+use resources::topology::TopologyKind;
+use resources::versus::VersusState;
 use resources::BoardAssets;
 use resources::BoardOptions;
 use resources::BoardPosition;
+use resources::GameRules;
 use resources::TileSize;
 
+use components::BoardBackground;
 use components::Bomb;
 use components::BombNeighbor;
 use components::Coordinates;
 use components::Covered;
+use components::EndScreen;
+use components::HintDisplay;
+use components::LivesDisplay;
+use components::PlaybackControls;
 #[cfg(feature = "debug")]
 use components::Flag;
 use components::TileCover;
+use components::WrapHint;
 
+use events::BoardCompletedEvent;
 use events::BombExplosionEvent;
+use events::PlayerCursorEvent;
 use events::TileDiscoverEvent;
+use events::TileChordEvent;
 use events::TileMarkEvent;
+use events::VersusEndEvent;
 
 use bounds::Bounds2;
 
+use net::NetworkClient;
+
+use replay::{ReplayPlayback, ReplayRecorder};
+
+/// Colors of the stripes along the left and right edges, then along the
+/// bottom and top edges of wrapping boards
+const WRAP_HINT_COLORS: [Color; 2] = [Color::CYAN, Color::ORANGE];
+
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum AppState {
     #[default]
     InGame,
     Out,
+    /// Endless board streamed around the camera
+    Endless,
 }
 
 pub struct BoardPlugin<T> {
@@ -44,20 +80,158 @@ pub struct BoardPlugin<T> {
 
 impl<T: States> Plugin for BoardPlugin<T> {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::create_board.in_schedule(OnEnter(AppState::InGame)))
+        app.init_resource::<MineGenerators>()
+            .add_system(Self::create_board.in_schedule(OnEnter(AppState::InGame)))
             .add_systems(
                 (
-                    systems::input::handle_input,
-                    systems::uncover::handle_discover_event,
+                    systems::input::handle_input
+                        .run_if(not(resource_exists::<GameSummary>()))
+                        .run_if(not(resource_exists::<ReplayPlayback>())),
+                    systems::uncover::handle_discover_event
+                        .run_if(not(resource_exists::<NetworkClient>())),
                     systems::uncover::discover_tiles,
-                    systems::mark::mark_tiles,
+                    systems::mark::mark_tiles.run_if(not(resource_exists::<NetworkClient>())),
+                )
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_system(
+                systems::network::request_sync
+                    .run_if(resource_exists::<NetworkClient>())
+                    .in_schedule(OnEnter(AppState::InGame)),
+            )
+            .add_systems(
+                (
+                    systems::network::send_commands,
+                    systems::network::send_cursor,
+                    systems::network::apply_server_diffs,
+                    systems::network::update_player_cursors,
+                )
+                    .distributive_run_if(resource_exists::<NetworkClient>())
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_system(
+                systems::undo::undo_moves
+                    .run_if(resource_exists::<UndoHistory>())
+                    .run_if(not(resource_exists::<ShareCodeInput>()))
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_systems(
+                (
+                    systems::hint::give_hint
+                        .run_if(not(resource_exists::<GameSummary>()))
+                        .run_if(not(resource_exists::<ReplayPlayback>()))
+                        .run_if(not(resource_exists::<ShareCodeInput>())),
+                    systems::hint::show_found_hint
+                        .run_if(resource_exists::<HintSearch>())
+                        .run_if(not(resource_exists::<GameSummary>())),
+                    systems::hint::clear_hint
+                        .run_if(resource_exists_and_changed::<Board>())
+                        .after(systems::uncover::handle_discover_event)
+                        .after(systems::mark::mark_tiles),
+                )
+                    .distributive_run_if(resource_exists::<HintCounter>())
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_system(
+                systems::lives::update_lives_display
+                    .run_if(resource_exists_and_changed::<Board>())
+                    .after(systems::uncover::handle_discover_event)
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_system(
+                systems::versus::update_turn_indicator
+                    .run_if(resource_exists::<VersusState>())
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_systems(
+                (
+                    systems::summary::summarize_local_game
+                        .run_if(not(resource_exists::<NetworkClient>()))
+                        .after(systems::uncover::handle_discover_event),
+                    systems::summary::spawn_end_screen.run_if(resource_added::<GameSummary>()),
+                    systems::daily::begin_daily_attempt
+                        .run_if(resource_added::<Board>())
+                        .run_if(resource_exists::<DailyChallenge>())
+                        .run_if(resource_exists::<DailyHistory>())
+                        .run_if(not(resource_exists::<NetworkClient>()))
+                        .run_if(not(resource_exists::<ReplayPlayback>()))
+                        .before(systems::daily::record_daily_result),
+                    systems::daily::record_daily_result
+                        .run_if(resource_exists::<DailyChallenge>())
+                        .run_if(resource_exists::<DailyHistory>())
+                        .run_if(not(resource_exists::<NetworkClient>()))
+                        .run_if(not(resource_exists::<ReplayPlayback>()))
+                        .after(systems::uncover::handle_discover_event),
+                    // Only regular single player games count
+                    systems::stats::record_game_stats
+                        .run_if(resource_exists::<GameStats>())
+                        .run_if(not(resource_exists::<UndoHistory>()))
+                        .run_if(not(resource_exists::<NetworkClient>()))
+                        .run_if(not(resource_exists::<ReplayPlayback>()))
+                        .run_if(not(resource_exists::<VersusState>()))
+                        .after(systems::uncover::handle_discover_event),
                 )
                     .in_set(OnUpdate(AppState::InGame)),
             )
+            .add_systems(
+                (
+                    systems::record::record_actions,
+                    systems::record::save_replay
+                        .after(systems::record::record_actions)
+                        .after(systems::summary::summarize_local_game),
+                )
+                    .distributive_run_if(resource_exists::<ReplayRecorder>())
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_system(
+                systems::playback::spawn_playback_controls
+                    .run_if(resource_exists::<ReplayPlayback>())
+                    .in_schedule(OnEnter(AppState::InGame)),
+            )
+            .add_systems(
+                (
+                    systems::playback::handle_playback_keys,
+                    systems::playback::scrub,
+                    systems::playback::play_replay
+                        .after(systems::playback::handle_playback_keys)
+                        .after(systems::playback::scrub),
+                    systems::playback::update_playback_controls
+                        .after(systems::playback::play_replay),
+                    systems::playback::update_ghost_cursor.after(systems::playback::play_replay),
+                )
+                    .distributive_run_if(resource_exists::<ReplayPlayback>())
+                    .in_set(OnUpdate(AppState::InGame)),
+            )
+            .add_systems((
+                systems::share::open_share_code_input
+                    .run_if(not(resource_exists::<ShareCodeInput>())),
+                systems::share::spawn_share_code_dialog.run_if(resource_added::<ShareCodeInput>()),
+                systems::share::edit_share_code.run_if(resource_exists::<ShareCodeInput>()),
+                systems::stats::toggle_stats_screen
+                    .run_if(resource_exists::<GameStats>())
+                    .run_if(not(resource_exists::<ShareCodeInput>())),
+            ))
             .add_system(Self::cleanup_board.in_schedule(OnExit(AppState::InGame)))
+            .add_system(systems::endless::create_world.in_schedule(OnEnter(AppState::Endless)))
+            .add_systems(
+                (
+                    systems::endless::pan_camera,
+                    systems::endless::handle_input.run_if(not(resource_exists::<ShareCodeInput>())),
+                    systems::endless::stream_chunks
+                        .after(systems::endless::pan_camera)
+                        .after(systems::endless::handle_input),
+                    systems::endless::update_hud.after(systems::endless::handle_input),
+                )
+                    .in_set(OnUpdate(AppState::Endless)),
+            )
+            .add_system(systems::endless::cleanup_world.in_schedule(OnExit(AppState::Endless)))
             .add_event::<BombExplosionEvent>()
+            .add_event::<BoardCompletedEvent>()
             .add_event::<TileMarkEvent>()
-            .add_event::<TileDiscoverEvent>();
+            .add_event::<TileChordEvent>()
+            .add_event::<TileDiscoverEvent>()
+            .add_event::<VersusEndEvent>()
+            .add_event::<PlayerCursorEvent>();
 
         log::info!("Loaded Board Plugin");
         #[cfg(feature = "debug")]
@@ -73,20 +247,43 @@ impl<T: States> Plugin for BoardPlugin<T> {
 
 impl<T> BoardPlugin<T> {
     /// System to generate the complete board
+    #[allow(clippy::too_many_arguments)]
     pub fn create_board(
         mut commands: Commands,
+        time: Res<Time>,
         board_options: Option<Res<BoardOptions>>,
+        network: Option<Res<NetworkClient>>,
+        playback: Option<ResMut<ReplayPlayback>>,
+        generators: Res<MineGenerators>,
         window: Query<&Window>,
         mut tile_trigger_ewr: EventWriter<TileDiscoverEvent>,
         board_assets: Res<BoardAssets>,
     ) {
-        let options = match board_options {
-            None => BoardOptions::default(),
-            Some(opts) => opts.clone(),
+        let mut options = match (board_options, &playback) {
+            (_, Some(playback)) => playback.replay.options.clone(),
+            (None, None) => BoardOptions::default(),
+            (Some(opts), None) => opts.clone(),
         };
 
-        let mut tile_map = TileMap::empty(options.map_size.0, options.map_size.1);
-        tile_map.set_bombs(options.bomb_count);
+        let replaying = playback.is_some();
+        let tile_map = match (&network, playback) {
+            // Replays bring their own layout
+            (_, Some(mut playback)) => {
+                playback.take_rebuild();
+                let mut tile_map = TileMap::blank(&options);
+                tile_map.place_mines(&playback.replay.mines);
+                tile_map
+            }
+            // The layout stays on the server, tiles are filled in as they get revealed
+            (Some(client), None) => {
+                client.welcome.apply_to(&mut options);
+                let mut tile_map = TileMap::blank(&options);
+                tile_map.bomb_count = options.bomb_count;
+                tile_map.mine_cells = options.bomb_count;
+                tile_map
+            }
+            (None, None) => TileMap::generate(&options, &generators),
+        };
 
         #[cfg(feature = "debug")]
         // Tilemap debugging
@@ -94,15 +291,13 @@ impl<T> BoardPlugin<T> {
 
         let tile_size = match options.tile_size {
             TileSize::Fixed(v) => v,
-            TileSize::Adaptive { min, max } => {
-                Self::adaptive_tile_size(window, (min, max), (tile_map.width, tile_map.height))
-            }
+            TileSize::Adaptive { min, max } => Self::adaptive_tile_size(window, (min, max), &tile_map),
         };
 
-        let board_size = Vec2::new(
-            tile_map.width as f32 * tile_size,
-            tile_map.height as f32 * tile_size,
-        );
+        let board_size = tile_map
+            .topology
+            .topology()
+            .board_size(tile_map.size(), tile_size);
         log::info!("board size: {}", board_size);
         // We define the board anchor position (bottom left)
         let board_position = match options.position {
@@ -113,13 +308,12 @@ impl<T> BoardPlugin<T> {
         };
 
         // let mut covered_tiles = HashSet::with_capacity((tile_map.width * tile_map.height).into());
-        let mut tiles = HashMap::with_capacity((tile_map.width * tile_map.height).into());
-        let mut safe_start = None;
+        let mut tiles = HashMap::with_capacity(tile_map.width as usize * tile_map.height as usize);
 
         let board_entity = commands
             .spawn(SpatialBundle {
                 visibility: Visibility::Visible,
-                transform: Transform::from_translation(board_position.into()),
+                transform: Transform::from_translation(board_position),
                 ..Default::default()
             })
             .insert(Name::new("Board"))
@@ -135,7 +329,8 @@ impl<T> BoardPlugin<T> {
                         transform: Transform::from_xyz(board_size.x / 2., board_size.y / 2., 0.),
                         ..Default::default()
                     })
-                    .insert(Name::new("Background"));
+                    .insert(Name::new("Background"))
+                    .insert(BoardBackground);
 
                 Self::spawn_tiles(
                     parent,
@@ -144,15 +339,43 @@ impl<T> BoardPlugin<T> {
                     options.tile_padding,
                     &board_assets,
                     &mut tiles,
-                    &mut safe_start,
                 );
+
+                if tile_map.topology == TopologyKind::Torus {
+                    Self::spawn_wrap_hints(parent, tile_map.size(), tile_size);
+                }
             })
             .id();
 
-        if options.safe_start {
-            if let Some(entity) = safe_start {
-                tile_trigger_ewr.send(TileDiscoverEvent(entity));
+        // The opening of a replay is its first recorded action
+        let start = options.start_coordinates(&tile_map);
+        if let Some(entity) = start.and_then(|start| tiles.get(&start)).filter(|_| !replaying) {
+            tile_trigger_ewr.send(TileDiscoverEvent(*entity));
+        }
+
+        // Practice games can be rewound, their replays would not add up
+        let practice = options.practice && options.rules == GameRules::Classic;
+        if network.is_none() && !replaying {
+            log::info!("Board code: {}", ShareCode::new(&tile_map, start));
+            if practice {
+                log::info!("Practice game: Z undoes, Y redoes, not ranked");
+                commands.insert_resource(UndoHistory::default());
+            } else {
+                commands.insert_resource(ReplayRecorder::new(&options, &tile_map));
             }
+            commands.insert_resource(ClickCounter::default());
+        }
+
+        if options.rules == GameRules::Versus {
+            // Only an opening that is actually uncovered spares the turn
+            commands.insert_resource(VersusState::new(start.is_some()));
+        } else if options.lives > 1 {
+            systems::lives::spawn_lives_display(&mut commands, options.lives, &board_assets);
+        }
+        // Hints are for a single player against the board, replays count
+        // the recorded ones
+        if network.is_none() && options.rules == GameRules::Classic {
+            commands.insert_resource(HintCounter::default());
         }
 
         commands.insert_resource(Board::new(
@@ -164,42 +387,26 @@ impl<T> BoardPlugin<T> {
             tile_size,
             tile_map,
             tiles,
+            options.lives,
+            time.elapsed(),
         ));
     }
 
     fn adaptive_tile_size(
         windows: Query<&Window>,
         (min, max): (f32, f32),
-        (width, height): (u16, u16),
+        tile_map: &TileMap,
     ) -> f32 {
-        for window in &windows {
-            // TODO: fix this (get primary window instead)
-            let max_width = window.resolution.width() / width as f32;
-            let max_heigth = window.resolution.height() / height as f32;
-            return max_width.min(max_heigth).clamp(min, max);
-        }
-
-        return 0.;
-    }
-
-    fn bomb_count_text_bundle(
-        count: u8,
-        board_assets: &BoardAssets, 
-    ) -> SpriteBundle {
-        let asset = match count {
-            1 => board_assets.material_1.texture.clone(),
-            2 => board_assets.material_2.texture.clone(),
-            3 => board_assets.material_3.texture.clone(),
-            _ => panic!()
-        };
-
-        SpriteBundle {
-            sprite: Sprite {
-                ..default()
-            },
-            transform: Transform::from_xyz(0., 0., 2.),
-            texture: asset,
-            ..default()
+        // Board extent for tiles of size 1
+        let extent = tile_map.topology.topology().board_size(tile_map.size(), 1.);
+        // TODO: fix this (get primary window instead)
+        match windows.iter().next() {
+            Some(window) => {
+                let max_width = window.resolution.width() / extent.x;
+                let max_heigth = window.resolution.height() / extent.y;
+                max_width.min(max_heigth).clamp(min, max)
+            }
+            None => 0.,
         }
     }
 
@@ -210,14 +417,22 @@ impl<T> BoardPlugin<T> {
         padding: f32,
         board_assets: &BoardAssets,
         tiles: &mut HashMap<Coordinates, Entity>,
-        safe_start_entity: &mut Option<Entity>,
     ) {
-        for (y, line) in tile_map.iter().enumerate() {
+        let topology = tile_map.topology.topology();
+        let (tile_material, cover_material) = board_assets.tile_materials(tile_map.topology);
+        let sprite_size = topology.tile_sprite_size(size - padding);
+        // Square covers keep their texture size, hexagons have to line up
+        let cover_size = (tile_map.topology == TopologyKind::Hex).then_some(sprite_size);
+        for (y, line) in tile_map.rows().enumerate() {
             for (x, tile) in line.iter().enumerate() {
                 let coordinates = Coordinates {
                     x: x as u16,
                     y: y as u16,
                 };
+                // Holes get no entity at all
+                if !tile_map.contains(coordinates) {
+                    continue;
+                }
 
                 let covered = Covered { is_covered: true };
 
@@ -230,16 +445,14 @@ impl<T> BoardPlugin<T> {
                 let base_command = cmd
                     .insert(SpriteBundle {
                         sprite: Sprite {
-                            color: board_assets.tile_material.color,
-                            custom_size: Some(Vec2::splat(size - padding as f32)),
+                            color: tile_material.color,
+                            custom_size: Some(sprite_size),
                             ..Default::default()
                         },
-                        transform: Transform::from_xyz(
-                            (x as f32 * size) + (size / 2.),
-                            (y as f32 * size) + (size / 2.),
-                            1.,
+                        transform: Transform::from_translation(
+                            topology.tile_center(coordinates, size).extend(1.),
                         ),
-                        texture: board_assets.tile_material.texture.clone(),
+                        texture: tile_material.texture.clone(),
                         ..Default::default() // We add the `Coordinates` component to our tile entity
                     })
                     .insert(covered)
@@ -254,69 +467,190 @@ impl<T> BoardPlugin<T> {
                     x, y, tile_entity
                 )));
 
-                if safe_start_entity.is_none() && *tile == Tile::Empty {
-                    *safe_start_entity = Some(tile_entity);
-                }
-
                 // spawn tile cover
                 cmd.with_children(|parent| {
-                    let entity = parent
+                    parent
                         .spawn(SpriteBundle {
                             sprite: Sprite {
+                                color: cover_material.color,
+                                custom_size: cover_size,
                                 ..Default::default()
                             },
                             transform: Transform::from_xyz(-1., 1., 3.),
-                            texture: board_assets.covered_tile_material.texture.clone(),
+                            texture: cover_material.texture.clone(),
                             visibility: Visibility::Visible,
                             ..Default::default()
                         })
                         .insert(Name::new("Tile: Cover"))
-                        .insert(TileCover)
-                        .id();
-
-                    if safe_start_entity.is_none() && *tile == Tile::Empty {
-                        *safe_start_entity = Some(entity);
-                    }
+                        .insert(TileCover);
                 });
 
                 // spawn tile face
-                match tile {
-                    Tile::Bomb => {
-                        cmd.insert(Bomb);
-                        cmd.with_children(|parent| {
-                            parent
-                                .spawn(SpriteBundle {
-                                    sprite: Sprite {
-                                        custom_size: Some(Vec2::splat(size - padding)),
-                                        ..default()
-                                    },
-                                    transform: Transform::from_xyz(0., 0., 1.),
-                                    texture: board_assets.bomb_material.texture.clone(),
-                                    ..default()
-                                })
-                                .insert(Name::new("Tile: Bomb face"));
-                        });
-                    }
-                    Tile::BombNeighbor(count) => {
-                        cmd.insert(BombNeighbor { count: *count });
-                        cmd.with_children(|parent| {
-                            parent
-                                .spawn(Self::bomb_count_text_bundle(
-                                    *count,
-                                    board_assets,
-                                ))
-                                .insert(Name::new("Tile: Neighbor face"));
-                        });
-                    }
-                    _ => (),
-                }
+                spawn_tile_face(&mut cmd, *tile, size, padding, board_assets);
+            }
+        }
+    }
+
+    /// Striped strips outside the edges of a wrapping board. Every tile of
+    /// an edge gets a stripe, the tile it touches across the seam has a
+    /// stripe of the same color on the opposite edge.
+    fn spawn_wrap_hints(parent: &mut ChildBuilder, (width, height): (u16, u16), size: f32) {
+        let thickness = size / 4.;
+        let gap = 2.;
+        let stripe = |color: Color, index: u16| {
+            let alpha = if index % 2 == 0 { 0.9 } else { 0.5 };
+            color.with_a(alpha)
+        };
+
+        for y in 0..height {
+            let center = y as f32 * size + size / 2.;
+            for x in [-gap - thickness / 2., width as f32 * size + gap + thickness / 2.] {
+                parent
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: stripe(WRAP_HINT_COLORS[0], y),
+                            custom_size: Some(Vec2::new(thickness, size)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(x, center, 0.),
+                        ..default()
+                    })
+                    .insert(Name::new("Wrap hint"))
+                    .insert(WrapHint);
+            }
+        }
+        for x in 0..width {
+            let center = x as f32 * size + size / 2.;
+            for y in [-gap - thickness / 2., height as f32 * size + gap + thickness / 2.] {
+                parent
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: stripe(WRAP_HINT_COLORS[1], x),
+                            custom_size: Some(Vec2::new(size, thickness)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(center, y, 0.),
+                        ..default()
+                    })
+                    .insert(Name::new("Wrap hint"))
+                    .insert(WrapHint);
             }
         }
     }
 
-    fn cleanup_board(board: Res<Board>, mut commands: Commands) {
+    fn cleanup_board(
+        board: Res<Board>,
+        playback: Option<Res<ReplayPlayback>>,
+        end_screens: Query<Entity, With<EndScreen>>,
+        playback_controls: Query<Entity, With<PlaybackControls>>,
+        lives_displays: Query<Entity, With<LivesDisplay>>,
+        hint_displays: Query<Entity, With<HintDisplay>>,
+        mut commands: Commands,
+    ) {
         info!("Cleaning");
         commands.entity(board.entity).despawn_recursive();
+        for entity in end_screens
+            .iter()
+            .chain(playback_controls.iter())
+            .chain(lives_displays.iter())
+            .chain(hint_displays.iter())
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+        // Leaving the board ends the playback, unless it is being rebuilt
+        if !playback.is_some_and(|playback| playback.is_rebuilding()) {
+            commands.remove_resource::<ReplayPlayback>();
+        }
         commands.remove_resource::<Board>();
+        commands.remove_resource::<VersusState>();
+        commands.remove_resource::<GameSummary>();
+        commands.remove_resource::<ReplayRecorder>();
+        commands.remove_resource::<ClickCounter>();
+        commands.remove_resource::<UndoHistory>();
+        commands.remove_resource::<HintCounter>();
+        commands.remove_resource::<HintSearch>();
+    }
+}
+
+fn bomb_count_sprite_bundle(count: u8, board_assets: &BoardAssets) -> Option<SpriteBundle> {
+    let asset = match count {
+        1 => board_assets.material_1.texture.clone(),
+        2 => board_assets.material_2.texture.clone(),
+        3 => board_assets.material_3.texture.clone(),
+        _ => return None,
+    };
+
+    Some(SpriteBundle {
+        sprite: Sprite {
+            ..default()
+        },
+        transform: Transform::from_xyz(0., 0., 2.),
+        texture: asset,
+        ..default()
+    })
+}
+
+/// Counts without a dedicated sprite are written out with the board font,
+/// two digit counts of layered boards in a smaller size to fit the tile
+fn bomb_count_text_bundle(count: u8, size: f32, board_assets: &BoardAssets) -> Text2dBundle {
+    let scale = if count < 10 { 0.8 } else { 0.55 };
+    Text2dBundle {
+        text: Text::from_section(
+            count.to_string(),
+            TextStyle {
+                font: board_assets.font.clone(),
+                font_size: size * scale,
+                color: *board_assets.bomb_counter_color(count),
+            },
+        )
+        .with_alignment(TextAlignment::Center),
+        transform: Transform::from_xyz(0., 0., 2.),
+        ..default()
+    }
+}
+
+/// Spawns the face revealed once the tile is uncovered and tags the tile
+/// with its `Bomb`/`BombNeighbor` component
+pub(crate) fn spawn_tile_face(
+    cmd: &mut EntityCommands,
+    tile: Tile,
+    size: f32,
+    padding: f32,
+    board_assets: &BoardAssets,
+) {
+    match tile {
+        Tile::Bomb(mines) => {
+            cmd.insert(Bomb);
+            cmd.with_children(|parent| {
+                parent
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(size - padding)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0., 0., 1.),
+                        texture: board_assets.bomb_material.texture.clone(),
+                        ..default()
+                    })
+                    .insert(Name::new("Tile: Bomb face"));
+                // Cells holding several mines show how many
+                if mines > 1 {
+                    let mut count = bomb_count_text_bundle(mines, size / 2., board_assets);
+                    count.transform = Transform::from_xyz(size / 4., -size / 4., 2.);
+                    parent.spawn(count).insert(Name::new("Tile: Bomb count"));
+                }
+            });
+        }
+        Tile::BombNeighbor(count) => {
+            cmd.insert(BombNeighbor { count });
+            cmd.with_children(|parent| {
+                let mut face = match bomb_count_sprite_bundle(count, board_assets) {
+                    Some(sprite) => parent.spawn(sprite),
+                    None => parent.spawn(bomb_count_text_bundle(count, size, board_assets)),
+                };
+                face.insert(Name::new("Tile: Neighbor face"));
+            });
+        }
+        _ => (),
     }
 }
@@ -0,0 +1,397 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::components::{Coordinates, PlayerId};
+use crate::net::{BoardDiff, GameOutcome, TileReveal, Welcome};
+use crate::resources::cells::{CellMap, CellSet};
+use crate::resources::game_summary::GameSummary;
+use crate::resources::mask::BoardMask;
+use crate::resources::tile_map::TileMap;
+use crate::resources::versus::{VersusOutcome, VersusState};
+use crate::resources::{BoardOptions, BoardShape, GameRules, Neighborhood};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CommandError {
+    OutOfBounds,
+    GameOver,
+    AlreadyRevealed,
+    Flagged,
+    NotYourTurn,
+    ChordDisabled,
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            CommandError::OutOfBounds => "coordinates are outside of the board",
+            CommandError::GameOver => "the game is over",
+            CommandError::AlreadyRevealed => "tile is already revealed",
+            CommandError::Flagged => "tile is flagged",
+            CommandError::NotYourTurn => "it is not your turn",
+            CommandError::ChordDisabled => "chording is disabled in versus games",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl Error for CommandError {}
+
+/// Authoritative board state of a networked game. It owns the hidden layout
+/// and validates every command before turning it into a [`BoardDiff`].
+#[derive(Debug, Clone)]
+pub struct Authority {
+    pub tile_map: TileMap,
+    pub rules: GameRules,
+    pub shape: BoardShape,
+    pub wrap: bool,
+    pub neighborhood: Neighborhood,
+    pub mask: Option<BoardMask>,
+    pub coordinates_discovered: CellSet,
+    pub coordinates_marked: CellMap<PlayerId>,
+    pub revealed_by: CellMap<PlayerId>,
+    pub versus: Option<VersusState>,
+    pub outcome: Option<GameOutcome>,
+}
+
+impl Authority {
+    pub fn new(options: &BoardOptions) -> Self {
+        let tile_map = TileMap::from_options(options);
+
+        let (width, height) = (tile_map.width, tile_map.height);
+        let mut coordinates_discovered = CellSet::new(width, height);
+        if let Some(start) = options.start_coordinates(&tile_map) {
+            coordinates_discovered.extend(tile_map.flood_fill(start, |_| false));
+        }
+
+        Self {
+            tile_map,
+            rules: options.rules,
+            shape: options.shape,
+            wrap: options.wrap,
+            neighborhood: options.neighborhood,
+            mask: options.mask.clone(),
+            coordinates_discovered,
+            coordinates_marked: CellMap::new(width, height),
+            revealed_by: CellMap::new(width, height),
+            versus: (options.rules == GameRules::Versus).then(|| VersusState::new(false)),
+            outcome: None,
+        }
+    }
+
+    pub fn welcome(&self, player: PlayerId) -> Welcome {
+        Welcome {
+            player,
+            width: self.tile_map.width / self.tile_map.topology.layers(),
+            height: self.tile_map.height,
+            bomb_count: self.tile_map.bomb_count,
+            rules: self.rules,
+            shape: self.shape,
+            wrap: self.wrap,
+            neighborhood: self.neighborhood,
+            layers: self.tile_map.topology.layers(),
+            mask: self.mask.clone(),
+        }
+    }
+
+    /// Everything a freshly joined client needs to catch up
+    pub fn snapshot(&self) -> BoardDiff {
+        BoardDiff {
+            revealed: self
+                .coordinates_discovered
+                .iter()
+                .map(|coordinates| self.tile_reveal(coordinates))
+                .collect(),
+            marked: self.coordinates_marked.iter().collect(),
+            unmarked: Vec::new(),
+            versus: self.versus.clone(),
+            outcome: self.outcome,
+        }
+    }
+
+    pub fn reveal(
+        &mut self,
+        player: PlayerId,
+        coordinates: Coordinates,
+    ) -> Result<BoardDiff, CommandError> {
+        self.validate(player, coordinates)?;
+        if self.coordinates_marked.contains_key(&coordinates) {
+            return Err(CommandError::Flagged);
+        }
+
+        let uncovered = if self.tile_map.is_bomb_at(coordinates) {
+            match self.versus.as_mut() {
+                Some(versus) => {
+                    self.outcome = match versus.score_mine(self.tile_map.mine_cells) {
+                        Some(VersusOutcome::Won(player)) => Some(GameOutcome::VersusWon(player)),
+                        Some(VersusOutcome::Draw) => Some(GameOutcome::VersusDraw),
+                        None => None,
+                    };
+                }
+                None => {
+                    self.outcome = Some(GameOutcome::Exploded {
+                        player,
+                        coordinates,
+                    });
+                }
+            }
+            vec![coordinates]
+        } else {
+            if let Some(versus) = self.versus.as_mut() {
+                versus.pass_turn();
+            }
+            let marked = &self.coordinates_marked;
+            self.tile_map
+                .flood_fill(coordinates, |coordinates| marked.contains_key(coordinates))
+        };
+
+        let mut diff = BoardDiff::default();
+        for coordinates in uncovered {
+            if self.coordinates_discovered.insert(coordinates) {
+                self.revealed_by.insert(coordinates, player);
+                diff.revealed.push(self.tile_reveal(coordinates));
+            }
+        }
+
+        if self.outcome.is_none() && self.versus.is_none() && self.is_cleared() {
+            self.outcome = Some(GameOutcome::Cleared);
+        }
+
+        diff.versus = self.versus.clone();
+        diff.outcome = self.outcome;
+        Ok(diff)
+    }
+
+    /// Reveals the covered neighbors of a revealed number whose flags are all
+    /// placed, stopping at the first mine
+    pub fn chord(
+        &mut self,
+        player: PlayerId,
+        coordinates: Coordinates,
+    ) -> Result<BoardDiff, CommandError> {
+        if self.versus.is_some() {
+            return Err(CommandError::ChordDisabled);
+        }
+        if self.outcome.is_some() {
+            return Err(CommandError::GameOver);
+        }
+
+        let targets = self.tile_map.chord_targets(
+            coordinates,
+            |coordinates| self.coordinates_discovered.contains(coordinates),
+            |coordinates| self.coordinates_marked.contains_key(coordinates) as u8,
+        );
+        let mut diff = BoardDiff::default();
+        for target in targets {
+            // Earlier targets may have flooded this one already
+            if let Ok(target_diff) = self.reveal(player, target) {
+                diff.revealed.extend(target_diff.revealed);
+                diff.outcome = target_diff.outcome;
+            }
+        }
+        Ok(diff)
+    }
+
+    pub fn toggle_flag(
+        &mut self,
+        player: PlayerId,
+        coordinates: Coordinates,
+    ) -> Result<BoardDiff, CommandError> {
+        self.validate(player, coordinates)?;
+
+        let mut diff = BoardDiff::default();
+        // Anyone may lift a flag, the new one belongs to whoever placed it
+        if self.coordinates_marked.remove(&coordinates).is_some() {
+            diff.unmarked.push(coordinates);
+        } else {
+            self.coordinates_marked.insert(coordinates, player);
+            diff.marked.push((coordinates, player));
+        }
+        Ok(diff)
+    }
+
+    /// Per player report of a finished shared game, versus games are settled
+    /// by their score instead
+    pub fn summary(&self) -> Option<GameSummary> {
+        let exploded_by = match self.outcome? {
+            GameOutcome::Cleared => None,
+            GameOutcome::Exploded { player, .. } => Some(player),
+            GameOutcome::VersusWon(_) | GameOutcome::VersusDraw => return None,
+        };
+
+        Some(GameSummary::new(
+            &self.tile_map,
+            self.revealed_by.iter(),
+            self.coordinates_marked.iter(),
+            exploded_by,
+        ))
+    }
+
+    fn validate(&self, player: PlayerId, coordinates: Coordinates) -> Result<(), CommandError> {
+        if self.outcome.is_some() {
+            return Err(CommandError::GameOver);
+        }
+        if !self.tile_map.contains(coordinates) {
+            return Err(CommandError::OutOfBounds);
+        }
+        if self.coordinates_discovered.contains(&coordinates) {
+            return Err(CommandError::AlreadyRevealed);
+        }
+        match &self.versus {
+            Some(versus) if versus.current != player => Err(CommandError::NotYourTurn),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_cleared(&self) -> bool {
+        self.coordinates_discovered.len() == self.tile_map.safe_count()
+    }
+
+    fn tile_reveal(&self, coordinates: Coordinates) -> TileReveal {
+        TileReveal {
+            coordinates,
+            // Only revealed, hence in-bounds, coordinates end up here
+            tile: self.tile_map.tile_at(coordinates).unwrap(),
+            player: self.revealed_by.get(&coordinates),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The top row comes first: mines at (0, 2) and (3, 0)
+    const GRID: &str = "*...\n....\n...*\n";
+
+    fn authority(rules: GameRules) -> Authority {
+        let tile_map: TileMap = GRID.parse().unwrap();
+        let mut options = BoardOptions {
+            rules,
+            ..Default::default()
+        };
+        options.set_layout(&tile_map);
+        Authority::new(&options)
+    }
+
+    fn at(x: u16, y: u16) -> Coordinates {
+        Coordinates { x, y }
+    }
+
+    #[test]
+    fn reveal_floods_and_credits_the_player() {
+        let mut authority = authority(GameRules::Classic);
+        let diff = authority.reveal(PlayerId(1), at(0, 0)).unwrap();
+
+        let mut revealed: Vec<_> = diff.revealed.iter().map(|r| r.coordinates).collect();
+        revealed.sort_by_key(|c| (c.y, c.x));
+        assert_eq!(
+            revealed,
+            vec![at(0, 0), at(1, 0), at(2, 0), at(0, 1), at(1, 1), at(2, 1)]
+        );
+        assert!(diff
+            .revealed
+            .iter()
+            .all(|r| r.player == Some(PlayerId(1)) && !r.tile.is_bomb()));
+        assert!(diff.outcome.is_none());
+        assert_eq!(authority.snapshot().revealed.len(), 6);
+    }
+
+    #[test]
+    fn invalid_commands_are_refused() {
+        let mut authority = authority(GameRules::Classic);
+        authority.reveal(PlayerId(0), at(0, 0)).unwrap();
+
+        assert_eq!(
+            authority.reveal(PlayerId(0), at(4, 0)).unwrap_err(),
+            CommandError::OutOfBounds
+        );
+        assert_eq!(
+            authority.reveal(PlayerId(0), at(1, 1)).unwrap_err(),
+            CommandError::AlreadyRevealed
+        );
+        assert_eq!(
+            authority.toggle_flag(PlayerId(0), at(1, 1)).unwrap_err(),
+            CommandError::AlreadyRevealed
+        );
+
+        authority.toggle_flag(PlayerId(0), at(3, 0)).unwrap();
+        assert_eq!(
+            authority.reveal(PlayerId(1), at(3, 0)).unwrap_err(),
+            CommandError::Flagged
+        );
+    }
+
+    #[test]
+    fn flags_toggle_into_diffs() {
+        let mut authority = authority(GameRules::Classic);
+        let diff = authority.toggle_flag(PlayerId(2), at(3, 0)).unwrap();
+        assert_eq!(diff.marked, vec![(at(3, 0), PlayerId(2))]);
+        assert!(diff.unmarked.is_empty());
+
+        // Anyone may lift it
+        let diff = authority.toggle_flag(PlayerId(0), at(3, 0)).unwrap();
+        assert!(diff.marked.is_empty());
+        assert_eq!(diff.unmarked, vec![at(3, 0)]);
+        assert!(authority.snapshot().marked.is_empty());
+    }
+
+    #[test]
+    fn mines_end_the_game_and_clearing_wins_it() {
+        let mut authority = authority(GameRules::Classic);
+        let diff = authority.reveal(PlayerId(1), at(3, 0)).unwrap();
+        assert!(matches!(
+            diff.outcome,
+            Some(GameOutcome::Exploded { player: PlayerId(1), coordinates }) if coordinates == at(3, 0)
+        ));
+        assert_eq!(
+            authority.reveal(PlayerId(1), at(0, 0)).unwrap_err(),
+            CommandError::GameOver
+        );
+
+        let mut authority = authority_cleared();
+        assert!(matches!(authority.outcome, Some(GameOutcome::Cleared)));
+        assert!(authority.summary().is_some());
+        assert_eq!(
+            authority.toggle_flag(PlayerId(0), at(3, 0)).unwrap_err(),
+            CommandError::GameOver
+        );
+        assert_eq!(
+            authority.chord(PlayerId(0), at(2, 0)).unwrap_err(),
+            CommandError::GameOver
+        );
+    }
+
+    fn authority_cleared() -> Authority {
+        let mut authority = authority(GameRules::Classic);
+        authority.reveal(PlayerId(0), at(0, 0)).unwrap();
+        let diff = authority.reveal(PlayerId(0), at(2, 2)).unwrap();
+        assert!(matches!(diff.outcome, Some(GameOutcome::Cleared)));
+        authority
+    }
+
+    #[test]
+    fn versus_turns_are_enforced() {
+        let mut authority = authority(GameRules::Versus);
+        assert_eq!(
+            authority.reveal(PlayerId(1), at(0, 0)).unwrap_err(),
+            CommandError::NotYourTurn
+        );
+        assert_eq!(
+            authority.chord(PlayerId(0), at(0, 0)).unwrap_err(),
+            CommandError::ChordDisabled
+        );
+
+        // A safe tile passes the turn, a found mine keeps it
+        let diff = authority.reveal(PlayerId(0), at(0, 0)).unwrap();
+        assert_eq!(diff.versus.unwrap().current, PlayerId(1));
+        assert_eq!(
+            authority.reveal(PlayerId(0), at(2, 2)).unwrap_err(),
+            CommandError::NotYourTurn
+        );
+        let diff = authority.reveal(PlayerId(1), at(3, 0)).unwrap();
+        let versus = diff.versus.unwrap();
+        assert_eq!(versus.current, PlayerId(1));
+        assert_eq!(versus.score(PlayerId(1)), 1);
+        assert!(diff.outcome.is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::{self, ErrorKind, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+use bevy::prelude::Resource;
+
+use crate::net::{encode_message, ClientMessage, MessageReader, ServerMessage, Welcome};
+
+/// Connection to an authority server. While this resource exists the board
+/// forwards clicks to the server and only applies the diffs it sends back.
+#[derive(Debug, Resource)]
+pub struct NetworkClient {
+    pub welcome: Welcome,
+    stream: TcpStream,
+    reader: MessageReader,
+    /// Bytes the socket had no room for yet, sent on the next flush
+    outgoing: Vec<u8>,
+    connected: bool,
+}
+
+impl NetworkClient {
+    /// Connects to the server and waits for its welcome message
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        let mut reader = MessageReader::default();
+
+        let welcome = match reader.next_blocking(&mut stream)? {
+            ServerMessage::Welcome(welcome) => welcome,
+            message => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected a welcome message, got {:?}", message),
+                ))
+            }
+        };
+        if let Some(mask) = &welcome.mask {
+            mask.check_size((welcome.width, welcome.height))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+
+        Ok(Self {
+            welcome,
+            stream,
+            reader,
+            outgoing: Vec::new(),
+            connected: true,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Queues `message` and sends as much of the queue as the socket takes
+    pub fn send(&mut self, message: ClientMessage) -> io::Result<()> {
+        self.outgoing.extend(encode_message(&message)?);
+        self.flush()
+    }
+
+    /// Sends queued bytes until the socket would block
+    pub fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => {
+                    self.connected = false;
+                    return Err(ErrorKind::WriteZero.into());
+                }
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.connected = false;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Messages received since the last poll. Queued messages are sent
+    /// first.
+    pub fn poll(&mut self) -> io::Result<Vec<ServerMessage>> {
+        self.flush()?;
+        let result = self.reader.poll(&mut self.stream);
+        if result.is_err() {
+            self.connected = false;
+        }
+        result
+    }
+}
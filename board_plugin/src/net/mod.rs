@@ -0,0 +1,7 @@
+pub use authority::*;
+pub use client::*;
+pub use protocol::*;
+
+mod authority;
+mod client;
+mod protocol;
@@ -0,0 +1,244 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Coordinates, PlayerId};
+use crate::resources::game_summary::GameSummary;
+use crate::resources::mask::BoardMask;
+use crate::resources::tile::Tile;
+use crate::resources::versus::VersusState;
+use crate::resources::{single_layer, BoardOptions, BoardShape, GameRules, Neighborhood};
+
+pub const DEFAULT_PORT: u16 = 7878;
+/// Longest command line the server accepts, commands are a few dozen bytes
+pub const MAX_COMMAND_LINE: usize = 64 * 1024;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    Reveal(Coordinates),
+    ToggleFlag(Coordinates),
+    Chord(Coordinates),
+    /// Asks the server for a snapshot of the whole board
+    Sync,
+    /// Tile under the player's mouse, shared with the other players
+    Cursor(Option<Coordinates>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Welcome(Welcome),
+    Diff(BoardDiff),
+    Rejected(String),
+    Cursor {
+        player: PlayerId,
+        coordinates: Option<Coordinates>,
+    },
+    Summary(GameSummary),
+}
+
+/// First message of every connection, describing the game being joined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Welcome {
+    pub player: PlayerId,
+    pub width: u16,
+    pub height: u16,
+    pub bomb_count: u16,
+    pub rules: GameRules,
+    #[serde(default)]
+    pub shape: BoardShape,
+    #[serde(default)]
+    pub wrap: bool,
+    #[serde(default)]
+    pub neighborhood: Neighborhood,
+    #[serde(default = "single_layer")]
+    pub layers: u16,
+    #[serde(default)]
+    pub mask: Option<BoardMask>,
+}
+
+impl Welcome {
+    pub fn apply_to(&self, options: &mut BoardOptions) {
+        options.map_size = (self.width, self.height);
+        options.bomb_count = self.bomb_count;
+        options.rules = self.rules;
+        options.shape = self.shape;
+        options.wrap = self.wrap;
+        options.neighborhood = self.neighborhood;
+        options.layers = self.layers;
+        options.mask = self.mask.clone();
+        // Network games hold one mine per cell and end on the first mine
+        options.cell_mines = 1;
+        options.lives = 1;
+        options.practice = false;
+        // The server already revealed the opening
+        options.safe_start = false;
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GameOutcome {
+    Cleared,
+    Exploded {
+        player: PlayerId,
+        coordinates: Coordinates,
+    },
+    VersusWon(PlayerId),
+    /// Every mine was found and the scores are level
+    VersusDraw,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct TileReveal {
+    pub coordinates: Coordinates,
+    pub tile: Tile,
+    /// `None` for tiles revealed on the board's behalf, like the opening
+    pub player: Option<PlayerId>,
+}
+
+/// Coordinate based board changes broadcast by the server
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BoardDiff {
+    pub revealed: Vec<TileReveal>,
+    pub marked: Vec<(Coordinates, PlayerId)>,
+    pub unmarked: Vec<Coordinates>,
+    pub versus: Option<VersusState>,
+    pub outcome: Option<GameOutcome>,
+}
+
+/// A message as the line sent over the wire
+pub fn encode_message<T: Serialize>(message: &T) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+pub fn send_message<T: Serialize>(stream: &mut impl Write, message: &T) -> io::Result<()> {
+    stream.write_all(&encode_message(message)?)
+}
+
+/// Splits a byte stream into newline delimited JSON messages. Partial lines
+/// are kept between reads so it works on non-blocking sockets.
+#[derive(Debug, Default)]
+pub struct MessageReader {
+    buffer: Vec<u8>,
+    /// Longest line accepted, `None` for no limit
+    max_line: Option<usize>,
+}
+
+impl MessageReader {
+    /// Reader failing with `InvalidData` once a line grows past `max_line`
+    /// bytes, for streams the other end can't be trusted with
+    pub fn with_max_line(max_line: usize) -> Self {
+        Self {
+            buffer: Vec::new(),
+            max_line: Some(max_line),
+        }
+    }
+
+    /// Waits until a complete message is available
+    pub fn next_blocking<T: DeserializeOwned>(&mut self, stream: &mut impl Read) -> io::Result<T> {
+        loop {
+            if let Some(message) = self.pop()? {
+                return Ok(message);
+            }
+            self.read_chunk(stream)?;
+        }
+    }
+
+    /// Returns every complete message without blocking
+    pub fn poll<T: DeserializeOwned>(&mut self, stream: &mut impl Read) -> io::Result<Vec<T>> {
+        loop {
+            match self.read_chunk(stream) {
+                Ok(()) => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(message) = self.pop()? {
+            messages.push(message);
+        }
+        Ok(messages)
+    }
+
+    fn read_chunk(&mut self, stream: &mut impl Read) -> io::Result<()> {
+        let mut chunk = [0; 4096];
+        loop {
+            match stream.read(&mut chunk) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => {
+                    self.buffer.extend_from_slice(&chunk[..n]);
+                    return self.check_line_length();
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Fails if the line still waiting for its newline is too long
+    fn check_line_length(&self) -> io::Result<()> {
+        let max_line = match self.max_line {
+            Some(max_line) => max_line,
+            None => return Ok(()),
+        };
+        let start = self
+            .buffer
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |end| end + 1);
+        if self.buffer.len() - start > max_line {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("message longer than {} bytes", max_line),
+            ));
+        }
+        Ok(())
+    }
+
+    fn pop<T: DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+        match self.buffer.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                Ok(Some(serde_json::from_slice(&line)?))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_are_split_on_newlines() {
+        let mut bytes = encode_message(&ClientMessage::Sync).unwrap();
+        bytes.extend(encode_message(&ClientMessage::Cursor(None)).unwrap());
+        let mut reader = MessageReader::with_max_line(MAX_COMMAND_LINE);
+        let mut stream = bytes.as_slice();
+        assert!(matches!(
+            reader.next_blocking(&mut stream).unwrap(),
+            ClientMessage::Sync
+        ));
+        assert!(matches!(
+            reader.next_blocking(&mut stream).unwrap(),
+            ClientMessage::Cursor(None)
+        ));
+    }
+
+    #[test]
+    fn endless_lines_are_cut_off() {
+        let bytes = vec![b' '; 4 * MAX_COMMAND_LINE];
+        let mut stream = bytes.as_slice();
+        let mut reader = MessageReader::with_max_line(MAX_COMMAND_LINE);
+        let error = reader
+            .next_blocking::<ClientMessage>(&mut stream)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        // It gave up without reading the rest
+        assert!(!stream.is_empty());
+    }
+}
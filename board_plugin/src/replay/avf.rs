@@ -0,0 +1,285 @@
+//! Minesweeper Arbiter's binary `.avf` videos.
+//!
+//! The layout is a version byte, four reserved bytes, the level (3 to 5 for
+//! the standard levels, 6 for custom ones followed by width - 1, height - 1
+//! and the mine count as big endian u16), then one `(row, column)` byte
+//! pair per mine, both starting at 1. A bracketed text header follows,
+//! directly followed by 8 byte mouse event records:
+//! `[kind, x_hi, hundredths, x_lo, seconds_lo, y_hi, seconds_hi, y_lo]`
+//! where the stored seconds are one more than the elapsed ones. Arbiter's
+//! trailing metadata (skin, checksum) is ignored when reading and not
+//! written, so exported files play in community viewers but are not
+//! accepted as signed Arbiter results.
+
+use std::io::{Read, Write};
+
+use crate::components::Coordinates;
+use crate::replay::mouse::{self, MouseEvent, MouseEventKind};
+use crate::replay::{Replay, ReplayError};
+use crate::resources::BoardOptions;
+
+pub const AVF_EXTENSION: &str = "avf";
+
+const AVF_VERSION: u8 = 3;
+const CUSTOM_LEVEL: u8 = 6;
+const LEVELS: [(u8, (u16, u16, u16)); 3] = [(3, (8, 8, 10)), (4, (16, 16, 40)), (5, (30, 16, 99))];
+
+fn event_code(kind: MouseEventKind) -> u8 {
+    match kind {
+        MouseEventKind::Move => 1,
+        MouseEventKind::LeftPress => 3,
+        MouseEventKind::LeftRelease => 5,
+        MouseEventKind::RightPress => 9,
+        MouseEventKind::RightRelease => 17,
+        MouseEventKind::MiddlePress => 33,
+        MouseEventKind::MiddleRelease => 65,
+    }
+}
+
+fn event_kind(code: u8) -> Option<MouseEventKind> {
+    let kind = match code {
+        1 => MouseEventKind::Move,
+        3 => MouseEventKind::LeftPress,
+        5 | 21 => MouseEventKind::LeftRelease,
+        9 => MouseEventKind::RightPress,
+        17 | 145 => MouseEventKind::RightRelease,
+        33 => MouseEventKind::MiddlePress,
+        65 | 193 => MouseEventKind::MiddleRelease,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+fn decode_event(record: &[u8]) -> Option<MouseEvent> {
+    let kind = event_kind(record[0])?;
+    let seconds = u16::from_be_bytes([record[6], record[4]]).checked_sub(1)?;
+    if record[2] > 99 {
+        return None;
+    }
+    Some(MouseEvent {
+        time: seconds as u32 * 1000 + record[2] as u32 * 10,
+        kind,
+        x: u16::from_be_bytes([record[1], record[3]]),
+        y: u16::from_be_bytes([record[5], record[7]]),
+    })
+}
+
+fn encode_event(event: &MouseEvent) -> [u8; 8] {
+    let [x_hi, x_lo] = event.x.to_be_bytes();
+    let [y_hi, y_lo] = event.y.to_be_bytes();
+    let [seconds_hi, seconds_lo] = ((event.time / 1000) as u16 + 1).to_be_bytes();
+    let hundredths = ((event.time % 1000) / 10) as u8;
+    [
+        event_code(event.kind),
+        x_hi,
+        hundredths,
+        x_lo,
+        seconds_lo,
+        y_hi,
+        seconds_hi,
+        y_lo,
+    ]
+}
+
+impl Replay {
+    pub fn read_avf(mut reader: impl Read) -> Result<Self, ReplayError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let corrupted = || ReplayError::Format("truncated avf file".to_string());
+
+        let level = *data.get(5).ok_or_else(corrupted)?;
+        let mut offset = 6;
+        let (width, height, mine_count) = match LEVELS.iter().find(|(l, _)| *l == level) {
+            Some((_, size)) => *size,
+            None if level == CUSTOM_LEVEL => {
+                let header = data.get(6..10).ok_or_else(corrupted)?;
+                offset = 10;
+                (
+                    header[0] as u16 + 1,
+                    header[1] as u16 + 1,
+                    u16::from_be_bytes([header[2], header[3]]),
+                )
+            }
+            None => return Err(ReplayError::Format(format!("unknown avf level {}", level))),
+        };
+
+        let mine_bytes = data
+            .get(offset..offset + mine_count as usize * 2)
+            .ok_or_else(corrupted)?;
+        let mut mines = Vec::with_capacity(mine_count as usize);
+        for pair in mine_bytes.chunks(2) {
+            let (row, column) = (pair[0] as u16, pair[1] as u16);
+            if row == 0 || column == 0 || row > height || column > width {
+                return Err(ReplayError::Format("mine outside of the board".to_string()));
+            }
+            mines.push(Coordinates {
+                x: column - 1,
+                y: height - row,
+            });
+        }
+        offset += mine_bytes.len();
+
+        // The bracketed text header comes right after the mines and the
+        // event records right after the header
+        if data.get(offset) != Some(&b'[') {
+            return Err(ReplayError::Format("missing avf header".to_string()));
+        }
+        let start = data[offset..]
+            .iter()
+            .position(|byte| *byte == b']')
+            .map(|position| offset + position + 1)
+            .ok_or_else(corrupted)?;
+        let events: Vec<MouseEvent> = data[start..]
+            .chunks_exact(8)
+            .map_while(decode_event)
+            .collect();
+        if events.is_empty() {
+            return Err(ReplayError::Format("no mouse events".to_string()));
+        }
+
+        Ok(Replay {
+            options: BoardOptions {
+                map_size: (width, height),
+                bomb_count: mines.len() as u16,
+                safe_start: false,
+                ..Default::default()
+            },
+            actions: mouse::to_actions(&events, (width, height)),
+            mines,
+        })
+    }
+
+    pub fn write_avf(&self, mut writer: impl Write) -> Result<(), ReplayError> {
+        let (width, height) = self.options.map_size;
+        let mine_count = self.mines.len() as u16;
+        if width > 256 || height > 256 {
+            return Err(ReplayError::Format("board too large for avf".to_string()));
+        }
+
+        let mut data = vec![AVF_VERSION, 0, 0, 0, 0];
+        match LEVELS
+            .iter()
+            .find(|(_, size)| *size == (width, height, mine_count))
+        {
+            Some((level, _)) => data.push(*level),
+            None => {
+                data.extend([CUSTOM_LEVEL, (width - 1) as u8, (height - 1) as u8]);
+                data.extend(mine_count.to_be_bytes());
+            }
+        }
+        for mine in self.mines.iter() {
+            data.extend([(height - mine.y) as u8, (mine.x + 1) as u8]);
+        }
+        data.extend(b"[0|RustySweeper]");
+        for event in mouse::from_actions(&self.actions, height) {
+            data.extend(encode_event(&event));
+        }
+
+        writer.write_all(&data)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{ActionKind, ReplayAction};
+
+    fn replay(map_size: (u16, u16), mines: Vec<Coordinates>) -> Replay {
+        let action = |time, kind, x, y| ReplayAction {
+            time,
+            kind,
+            coordinates: Coordinates { x, y },
+            cursor: None,
+        };
+        Replay {
+            options: BoardOptions {
+                map_size,
+                bomb_count: mines.len() as u16,
+                ..Default::default()
+            },
+            mines,
+            actions: vec![
+                action(0, ActionKind::Reveal, 4, 0),
+                action(730, ActionKind::Flag, 0, 3),
+                action(1250, ActionKind::Chord, 1, 2),
+                action(12340, ActionKind::Reveal, 2, 1),
+            ],
+        }
+    }
+
+    fn assert_same_game(read: &Replay, written: &Replay) {
+        assert_eq!(read.options.map_size, written.options.map_size);
+        assert_eq!(read.mines, written.mines);
+        let actions = |replay: &Replay| -> Vec<_> {
+            replay
+                .actions
+                .iter()
+                .map(|action| (action.time, action.kind, action.coordinates))
+                .collect()
+        };
+        assert_eq!(actions(read), actions(written));
+    }
+
+    fn encode(replay: &Replay) -> Vec<u8> {
+        let mut data = Vec::new();
+        replay.write_avf(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn custom_boards_round_trip() {
+        let mines = vec![Coordinates { x: 0, y: 3 }, Coordinates { x: 3, y: 0 }];
+        let replay = replay((5, 4), mines);
+        let data = encode(&replay);
+        assert_eq!(&data[5..10], &[CUSTOM_LEVEL, 4, 3, 0, 2]);
+
+        let read = Replay::read_avf(&data[..]).unwrap();
+        assert_same_game(&read, &replay);
+        // Cursors come back at the centers of the tiles
+        assert_eq!(read.actions[1].cursor, Some((0.5, 3.5)));
+    }
+
+    #[test]
+    fn standard_levels_round_trip() {
+        let mines = (0..10)
+            .map(|x| Coordinates {
+                x: x % 8,
+                y: 7 - x / 8,
+            })
+            .collect();
+        let replay = replay((8, 8), mines);
+        let data = encode(&replay);
+        assert_eq!(data[5], 3);
+
+        assert_same_game(&Replay::read_avf(&data[..]).unwrap(), &replay);
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        let data = encode(&replay((5, 4), vec![Coordinates { x: 1, y: 1 }]));
+
+        for length in [0, 5, 9, 11] {
+            assert!(matches!(
+                Replay::read_avf(&data[..length]),
+                Err(ReplayError::Format(_))
+            ));
+        }
+        // Header without its closing bracket
+        let header_end = data.iter().position(|byte| *byte == b']').unwrap();
+        assert!(Replay::read_avf(&data[..header_end]).is_err());
+        // Header but no events
+        assert!(Replay::read_avf(&data[..=header_end]).is_err());
+
+        let mut no_header = data.clone();
+        no_header[12] = b'x';
+        assert!(Replay::read_avf(&no_header[..]).is_err());
+
+        let mut unknown_level = data;
+        unknown_level[5] = 9;
+        assert!(Replay::read_avf(&unknown_level[..]).is_err());
+
+        assert!(Replay::read_avf(&b"not a video at all"[..]).is_err());
+    }
+}
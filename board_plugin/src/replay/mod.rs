@@ -0,0 +1,253 @@
+pub use avf::AVF_EXTENSION;
+pub use playback::*;
+pub use rawvf::RAWVF_EXTENSION;
+pub use recorder::*;
+
+mod avf;
+mod mouse;
+mod playback;
+mod rawvf;
+mod recorder;
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::Coordinates;
+use crate::resources::topology::TopologyKind;
+use crate::resources::BoardOptions;
+
+pub const REPLAY_MAGIC: &[u8; 4] = b"MSRP";
+/// Bumped whenever the encoded replay changes, `BoardOptions` included
+pub const REPLAY_VERSION: u16 = 5;
+pub const REPLAY_EXTENSION: &str = "msr";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ActionKind {
+    Reveal,
+    Flag,
+    Chord,
+    /// The player asked for a hint, which pointed at the action's tile
+    Hint,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ReplayAction {
+    /// Milliseconds since the board was created
+    pub time: u32,
+    pub kind: ActionKind,
+    pub coordinates: Coordinates,
+    /// Mouse position in tile units from the bottom left corner of the board
+    pub cursor: Option<(f32, f32)>,
+}
+
+/// A recorded game: everything needed to rebuild the board and every action
+/// the board processed, in order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub options: BoardOptions,
+    pub mines: Vec<Coordinates>,
+    pub actions: Vec<ReplayAction>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Corrupted(bincode::Error),
+    /// Malformed community video file
+    Format(String),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "{}", e),
+            ReplayError::InvalidMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay version {}", version)
+            }
+            ReplayError::Corrupted(e) => write!(f, "corrupted replay: {}", e),
+            ReplayError::Format(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for ReplayError {
+    fn from(e: bincode::Error) -> Self {
+        ReplayError::Corrupted(e)
+    }
+}
+
+impl Replay {
+    /// Milliseconds from board creation to the last action
+    pub fn duration(&self) -> u32 {
+        self.actions.last().map_or(0, |action| action.time)
+    }
+
+    /// Hints used during the game, assisted games can't be exported
+    pub fn hints(&self) -> usize {
+        self.actions
+            .iter()
+            .filter(|action| action.kind == ActionKind::Hint)
+            .count()
+    }
+
+    /// Writes the magic, the format version and the bincode encoded replay
+    pub fn write(&self, mut writer: impl Write) -> Result<(), ReplayError> {
+        writer.write_all(REPLAY_MAGIC)?;
+        writer.write_all(&REPLAY_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read(mut reader: impl Read) -> Result<Self, ReplayError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(ReplayError::InvalidMagic);
+        }
+
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        match u16::from_le_bytes(version) {
+            REPLAY_VERSION => Ok(bincode::deserialize_from(reader)?),
+            version => Err(ReplayError::UnsupportedVersion(version)),
+        }
+    }
+
+    /// Saves the replay in the format matching the file extension: `.avf`,
+    /// `.rawvf` or our own format for anything else
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let format = extension(path);
+        let foreign = matches!(format.as_deref(), Some(AVF_EXTENSION | RAWVF_EXTENSION));
+        let classic = self.options.topology() == TopologyKind::Square8
+            && self.options.mask.is_none()
+            && self.options.cell_mines == 1
+            && self.options.lives == 1;
+        if foreign && !classic {
+            return Err(ReplayError::Format(
+                "only classic rectangular boards can be exported".to_string(),
+            ));
+        }
+        if foreign && self.hints() > 0 {
+            return Err(ReplayError::Format(
+                "games played with hints can't be exported".to_string(),
+            ));
+        }
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        match format.as_deref() {
+            Some(AVF_EXTENSION) => self.write_avf(writer),
+            Some(RAWVF_EXTENSION) => {
+                writer.write_all(self.to_rawvf().as_bytes())?;
+                writer.flush()?;
+                Ok(())
+            }
+            _ => self.write(writer),
+        }
+    }
+
+    /// Loads a replay, picking the format from the file extension like `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            Some(AVF_EXTENSION) => Self::read_avf(BufReader::new(File::open(path)?)),
+            Some(RAWVF_EXTENSION) => Self::from_rawvf(&std::fs::read_to_string(path)?),
+            _ => Self::read(BufReader::new(File::open(path)?)),
+        }
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay() -> Replay {
+        let mine = Coordinates { x: 1, y: 2 };
+        Replay {
+            options: BoardOptions {
+                map_size: (9, 9),
+                bomb_count: 10,
+                seed: Some(7),
+                ..Default::default()
+            },
+            mines: vec![mine],
+            actions: vec![
+                ReplayAction {
+                    time: 1200,
+                    kind: ActionKind::Hint,
+                    coordinates: Coordinates { x: 0, y: 0 },
+                    cursor: None,
+                },
+                ReplayAction {
+                    time: 1500,
+                    kind: ActionKind::Flag,
+                    coordinates: mine,
+                    cursor: Some((1.5, 2.5)),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut bytes = Vec::new();
+        replay().write(&mut bytes).unwrap();
+        let read = Replay::read(bytes.as_slice()).unwrap();
+        assert_eq!(format!("{:?}", read), format!("{:?}", replay()));
+        assert_eq!(read.hints(), 1);
+    }
+
+    #[test]
+    fn assisted_games_are_not_exported() {
+        let path = std::env::temp_dir().join("assisted.avf");
+        assert!(matches!(replay().save(&path), Err(ReplayError::Format(_))));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn other_versions_are_rejected_before_decoding() {
+        let mut bytes = Vec::new();
+        replay().write(&mut bytes).unwrap();
+        bytes[4..6].copy_from_slice(&(REPLAY_VERSION - 1).to_le_bytes());
+        assert!(matches!(
+            Replay::read(bytes.as_slice()),
+            Err(ReplayError::UnsupportedVersion(version)) if version == REPLAY_VERSION - 1
+        ));
+        assert!(matches!(
+            Replay::read(&b"NOPE"[..]),
+            Err(ReplayError::InvalidMagic)
+        ));
+    }
+
+    /// A new size means the replay layout changed: bump `REPLAY_VERSION`
+    /// along with it
+    #[test]
+    fn options_layout_is_pinned() {
+        let size = bincode::serialized_size(&BoardOptions::default()).unwrap();
+        assert_eq!(size, 76);
+    }
+}
@@ -0,0 +1,138 @@
+//! Mouse event streams used by the community video formats, and their
+//! conversion from and to board actions
+
+use crate::components::Coordinates;
+use crate::replay::{ActionKind, ReplayAction};
+
+/// Cell size in pixels assumed by the community formats
+pub const CELL_PIXELS: u16 = 16;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MouseEventKind {
+    Move,
+    LeftPress,
+    LeftRelease,
+    RightPress,
+    RightRelease,
+    MiddlePress,
+    MiddleRelease,
+}
+
+/// Mouse event in pixels from the top left corner of the board
+#[derive(Debug, Copy, Clone)]
+pub struct MouseEvent {
+    /// Milliseconds since the first click
+    pub time: u32,
+    pub kind: MouseEventKind,
+    pub x: u16,
+    pub y: u16,
+}
+
+impl MouseEventKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MouseEventKind::Move => "mv",
+            MouseEventKind::LeftPress => "lc",
+            MouseEventKind::LeftRelease => "lr",
+            MouseEventKind::RightPress => "rc",
+            MouseEventKind::RightRelease => "rr",
+            MouseEventKind::MiddlePress => "mc",
+            MouseEventKind::MiddleRelease => "mr",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        let kind = match code {
+            "mv" => MouseEventKind::Move,
+            "lc" => MouseEventKind::LeftPress,
+            "lr" => MouseEventKind::LeftRelease,
+            "rc" => MouseEventKind::RightPress,
+            "rr" => MouseEventKind::RightRelease,
+            "mc" => MouseEventKind::MiddlePress,
+            "mr" => MouseEventKind::MiddleRelease,
+            _ => return None,
+        };
+        Some(kind)
+    }
+}
+
+/// Replays the clicks the way Arbiter and Viennasweeper interpret them:
+/// left release reveals, right press flags, and releasing either button
+/// while both are held (or releasing the middle one) chords
+pub fn to_actions(events: &[MouseEvent], (width, height): (u16, u16)) -> Vec<ReplayAction> {
+    let mut actions = Vec::new();
+    let (mut left, mut right) = (false, false);
+
+    for event in events {
+        let (column, row) = (event.x / CELL_PIXELS, event.y / CELL_PIXELS);
+        let kind = match event.kind {
+            MouseEventKind::Move => None,
+            MouseEventKind::LeftPress => {
+                left = true;
+                None
+            }
+            MouseEventKind::RightPress => {
+                right = true;
+                (!left).then_some(ActionKind::Flag)
+            }
+            MouseEventKind::LeftRelease => {
+                let chord = std::mem::take(&mut left) && right;
+                Some(if chord {
+                    ActionKind::Chord
+                } else {
+                    ActionKind::Reveal
+                })
+            }
+            MouseEventKind::RightRelease => {
+                let chord = std::mem::take(&mut right) && left;
+                // Releasing the right button first ends the chord for the left one too
+                left = false;
+                chord.then_some(ActionKind::Chord)
+            }
+            MouseEventKind::MiddlePress => None,
+            MouseEventKind::MiddleRelease => Some(ActionKind::Chord),
+        };
+
+        if let Some(kind) = kind.filter(|_| column < width && row < height) {
+            actions.push(ReplayAction {
+                time: event.time,
+                kind,
+                // Rows count from the top, our coordinates from the bottom
+                coordinates: Coordinates {
+                    x: column,
+                    y: height - 1 - row,
+                },
+                cursor: Some((
+                    event.x as f32 / CELL_PIXELS as f32,
+                    height as f32 - event.y as f32 / CELL_PIXELS as f32,
+                )),
+            });
+        }
+    }
+    actions
+}
+
+/// A press and a release at the center of the tile for every action
+pub fn from_actions(actions: &[ReplayAction], height: u16) -> Vec<MouseEvent> {
+    actions
+        .iter()
+        .filter_map(|action| {
+            let (press, release) = match action.kind {
+                ActionKind::Reveal => (MouseEventKind::LeftPress, MouseEventKind::LeftRelease),
+                ActionKind::Flag => (MouseEventKind::RightPress, MouseEventKind::RightRelease),
+                ActionKind::Chord => (MouseEventKind::MiddlePress, MouseEventKind::MiddleRelease),
+                // Not a click
+                ActionKind::Hint => return None,
+            };
+            let x = action.coordinates.x * CELL_PIXELS + CELL_PIXELS / 2;
+            let y = (height - 1 - action.coordinates.y) * CELL_PIXELS + CELL_PIXELS / 2;
+            Some([press, release].map(|kind| MouseEvent {
+                time: action.time,
+                kind,
+                x,
+                y,
+            }))
+        })
+        .flatten()
+        .collect()
+}
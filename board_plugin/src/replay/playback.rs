@@ -0,0 +1,289 @@
+use bevy::prelude::{Resource, Vec2};
+
+use crate::replay::{ActionKind, Replay, ReplayAction};
+
+pub const PLAYBACK_SPEEDS: [f32; 5] = [0.5, 1., 2., 4., 8.];
+
+/// Drives a recorded game: the board is rebuilt from the replay layout and
+/// the recorded actions are fed back to it as time passes
+#[derive(Debug, Clone, Resource)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    paused: bool,
+    /// Milliseconds since the board was created
+    position: f32,
+    speed: usize,
+    /// Index of the next action to feed to the board
+    next_action: usize,
+    /// Number of actions to apply while stepping, regardless of their time
+    step_limit: Option<usize>,
+    /// Set when seeking backwards, the board has to be rebuilt from scratch
+    rebuild: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            paused: false,
+            position: 0.,
+            speed: 1,
+            next_action: 0,
+            step_limit: None,
+            rebuild: false,
+        }
+    }
+
+    pub fn position(&self) -> u32 {
+        self.position as u32
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_limit = None;
+    }
+
+    pub fn speed(&self) -> f32 {
+        PLAYBACK_SPEEDS[self.speed]
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(PLAYBACK_SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    /// Playback progress in `[0, 1]`
+    pub fn progress(&self) -> f32 {
+        match self.replay.duration() {
+            0 => 1.,
+            duration => (self.position / duration as f32).min(1.),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next_action >= self.replay.actions.len()
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) {
+        if !self.paused && !self.is_finished() {
+            self.position += delta_seconds * 1000. * self.speed();
+        }
+    }
+
+    /// Actions due at the current position that the board can take in one
+    /// frame, in their recorded order. Reveals and flags go through separate
+    /// systems and a chord sees the board as the frame found it, so a batch
+    /// holds a single kind of move, at most one chord and each tile once.
+    /// The rest is handed out over the next frames.
+    pub fn due_actions(&mut self) -> Vec<ReplayAction> {
+        let mut batch: Vec<ReplayAction> = Vec::new();
+        while let Some(&action) = self.replay.actions.get(self.next_action) {
+            let due = match self.step_limit {
+                Some(limit) => self.next_action < limit,
+                None => action.time as f32 <= self.position,
+            };
+            if !due || !fits_batch(&batch, &action) {
+                break;
+            }
+            self.next_action += 1;
+            batch.push(action);
+        }
+        batch
+    }
+
+    pub fn seek(&mut self, time: u32) {
+        let applied = self
+            .next_action
+            .checked_sub(1)
+            .map(|i| self.replay.actions[i].time);
+        if applied.is_some_and(|applied| time < applied) {
+            self.next_action = 0;
+            self.rebuild = true;
+        }
+        self.position = time as f32;
+        self.step_limit = None;
+    }
+
+    pub fn seek_progress(&mut self, progress: f32) {
+        self.seek((progress.clamp(0., 1.) * self.replay.duration() as f32) as u32);
+    }
+
+    /// Pauses right after the next action
+    pub fn step_forward(&mut self) {
+        let target = self
+            .step_limit
+            .unwrap_or(self.next_action)
+            .max(self.next_action)
+            + 1;
+        self.step_to(target.min(self.replay.actions.len()));
+    }
+
+    /// Pauses right after the previous action
+    pub fn step_back(&mut self) {
+        let target = self
+            .step_limit
+            .unwrap_or(self.next_action)
+            .saturating_sub(1);
+        if target < self.next_action {
+            self.next_action = 0;
+            self.rebuild = true;
+        }
+        self.step_to(target);
+    }
+
+    /// Pauses once the first `count` actions are applied
+    fn step_to(&mut self, count: usize) {
+        self.paused = true;
+        self.step_limit = Some(count);
+        self.position = match count.checked_sub(1) {
+            Some(i) => self.replay.actions[i].time as f32,
+            None => 0.,
+        };
+    }
+
+    /// Consumes the pending rebuild request
+    pub fn take_rebuild(&mut self) -> bool {
+        std::mem::take(&mut self.rebuild)
+    }
+
+    pub fn is_rebuilding(&self) -> bool {
+        self.rebuild
+    }
+
+    /// Recorded mouse position at the current time, interpolated between
+    /// the surrounding actions, in tile units
+    pub fn cursor(&self) -> Option<Vec2> {
+        let actions = &self.replay.actions;
+        let previous = actions[..self.next_action.min(actions.len())]
+            .iter()
+            .rev()
+            .find_map(|action| Some((action.time, action.cursor?)));
+        let next = actions[self.next_action.min(actions.len())..]
+            .iter()
+            .find_map(|action| Some((action.time, action.cursor?)));
+
+        match (previous, next) {
+            (Some((from_time, from)), Some((to_time, to))) if to_time > from_time => {
+                let t = ((self.position - from_time as f32) / (to_time - from_time) as f32)
+                    .clamp(0., 1.);
+                Some(Vec2::from(from).lerp(Vec2::from(to), t))
+            }
+            (Some((_, cursor)), _) | (None, Some((_, cursor))) => Some(Vec2::from(cursor)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Whether `action` can be applied in the same frame as `batch`, hints
+/// leave the board alone and fit anywhere
+fn fits_batch(batch: &[ReplayAction], action: &ReplayAction) -> bool {
+    if action.kind == ActionKind::Hint {
+        return true;
+    }
+    let mut moves = batch
+        .iter()
+        .filter(|queued| queued.kind != ActionKind::Hint);
+    moves.all(|queued| {
+        queued.kind == action.kind
+            && queued.kind != ActionKind::Chord
+            && queued.coordinates != action.coordinates
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::Coordinates;
+    use crate::resources::BoardOptions;
+
+    fn action(time: u32, kind: ActionKind, x: u16) -> ReplayAction {
+        ReplayAction {
+            time,
+            kind,
+            coordinates: Coordinates { x, y: 0 },
+            cursor: None,
+        }
+    }
+
+    fn playback(actions: Vec<ReplayAction>) -> ReplayPlayback {
+        ReplayPlayback::new(Replay {
+            options: BoardOptions::default(),
+            mines: Vec::new(),
+            actions,
+        })
+    }
+
+    fn kinds(actions: Vec<ReplayAction>) -> Vec<(ActionKind, u16)> {
+        actions
+            .into_iter()
+            .map(|action| (action.kind, action.coordinates.x))
+            .collect()
+    }
+
+    #[test]
+    fn every_due_action_of_a_kind_is_applied_at_once() {
+        let mut playback = playback(vec![
+            action(10, ActionKind::Flag, 0),
+            action(20, ActionKind::Hint, 5),
+            action(30, ActionKind::Flag, 1),
+            action(40, ActionKind::Flag, 2),
+            action(500, ActionKind::Flag, 3),
+        ]);
+        assert!(playback.due_actions().is_empty());
+
+        playback.advance(0.1);
+        assert_eq!(
+            kinds(playback.due_actions()),
+            vec![
+                (ActionKind::Flag, 0),
+                (ActionKind::Hint, 5),
+                (ActionKind::Flag, 1),
+                (ActionKind::Flag, 2)
+            ]
+        );
+        assert!(playback.due_actions().is_empty());
+    }
+
+    #[test]
+    fn batches_keep_the_recorded_order() {
+        let mut playback = playback(vec![
+            action(10, ActionKind::Reveal, 0),
+            action(20, ActionKind::Reveal, 1),
+            action(30, ActionKind::Flag, 2),
+            action(40, ActionKind::Flag, 2),
+            action(50, ActionKind::Chord, 3),
+            action(60, ActionKind::Chord, 4),
+        ]);
+        playback.advance(1.);
+
+        assert_eq!(
+            kinds(playback.due_actions()),
+            vec![(ActionKind::Reveal, 0), (ActionKind::Reveal, 1)]
+        );
+        // The same tile flagged twice waits for its first flag to be drawn
+        assert_eq!(kinds(playback.due_actions()), vec![(ActionKind::Flag, 2)]);
+        assert_eq!(kinds(playback.due_actions()), vec![(ActionKind::Flag, 2)]);
+        assert_eq!(kinds(playback.due_actions()), vec![(ActionKind::Chord, 3)]);
+        assert_eq!(kinds(playback.due_actions()), vec![(ActionKind::Chord, 4)]);
+        assert!(playback.is_finished());
+    }
+
+    #[test]
+    fn stepping_ignores_the_time() {
+        let mut playback = playback(vec![
+            action(100, ActionKind::Reveal, 0),
+            action(9000, ActionKind::Reveal, 1),
+        ]);
+        playback.step_forward();
+        assert_eq!(kinds(playback.due_actions()), vec![(ActionKind::Reveal, 0)]);
+        playback.step_forward();
+        assert_eq!(kinds(playback.due_actions()), vec![(ActionKind::Reveal, 1)]);
+    }
+}
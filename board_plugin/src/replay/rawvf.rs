@@ -0,0 +1,233 @@
+//! Viennasweeper's plain text `.rawvf` videos: a `Key: value` header, the
+//! board as rows of `*` and `0` under `Board:`, then one mouse event per
+//! line under `Events:`, e.g. `1.23 lc 4 2 (56 24)`
+
+use std::fmt::Write;
+
+use crate::components::Coordinates;
+use crate::replay::mouse::{self, MouseEvent, MouseEventKind};
+use crate::replay::{Replay, ReplayError};
+use crate::resources::BoardOptions;
+
+pub const RAWVF_EXTENSION: &str = "rawvf";
+
+enum Section {
+    Header,
+    Board,
+    Events,
+}
+
+impl Replay {
+    pub fn from_rawvf(text: &str) -> Result<Self, ReplayError> {
+        let mut section = Section::Header;
+        let mut header: Vec<(&str, &str)> = Vec::new();
+        let mut rows: Vec<&str> = Vec::new();
+        let mut events = Vec::new();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line {
+                "Board:" => section = Section::Board,
+                "Events:" => section = Section::Events,
+                _ => match section {
+                    // Metadata such as the player name is not part of our
+                    // replays, only the size is checked against the board
+                    Section::Header => header.extend(line.split_once(':')),
+                    Section::Board => rows.push(line),
+                    Section::Events => events.extend(parse_event(line)?),
+                },
+            }
+        }
+
+        let height = rows.len() as u16;
+        let width = rows.first().map_or(0, |row| row.chars().count() as u16);
+        if width == 0 || rows.iter().any(|row| row.chars().count() as u16 != width) {
+            return Err(ReplayError::Format("invalid board".to_string()));
+        }
+        for (key, size) in [("Width", width), ("Height", height)] {
+            let declared = header
+                .iter()
+                .find(|(field, _)| *field == key)
+                .map(|(_, value)| value.trim().parse::<u16>());
+            if declared.is_some_and(|declared| declared != Ok(size)) {
+                return Err(ReplayError::Format(format!(
+                    "board does not match the {} field",
+                    key
+                )));
+            }
+        }
+        let mines = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, cell)| *cell == '*')
+                    .map(move |(column, _)| Coordinates {
+                        x: column as u16,
+                        y: height - 1 - row as u16,
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Replay {
+            options: BoardOptions {
+                map_size: (width, height),
+                bomb_count: mines.len() as u16,
+                safe_start: false,
+                ..Default::default()
+            },
+            actions: mouse::to_actions(&events, (width, height)),
+            mines,
+        })
+    }
+
+    pub fn to_rawvf(&self) -> String {
+        let (width, height) = self.options.map_size;
+        let mut text = String::new();
+        let _ = writeln!(text, "RawVF_Version: Rev5");
+        let _ = writeln!(text, "Program: RustySweeper");
+        let _ = writeln!(text, "Width: {}", width);
+        let _ = writeln!(text, "Height: {}", height);
+        let _ = writeln!(text, "Mines: {}", self.mines.len());
+        let _ = writeln!(text, "Marks: Off");
+        let _ = writeln!(text, "Time: {:.2}", self.duration() as f32 / 1000.);
+
+        let _ = writeln!(text, "Board:");
+        for y in (0..height).rev() {
+            let row: String = (0..width)
+                .map(|x| match self.mines.contains(&Coordinates { x, y }) {
+                    true => '*',
+                    false => '0',
+                })
+                .collect();
+            let _ = writeln!(text, "{}", row);
+        }
+
+        let _ = writeln!(text, "Events:");
+        for event in mouse::from_actions(&self.actions, height) {
+            let _ = writeln!(
+                text,
+                "{:.2} {} {} {} ({} {})",
+                event.time as f32 / 1000.,
+                event.kind.code(),
+                event.x / mouse::CELL_PIXELS + 1,
+                event.y / mouse::CELL_PIXELS + 1,
+                event.x,
+                event.y
+            );
+        }
+        text
+    }
+}
+
+/// Mouse event of an `Events:` line, `None` for game events like `start`
+fn parse_event(line: &str) -> Result<Option<MouseEvent>, ReplayError> {
+    let invalid = || ReplayError::Format(format!("invalid event: {}", line));
+    let mut fields = line.split_whitespace();
+
+    let time: f32 = fields
+        .next()
+        .and_then(|time| time.parse().ok())
+        .ok_or_else(invalid)?;
+    let kind = match fields.next().and_then(MouseEventKind::from_code) {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+    // Column and row are followed by the exact pixel position in parentheses
+    let numbers: Vec<u16> = fields
+        .map(|field| field.trim_matches(|c| c == '(' || c == ')'))
+        .filter_map(|field| field.parse().ok())
+        .collect();
+    let (x, y) = match numbers[..] {
+        [_, _, x, y, ..] => (x, y),
+        [column, row] if column > 0 && row > 0 => (
+            (column - 1) * mouse::CELL_PIXELS + mouse::CELL_PIXELS / 2,
+            (row - 1) * mouse::CELL_PIXELS + mouse::CELL_PIXELS / 2,
+        ),
+        _ => return Err(invalid()),
+    };
+
+    Ok(Some(MouseEvent {
+        time: (time.max(0.) * 1000.).round() as u32,
+        kind,
+        x,
+        y,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::{ActionKind, ReplayAction};
+
+    fn replay() -> Replay {
+        let action = |time, kind, x, y| ReplayAction {
+            time,
+            kind,
+            coordinates: Coordinates { x, y },
+            cursor: None,
+        };
+        Replay {
+            options: BoardOptions {
+                map_size: (4, 3),
+                bomb_count: 2,
+                ..Default::default()
+            },
+            mines: vec![Coordinates { x: 0, y: 2 }, Coordinates { x: 3, y: 0 }],
+            actions: vec![
+                action(0, ActionKind::Reveal, 2, 2),
+                action(450, ActionKind::Flag, 0, 2),
+                action(1010, ActionKind::Chord, 1, 1),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = replay();
+        let text = replay.to_rawvf();
+        assert!(text.contains("Board:\n*000\n0000\n000*\n"));
+        assert!(text.contains("0.45 rc 1 1 (8 8)"));
+
+        let read = Replay::from_rawvf(&text).unwrap();
+        assert_eq!(read.options.map_size, (4, 3));
+        assert_eq!(read.mines.len(), 2);
+        assert!(replay.mines.iter().all(|mine| read.mines.contains(mine)));
+        let actions: Vec<_> = read
+            .actions
+            .iter()
+            .map(|action| (action.time, action.kind, action.coordinates))
+            .collect();
+        let expected: Vec<_> = replay
+            .actions
+            .iter()
+            .map(|action| (action.time, action.kind, action.coordinates))
+            .collect();
+        assert_eq!(actions, expected);
+    }
+
+    #[test]
+    fn events_without_pixels_click_the_tile_center() {
+        let text = "Board:\n*0\n00\nEvents:\n0.00 start\n0.12 lc 2 1\n0.20 lr 2 1\n";
+        let read = Replay::from_rawvf(text).unwrap();
+        assert_eq!(read.actions.len(), 1);
+        assert_eq!(read.actions[0].time, 200);
+        assert_eq!(read.actions[0].coordinates, Coordinates { x: 1, y: 1 });
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        let text = replay().to_rawvf();
+        // Cut in the middle of the board
+        let cut = text.find("0000").unwrap();
+        assert!(Replay::from_rawvf(&text[..cut]).is_err());
+        assert!(Replay::from_rawvf(&text[..cut + 2]).is_err());
+        let wrong_width = text.replace("Width: 4", "Width: 5");
+        assert!(Replay::from_rawvf(&wrong_width).is_err());
+
+        assert!(Replay::from_rawvf("").is_err());
+        assert!(Replay::from_rawvf("garbage\nwithout\nsections").is_err());
+        assert!(Replay::from_rawvf("Board:\n*0\n00\nEvents:\nsoon lc 1 1\n").is_err());
+        assert!(Replay::from_rawvf("Board:\n*0\n00\nEvents:\n0.10 lc 1\n").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::{Resource, Vec2};
+
+use crate::components::Coordinates;
+use crate::replay::{ActionKind, Replay, ReplayAction, REPLAY_EXTENSION};
+use crate::resources::tile_map::TileMap;
+use crate::resources::BoardOptions;
+
+/// Collects the actions of the running game, saved once the game ends
+#[derive(Debug, Clone, Resource)]
+pub struct ReplayRecorder {
+    pub replay: Replay,
+    started: Instant,
+}
+
+impl ReplayRecorder {
+    pub fn new(options: &BoardOptions, tile_map: &TileMap) -> Self {
+        Self {
+            replay: Replay {
+                options: options.clone(),
+                mines: tile_map.mines(),
+                actions: Vec::new(),
+            },
+            started: Instant::now(),
+        }
+    }
+
+    pub fn record(&mut self, kind: ActionKind, coordinates: Coordinates, cursor: Option<Vec2>) {
+        self.replay.actions.push(ReplayAction {
+            time: self.started.elapsed().as_millis() as u32,
+            kind,
+            coordinates,
+            cursor: cursor.map(|cursor| (cursor.x, cursor.y)),
+        });
+    }
+
+    /// Where finished games are saved: `replays/<unix time>.msr`
+    pub fn default_path() -> PathBuf {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        PathBuf::from("replays").join(format!("{}.{}", timestamp, REPLAY_EXTENSION))
+    }
+}
@@ -0,0 +1,115 @@
+//! Board difficulty metrics used by the competitive community
+
+use crate::queue::Queue;
+use crate::resources::cells::CellSet;
+use crate::resources::tile_map::TileMap;
+
+impl TileMap {
+    /// Bechtel's Board Benchmark Value: the minimum number of left clicks
+    /// needed to clear the board. Each opening counts once, together with
+    /// the numbers around it, and every other number counts once.
+    pub fn bbbv(&self) -> u32 {
+        let (openings, cleared) = self.openings_and_borders();
+        let isolated = self
+            .coordinates()
+            .filter(|coordinates| !self.is_bomb_at(*coordinates) && !cleared.contains(coordinates))
+            .count();
+        openings + isolated as u32
+    }
+
+    /// Number of openings: connected areas of empty tiles, each cleared by
+    /// a single click
+    pub fn openings(&self) -> u32 {
+        self.openings_and_borders().0
+    }
+
+    /// Number of islands: connected groups of numbers that no opening
+    /// reveals, they have to be cleared one click at a time
+    pub fn islands(&self) -> u32 {
+        let (_, cleared) = self.openings_and_borders();
+        let mut visited = CellSet::new(self.width, self.height);
+        let mut islands = 0;
+
+        for coordinates in self.coordinates() {
+            if self.is_bomb_at(coordinates)
+                || cleared.contains(&coordinates)
+                || !visited.insert(coordinates)
+            {
+                continue;
+            }
+            islands += 1;
+            let mut queue = Queue::from([coordinates]);
+            while let Some(current) = queue.dequeue() {
+                for neighbor in self.get_neighbor_coordinates(current) {
+                    if self.contains(neighbor)
+                        && !self.is_bomb_at(neighbor)
+                        && !cleared.contains(&neighbor)
+                        && visited.insert(neighbor)
+                    {
+                        queue.enqueue(neighbor);
+                    }
+                }
+            }
+        }
+        islands
+    }
+
+    /// Openings count and every tile they reveal, numbers on their border
+    /// included
+    fn openings_and_borders(&self) -> (u32, CellSet) {
+        let mut cleared = CellSet::new(self.width, self.height);
+        let mut openings = 0;
+
+        for coordinates in self.coordinates() {
+            if self.is_empty_at(coordinates) && !cleared.contains(&coordinates) {
+                openings += 1;
+                cleared.extend(self.flood_fill(coordinates, |_| false));
+            }
+        }
+        (openings, cleared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::components::Coordinates;
+    use crate::resources::cells::CellSet;
+    use crate::resources::tile_map::TileMap;
+    use crate::resources::topology::TopologyKind;
+
+    fn metrics(tile_map: &TileMap) -> (u32, u32, u32) {
+        (tile_map.bbbv(), tile_map.openings(), tile_map.islands())
+    }
+
+    #[test]
+    fn openings_claim_their_border() {
+        // Two openings on the sides, their borders leave the numbers above
+        // and below the mine on their own
+        let tile_map: TileMap = ".....\n..*..\n.....\n".parse().unwrap();
+        assert_eq!(metrics(&tile_map), (4, 2, 2));
+
+        // A single opening reveals every number
+        let tile_map: TileMap = "....*\n.....\n.....\n".parse().unwrap();
+        assert_eq!(metrics(&tile_map), (1, 1, 0));
+    }
+
+    #[test]
+    fn boards_without_openings() {
+        // Every safe tile is a number, all of them touching
+        let tile_map: TileMap = "*.*\n...\n*.*\n".parse().unwrap();
+        assert_eq!(metrics(&tile_map), (5, 0, 1));
+
+        let tile_map: TileMap = "*.*.*\n".parse().unwrap();
+        assert_eq!(metrics(&tile_map), (2, 0, 2));
+    }
+
+    #[test]
+    fn holes_split_openings() {
+        // The middle column is cut out, the top right number is left alone
+        let mut holes = CellSet::new(5, 3);
+        holes.extend((0..3).map(|y| Coordinates { x: 2, y }));
+        let mut tile_map = TileMap::empty_masked(TopologyKind::Square8, 5, 3, holes);
+        tile_map.place_mines(&[Coordinates { x: 4, y: 2 }]);
+        assert_eq!(metrics(&tile_map), (3, 2, 1));
+    }
+}
@@ -1,10 +1,14 @@
 use bevy::prelude::*;
-use bevy::utils::{HashMap, HashSet};
+use bevy::utils::{Duration, HashMap};
 
 use crate::bounds::Bounds2;
-use crate::queue::Queue;
+use crate::components::PlayerId;
+use crate::resources::cells::{CellMap, CellSet};
 use crate::{Coordinates, TileMap};
 
+/// Added to the time of lives games for every mine hit
+pub const HIT_PENALTY: Duration = Duration::from_secs(30);
+
 // #[derive(Default, Debug, Clone, Serialize, Deserialize, Resource, Reflect)]
 #[derive(Debug, Clone, Resource)]
 // #[reflect(Resource)]
@@ -17,8 +21,19 @@ pub struct Board {
     pub tile_map: TileMap,
     pub tiles: HashMap<Coordinates, Entity>,
 
-    pub coordinates_discovered: HashSet<Coordinates>,
-    pub coordinates_marked: HashSet<Coordinates>,
+    pub coordinates_discovered: CellSet,
+    /// Flagged tiles and the player who placed each flag
+    pub coordinates_marked: CellMap<PlayerId>,
+    /// Mines each flag stands for on boards whose cells hold several,
+    /// flags without a level stand for one
+    pub flag_levels: CellMap<u8>,
+    /// Mine hits the player may take, the last one ends the game
+    pub lives: u8,
+    /// Mines uncovered so far, they stay revealed while lives remain
+    pub detonated: CellSet,
+
+    /// `Time::elapsed` when the board was created
+    pub started: Duration,
 }
 
 impl Board {
@@ -28,18 +43,29 @@ impl Board {
         tile_size: f32,
         tile_map: TileMap,
         tiles: HashMap<Coordinates, Entity>,
+        lives: u8,
+        started: Duration,
     ) -> Self {
         Board {
             entity,
             bounds,
             tile_size,
+            coordinates_discovered: CellSet::new(tile_map.width, tile_map.height),
+            coordinates_marked: CellMap::new(tile_map.width, tile_map.height),
+            flag_levels: CellMap::new(tile_map.width, tile_map.height),
+            lives: lives.max(1),
+            detonated: CellSet::new(tile_map.width, tile_map.height),
             tile_map,
-            coordinates_discovered: HashSet::with_capacity(tiles.len()),
-            coordinates_marked: HashSet::with_capacity(tiles.len()),
             tiles,
+            started,
         }
     }
 
+    /// Time spent on the board so far
+    pub fn elapsed(&self, time: &Time) -> Duration {
+        time.elapsed().saturating_sub(self.started)
+    }
+
     pub fn mouse_position(&self, window: &Window, mouse_position: Vec2) -> Option<Coordinates> {
         let window_size = Vec2::new(window.width(), window.height());
 
@@ -54,12 +80,35 @@ impl Board {
             return None;
         }
         // World space to board space
-        let coordinates = mouse_position - self.bounds.position;
+        let position = mouse_position - self.bounds.position;
 
-        Some(Coordinates {
-            x: (coordinates.x / self.tile_size) as u16,
-            y: (coordinates.y / self.tile_size) as u16,
-        })
+        self.tile_map
+            .topology
+            .topology()
+            .tile_at(position, self.tile_map.size(), self.tile_size)
+            .filter(|coordinates| self.tile_map.contains(*coordinates))
+    }
+
+    /// Time spent on the board plus the penalty of every mine hit
+    pub fn score_time(&self, time: &Time) -> Duration {
+        self.elapsed(time) + HIT_PENALTY * self.detonated.len() as u32
+    }
+
+    pub fn lives_left(&self) -> u8 {
+        self.lives.saturating_sub(self.detonated.len() as u8)
+    }
+
+    /// Records an uncovered mine, returns whether the player survives it
+    pub fn hit_mine(&mut self, coordinates: Coordinates) -> bool {
+        self.detonated.insert(coordinates);
+        self.lives_left() > 0
+    }
+
+    /// Mouse position relative to the bottom left corner of the board, in
+    /// tile units. Unlike `mouse_position` it is not limited to the board.
+    pub fn board_position(&self, window: &Window, mouse_position: Vec2) -> Vec2 {
+        let window_size = Vec2::new(window.width(), window.height());
+        (mouse_position - window_size / 2. - self.bounds.position) / self.tile_size
     }
 
     pub fn get_tile_entity(&self, coordinates: Coordinates) -> Option<&Entity> {
@@ -74,73 +123,196 @@ impl Board {
             .collect()
     }
 
-    pub fn flood_discovery(&mut self, coordinates: &Coordinates) -> HashSet<Entity> {
-        let mut queue = Queue::from([*coordinates]);
-        let mut discovered: HashMap<Entity, Coordinates> = HashMap::new();
-        let mut visited: HashSet<Entity> = HashSet::new();
-
-        while let Some(current_coordinates) = queue.dequeue() {
-            if let Some(entity) = self.get_tile_entity(current_coordinates) {
-                if self.is_flag_at(&current_coordinates) {
-                    continue;
-                }
-
-                discovered.insert(*entity, current_coordinates);
-
-                if !self.tile_map.is_empty_at(current_coordinates) {
-                    continue;
-                }
-
-                for neighbor_coordinates in
-                    self.tile_map.get_neighbor_coordinates(current_coordinates)
-                {
-                    if let Some(entity) = self.get_tile_entity(neighbor_coordinates) {
-                        if !visited.contains(entity) {
-                            queue.enqueue(neighbor_coordinates);
-                        }
-                        visited.insert(*entity);
-                    }
-                }
-            }
-        }
+    /// Uncovers the opening around an empty tile, returns the tiles it
+    /// newly uncovered
+    pub fn flood_discovery(&mut self, coordinates: &Coordinates) -> HashMap<Coordinates, Entity> {
+        let marked = &self.coordinates_marked;
+        let discovered = self
+            .tile_map
+            .flood_fill(*coordinates, |coordinates| marked.contains_key(coordinates));
 
-        for v in discovered.values() {
-            self.coordinates_discovered.insert(*v);
-        }
+        discovered
+            .into_iter()
+            .filter(|coordinates| self.coordinates_discovered.insert(*coordinates))
+            .filter_map(|coordinates| Some((coordinates, *self.tiles.get(&coordinates)?)))
+            .collect()
+    }
 
-        discovered.keys().cloned().collect()
+    pub fn chord_targets(&self, coordinates: Coordinates) -> Vec<Coordinates> {
+        self.tile_map.chord_targets(
+            coordinates,
+            |coordinates| self.coordinates_discovered.contains(coordinates),
+            |coordinates| match self.detonated.contains(coordinates) {
+                true => self.tile_map.mines_at(*coordinates),
+                false => self.flag_mines(coordinates),
+            },
+        )
     }
 
     fn unmark_tile(&mut self, coords: &Coordinates) -> bool {
-        self.coordinates_marked.remove(coords)
+        self.flag_levels.remove(coords);
+        self.coordinates_marked.remove(coords).is_some()
     }
 
-    pub fn try_toggle_mark(&mut self, coordinates: Coordinates) -> bool {
-        let is_marked = if self.coordinates_marked.contains(&coordinates)
-            || self.coordinates_discovered.contains(&coordinates)
-        {
+    /// Cycles the flag of a covered tile through 1 to `cell_mines` mines,
+    /// then removes it. Returns the mines the flag now stands for, `None`
+    /// once it is gone.
+    pub fn try_toggle_mark(&mut self, coordinates: Coordinates, player: PlayerId) -> Option<u8> {
+        let level = self.flag_mines(&coordinates) + 1;
+        if level > self.tile_map.cell_mines || self.coordinates_discovered.contains(&coordinates) {
             self.unmark_tile(&coordinates);
-            false
-        } else {
-            self.coordinates_marked.insert(coordinates);
-            true
-        };
+            return None;
+        }
+        self.coordinates_marked.insert(coordinates, player);
+        self.flag_levels.insert(coordinates, level);
+        Some(level)
+    }
 
-        is_marked
+    /// Puts a flag standing for `level` mines on a tile, removes it at 0
+    pub fn set_flag_level(&mut self, coordinates: Coordinates, player: PlayerId, level: u8) {
+        if level == 0 {
+            self.unmark_tile(&coordinates);
+        } else {
+            self.coordinates_marked.insert(coordinates, player);
+            self.flag_levels.insert(coordinates, level);
+        }
     }
 
+    /// Whether the board is won: every safe tile is uncovered and, when
+    /// cells hold several mines, every flag stands for the mines under it.
+    /// Detonated mines count as found.
     pub fn is_completed(&self) -> bool {
-        let goal_count =
-            (self.tile_map.height * self.tile_map.width - self.tile_map.bomb_count) as usize;
+        let safe_discovered = self
+            .coordinates_discovered
+            .iter()
+            .filter(|coordinates| !self.tile_map.is_bomb_at(*coordinates))
+            .count();
+        safe_discovered == self.tile_map.safe_count()
+            && (self.tile_map.cell_mines == 1 || self.flags_match_mines())
+    }
+
+    /// Whether the flags add up to the mines, cell by cell
+    fn flags_match_mines(&self) -> bool {
+        let flagged: u16 = self
+            .coordinates_marked
+            .iter()
+            .map(|(coordinates, _)| self.flag_mines(&coordinates) as u16)
+            .sum();
+        let detonated: u16 = self
+            .detonated
+            .iter()
+            .map(|coordinates| self.tile_map.mines_at(coordinates) as u16)
+            .sum();
+        flagged + detonated == self.tile_map.bomb_count
+            && self.coordinates_marked.iter().all(|(coordinates, _)| {
+                self.flag_mines(&coordinates) == self.tile_map.mines_at(coordinates)
+            })
+    }
 
-        if self.coordinates_discovered.len() == goal_count {
-            // TODO: corner case when last element is bomb
-            return true;
+    /// Mines the flag of a tile stands for, 0 without a flag
+    pub fn flag_mines(&self, coordinates: &Coordinates) -> u8 {
+        match self.is_flag_at(coordinates) {
+            true => self.flag_levels.get(coordinates).unwrap_or(1),
+            false => 0,
         }
-        return false;
     }
 
     pub fn is_flag_at(&self, coordinates: &Coordinates) -> bool {
-        self.coordinates_marked.contains(&coordinates)
+        self.coordinates_marked.contains_key(coordinates)
+    }
+
+    pub fn flag_owner(&self, coordinates: &Coordinates) -> Option<PlayerId> {
+        self.coordinates_marked.get(coordinates)
+    }
+
+    /// Center of the tile in board space
+    pub fn tile_center(&self, coordinates: Coordinates) -> Vec2 {
+        self.tile_map
+            .topology
+            .topology()
+            .tile_center(coordinates, self.tile_size)
+    }
+}
+
+#[cfg(test)]
+impl Board {
+    /// Board of a text layout, `*` for mines and the top row first, without
+    /// any tile entity
+    pub(crate) fn from_grid(grid: &str, lives: u8) -> Self {
+        let bounds = Bounds2 {
+            position: Vec2::ZERO,
+            size: Vec2::ZERO,
+        };
+        let tile_map = grid.parse().unwrap();
+        Board::new(
+            Entity::PLACEHOLDER,
+            bounds,
+            1.,
+            tile_map,
+            HashMap::new(),
+            lives,
+            Duration::ZERO,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "*...\n....\n...*\n";
+
+    /// Uncovers a tile the way the game does, mines count as hits
+    fn uncover(board: &mut Board, x: u16, y: u16) {
+        let coordinates = Coordinates { x, y };
+        if board.tile_map.is_bomb_at(coordinates) {
+            board.coordinates_discovered.insert(coordinates);
+            board.hit_mine(coordinates);
+        } else {
+            board.flood_discovery(&coordinates);
+        }
+    }
+
+    #[test]
+    fn lives_games_are_won_after_hits() {
+        let mut board = Board::from_grid(GRID, 3);
+        uncover(&mut board, 0, 2);
+        uncover(&mut board, 3, 0);
+        assert_eq!(board.lives_left(), 1);
+
+        let safe: Vec<_> = board
+            .tile_map
+            .coordinates()
+            .filter(|coordinates| !board.tile_map.is_bomb_at(*coordinates))
+            .collect();
+        for coordinates in safe {
+            if !board.coordinates_discovered.contains(&coordinates) {
+                assert!(!board.is_completed());
+                uncover(&mut board, coordinates.x, coordinates.y);
+            }
+        }
+        assert_eq!(board.coordinates_discovered.len(), 12);
+        assert!(board.is_completed());
+    }
+
+    #[test]
+    fn an_uncovered_mine_is_not_a_safe_tile() {
+        // Every safe tile but one is uncovered, along with the mine that
+        // ended the game
+        let mut board = Board::from_grid(GRID, 1);
+        let covered = Coordinates { x: 1, y: 1 };
+        for coordinates in board.tile_map.coordinates().collect::<Vec<_>>() {
+            if coordinates != covered && !board.tile_map.is_bomb_at(coordinates) {
+                board.coordinates_discovered.insert(coordinates);
+            }
+        }
+        board
+            .coordinates_discovered
+            .insert(Coordinates { x: 0, y: 2 });
+        assert_eq!(
+            board.coordinates_discovered.len(),
+            board.tile_map.safe_count()
+        );
+        assert!(!board.is_completed());
     }
 }
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 use bevy::render::texture::DEFAULT_IMAGE_HANDLE;
 
+use crate::components::PlayerId;
+use crate::resources::topology::TopologyKind;
+
 #[derive(Debug, Clone)]
 pub struct SpriteMaterial {
     pub color: Color,
@@ -22,12 +25,17 @@ pub struct BoardAssets {
     pub board_material: SpriteMaterial,
     pub tile_material: SpriteMaterial,
     pub covered_tile_material: SpriteMaterial,
+    /// Tile base of hex boards, the texture gives the hexagon its shape
+    pub hex_tile_material: SpriteMaterial,
+    pub hex_covered_tile_material: SpriteMaterial,
     pub bomb_counter_colors: Vec<Color>,
     pub flag_material: SpriteMaterial,
     pub bomb_material: SpriteMaterial,
     pub material_1: SpriteMaterial,
     pub material_2: SpriteMaterial,
     pub material_3: SpriteMaterial,
+    pub player_colors: Vec<Color>,
+    pub font: Handle<Font>,
 }
 
 impl BoardAssets {
@@ -41,6 +49,25 @@ impl BoardAssets {
         ]
     }
 
+    pub fn default_player_colors() -> Vec<Color> {
+        vec![
+            Color::RED,
+            Color::BLUE,
+            Color::GREEN,
+            Color::ORANGE,
+            Color::PURPLE,
+            Color::CYAN,
+        ]
+    }
+
+    /// Materials of the tile base and of the tile cover
+    pub fn tile_materials(&self, topology: TopologyKind) -> (&SpriteMaterial, &SpriteMaterial) {
+        match topology {
+            TopologyKind::Hex => (&self.hex_tile_material, &self.hex_covered_tile_material),
+            _ => (&self.tile_material, &self.covered_tile_material),
+        }
+    }
+
     pub fn bomb_counter_color(&self, counter: u8) -> &Color {
         let counter = counter.saturating_sub(1) as usize;
 
@@ -52,4 +79,11 @@ impl BoardAssets {
             }
         }
     }
+
+    pub fn player_color(&self, player: PlayerId) -> &Color {
+        match self.player_colors.get(player.0 as usize) {
+            Some(color) => color,
+            None => &Color::WHITE,
+        }
+    }
 }
@@ -3,6 +3,13 @@ use bevy::{
 };
 use serde::{Deserialize, Serialize};
 
+use crate::components::Coordinates;
+use crate::resources::cells::CellSet;
+use crate::resources::generator;
+use crate::resources::mask::BoardMask;
+use crate::resources::tile_map::TileMap;
+use crate::resources::topology::{Layers, TopologyKind};
+
 #[derive(Debug, Clone, Serialize, Deserialize, Reflect)]
 pub enum TileSize {
     Fixed(f32),
@@ -15,15 +22,113 @@ pub enum BoardPosition {
     Custom(Vec3),
 }
 
+/// Decides how the board reacts to uncovered mines
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Reflect)]
+pub enum GameRules {
+    /// Uncovering a mine loses the game
+    #[default]
+    Classic,
+    /// Hot-seat duel: players alternate turns, uncovering a mine scores a
+    /// point and grants another turn
+    Versus,
+}
+
+/// Shape of the tiles
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Reflect)]
+pub enum BoardShape {
+    /// Square tiles with up to 8 neighbors
+    #[default]
+    Square,
+    /// Hexagonal tiles in offset rows with up to 6 neighbors
+    Hex,
+}
+
+/// Tiles whose mines a number counts on square boards, openings cascade
+/// and chords reach through the same tiles
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, Reflect)]
+pub enum Neighborhood {
+    /// The 8 tiles around
+    #[default]
+    Adjacent,
+    /// The 4 tiles sharing a side
+    Orthogonal,
+    /// The 8 tiles a knight's move away
+    Knight,
+    /// The 24 tiles of the 5x5 square around
+    Extended,
+}
+
+impl Neighborhood {
+    pub const NAMES: [&'static str; 4] = ["adjacent", "orthogonal", "knight", "extended"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "adjacent" => Some(Self::Adjacent),
+            "orthogonal" => Some(Self::Orthogonal),
+            "knight" => Some(Self::Knight),
+            "extended" => Some(Self::Extended),
+            _ => None,
+        }
+    }
+
+    /// Grid of a plain square board counting this neighborhood
+    pub fn topology(self) -> TopologyKind {
+        match self {
+            Neighborhood::Adjacent => TopologyKind::Square8,
+            Neighborhood::Orthogonal => TopologyKind::Square4,
+            Neighborhood::Knight => TopologyKind::Knight,
+            Neighborhood::Extended => TopologyKind::Square24,
+        }
+    }
+}
+
+/// Everything a board is built from. Replays embed the options through
+/// bincode, which has no notion of missing or defaulted fields: adding,
+/// removing or reordering a field changes the replay layout and must bump
+/// `REPLAY_VERSION`.
 #[derive(Debug, Clone, Serialize, Deserialize, Resource, Reflect)]
 #[reflect(Resource)]
 pub struct BoardOptions {
     pub map_size: (u16, u16),
+    /// Total number of mines
     pub bomb_count: u16,
+    /// Most mines a single cell holds, from 1 on classic boards to 3. Flags
+    /// cycle through as many levels.
+    pub cell_mines: u8,
     pub position: BoardPosition,
     pub tile_size: TileSize,
     pub tile_padding: f32,
     pub safe_start: bool,
+    pub rules: GameRules,
+    /// Mines that can be uncovered before the game is lost, each hit adds
+    /// a time penalty. Classic games have a single life.
+    pub lives: u8,
+    /// Moves can be taken back, the game is left out of the statistics
+    pub practice: bool,
+    pub shape: BoardShape,
+    /// Opposite edges touch, on square boards only
+    pub wrap: bool,
+    /// Tiles a number counts, on square boards that don't wrap
+    pub neighborhood: Neighborhood,
+    /// Square layers stacked on top of each other, `map_size` is the size
+    /// of one layer. Boards with several layers ignore `shape` and `wrap`.
+    pub layers: u16,
+    /// Cells left out of the board, the whole rectangle is played when
+    /// `None`. Layered boards have the same holes on every layer.
+    #[reflect(ignore)]
+    pub mask: Option<BoardMask>,
+    /// Fixed mine positions, mines are placed at random when `None`
+    #[reflect(ignore)]
+    pub layout: Option<Vec<Coordinates>>,
+    /// Name of the `MineGenerator` placing the mines
+    pub generator: String,
+    /// Seed for the random mine placement, a fresh one is drawn when `None`
+    pub seed: Option<u64>,
+    /// Tile opened by the safe start. The center or else the first empty
+    /// tile is opened instead when `None` or when this tile is not empty,
+    /// like a coded start on a layout that doesn't match it.
+    #[reflect(ignore)]
+    pub start: Option<Coordinates>,
 }
 
 impl Default for TileSize {
@@ -40,15 +145,104 @@ impl Default for BoardPosition {
     }
 }
 
+pub(crate) fn single_layer() -> u16 {
+    1
+}
+
+impl BoardOptions {
+    /// Grid the tiles are laid out on
+    pub fn topology(&self) -> TopologyKind {
+        if self.layers > 1 {
+            return TopologyKind::Layers(Layers {
+                count: self.layers,
+                width: self.map_size.0,
+            });
+        }
+        match (self.shape, self.wrap) {
+            (BoardShape::Square, false) => self.neighborhood.topology(),
+            (BoardShape::Square, true) => TopologyKind::Torus,
+            (BoardShape::Hex, _) => TopologyKind::Hex,
+        }
+    }
+
+    /// Cells the generators place mines in. Boards whose cells hold several
+    /// mines spread the total over fewer cells, keeping the average of the
+    /// mines per cell halfway between 1 and `cell_mines`.
+    pub fn mine_cells(&self) -> u16 {
+        let average_twice = 1 + self.cell_mines.max(1) as u32;
+        (self.bomb_count as u32 * 2).div_ceil(average_twice) as u16
+    }
+
+    /// Size of the tile map, in which the layers sit side by side
+    pub fn grid_size(&self) -> (u16, u16) {
+        (self.map_size.0 * self.layers.max(1), self.map_size.1)
+    }
+
+    /// Cells of the tile map left out by the mask, on every layer
+    pub fn hole_set(&self) -> CellSet {
+        let (width, height) = self.grid_size();
+        let mut holes = CellSet::new(width, height);
+        if let Some(mask) = &self.mask {
+            for layer in 0..self.layers.max(1) {
+                holes.extend(mask.holes.iter().map(|hole| Coordinates {
+                    x: layer * mask.width + hole.x,
+                    y: hole.y,
+                }));
+            }
+        }
+        holes
+    }
+
+    /// Plays the mines of `tile_map` instead of random ones
+    pub fn set_layout(&mut self, tile_map: &TileMap) {
+        self.layers = tile_map.topology.layers();
+        self.map_size = (tile_map.width / self.layers, tile_map.height);
+        self.bomb_count = tile_map.bomb_count;
+        self.cell_mines = tile_map.cell_mines;
+        self.layout = Some(tile_map.mines());
+    }
+
+    /// Plays on the cells of `mask`, the board takes its size
+    pub fn set_mask(&mut self, mask: BoardMask) {
+        self.map_size = (mask.width, mask.height);
+        self.mask = Some(mask);
+    }
+
+    /// Tile uncovered when the game starts, if any
+    pub fn start_coordinates(&self, tile_map: &TileMap) -> Option<Coordinates> {
+        if !self.safe_start {
+            return None;
+        }
+        let center = tile_map.topology.topology().center(tile_map.size());
+        self.start
+            .filter(|start| tile_map.is_empty_at(*start))
+            .or_else(|| tile_map.is_empty_at(center).then_some(center))
+            .or_else(|| tile_map.first_empty())
+    }
+}
+
 impl Default for BoardOptions {
     fn default() -> Self {
         Self {
             map_size: (15, 15),
             bomb_count: 30,
+            cell_mines: 1,
             position: Default::default(),
             tile_size: Default::default(),
             tile_padding: 0.,
             safe_start: false,
+            rules: Default::default(),
+            lives: 1,
+            practice: false,
+            shape: Default::default(),
+            wrap: false,
+            neighborhood: Default::default(),
+            layers: 1,
+            mask: None,
+            layout: None,
+            generator: generator::UNIFORM.to_string(),
+            seed: None,
+            start: None,
         }
     }
 }
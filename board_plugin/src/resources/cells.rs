@@ -0,0 +1,159 @@
+//! Per tile state stored flat, one bit or one slot per tile in row order
+
+use crate::components::Coordinates;
+
+/// Set of tiles on a `width` by `height` board, backed by a bitset
+#[derive(Debug, Clone, Default)]
+pub struct CellSet {
+    width: u16,
+    height: u16,
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl CellSet {
+    pub fn new(width: u16, height: u16) -> Self {
+        let cells = width as usize * height as usize;
+        Self {
+            width,
+            height,
+            words: vec![0; cells.div_ceil(64)],
+            len: 0,
+        }
+    }
+
+    fn index(&self, coordinates: &Coordinates) -> Option<usize> {
+        (coordinates.x < self.width && coordinates.y < self.height)
+            .then(|| coordinates.y as usize * self.width as usize + coordinates.x as usize)
+    }
+
+    pub fn contains(&self, coordinates: &Coordinates) -> bool {
+        self.index(coordinates)
+            .is_some_and(|index| self.words[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    /// Adds the tile, returns whether it was missing. Tiles outside of the
+    /// board are ignored.
+    pub fn insert(&mut self, coordinates: Coordinates) -> bool {
+        let index = match self.index(&coordinates) {
+            Some(index) => index,
+            None => return false,
+        };
+        let (word, bit) = (&mut self.words[index / 64], 1 << (index % 64));
+        let inserted = *word & bit == 0;
+        *word |= bit;
+        self.len += inserted as usize;
+        inserted
+    }
+
+    /// Removes the tile, returns whether it was present
+    pub fn remove(&mut self, coordinates: &Coordinates) -> bool {
+        let index = match self.index(coordinates) {
+            Some(index) => index,
+            None => return false,
+        };
+        let (word, bit) = (&mut self.words[index / 64], 1 << (index % 64));
+        let removed = *word & bit != 0;
+        *word &= !bit;
+        self.len -= removed as usize;
+        removed
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Tiles in the set, in row order
+    pub fn iter(&self) -> impl Iterator<Item = Coordinates> + '_ {
+        let width = self.width as usize;
+        self.words.iter().enumerate().flat_map(move |(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let index = i * 64 + word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(Coordinates {
+                    x: (index % width) as u16,
+                    y: (index / width) as u16,
+                })
+            })
+        })
+    }
+}
+
+impl Extend<Coordinates> for CellSet {
+    fn extend<I: IntoIterator<Item = Coordinates>>(&mut self, iter: I) {
+        for coordinates in iter {
+            self.insert(coordinates);
+        }
+    }
+}
+
+/// Value per tile for some of the tiles of a board, a [`CellSet`] tells
+/// which ones have a value
+#[derive(Debug, Clone, Default)]
+pub struct CellMap<T> {
+    keys: CellSet,
+    values: Vec<T>,
+}
+
+impl<T: Copy + Default> CellMap<T> {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            keys: CellSet::new(width, height),
+            values: vec![T::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn contains_key(&self, coordinates: &Coordinates) -> bool {
+        self.keys.contains(coordinates)
+    }
+
+    pub fn get(&self, coordinates: &Coordinates) -> Option<T> {
+        let index = self.keys.index(coordinates)?;
+        self.keys.contains(coordinates).then(|| self.values[index])
+    }
+
+    /// Sets the value of the tile, returns the previous one. Tiles outside
+    /// of the board are ignored.
+    pub fn insert(&mut self, coordinates: Coordinates, value: T) -> Option<T> {
+        let index = self.keys.index(&coordinates)?;
+        let previous = self.get(&coordinates);
+        self.keys.insert(coordinates);
+        self.values[index] = value;
+        previous
+    }
+
+    pub fn remove(&mut self, coordinates: &Coordinates) -> Option<T> {
+        let previous = self.get(coordinates)?;
+        self.keys.remove(coordinates);
+        Some(previous)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn keys(&self) -> &CellSet {
+        &self.keys
+    }
+
+    /// Tiles and their values, in row order
+    pub fn iter(&self) -> impl Iterator<Item = (Coordinates, T)> + '_ {
+        let width = self.keys.width as usize;
+        self.keys.iter().map(move |coordinates| {
+            let index = coordinates.y as usize * width + coordinates.x as usize;
+            (coordinates, self.values[index])
+        })
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::components::Coordinates;
+use crate::replay::ActionKind;
+use crate::resources::board::Board;
+
+/// Clicks made on the current board. A click is useful when it uncovers
+/// tiles or flags a mine, every other click is wasted.
+#[derive(Debug, Default, Copy, Clone, Resource, Serialize, Deserialize)]
+pub struct ClickCounter {
+    pub left: u32,
+    pub right: u32,
+    pub chord: u32,
+    pub useful: u32,
+    pub wasted: u32,
+}
+
+impl ClickCounter {
+    /// Counts a click on `coordinates`, judged against the board as it is
+    /// before the click goes through
+    pub fn count(&mut self, kind: ActionKind, coordinates: Coordinates, board: &Board) {
+        let useful = match kind {
+            ActionKind::Reveal => {
+                self.left += 1;
+                !board.coordinates_discovered.contains(&coordinates)
+                    && !board.is_flag_at(&coordinates)
+            }
+            ActionKind::Flag => {
+                self.right += 1;
+                !board.coordinates_discovered.contains(&coordinates)
+                    && !board.is_flag_at(&coordinates)
+                    && board.tile_map.is_bomb_at(coordinates)
+            }
+            ActionKind::Chord => {
+                self.chord += 1;
+                !board.chord_targets(coordinates).is_empty()
+            }
+            // Not a click
+            ActionKind::Hint => return,
+        };
+        if useful {
+            self.useful += 1;
+        } else {
+            self.wasted += 1;
+        }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.left + self.right + self.chord
+    }
+
+    /// 3BV divided by the clicks it took, `None` before the first click
+    pub fn efficiency(&self, bbbv: u32) -> Option<f32> {
+        (self.total() > 0).then(|| bbbv as f32 / self.total() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::PlayerId;
+
+    fn at(x: u16, y: u16) -> Coordinates {
+        Coordinates { x, y }
+    }
+
+    #[test]
+    fn reveals_are_useful_on_covered_tiles_only() {
+        let mut board = Board::from_grid("*...\n....\n...*\n", 1);
+        let mut clicks = ClickCounter::default();
+
+        clicks.count(ActionKind::Reveal, at(0, 0), &board);
+        board.flood_discovery(&at(0, 0));
+        clicks.count(ActionKind::Reveal, at(1, 1), &board);
+        board.try_toggle_mark(at(3, 1), PlayerId(0));
+        clicks.count(ActionKind::Reveal, at(3, 1), &board);
+
+        assert_eq!((clicks.left, clicks.useful, clicks.wasted), (3, 1, 2));
+    }
+
+    #[test]
+    fn flags_are_useful_on_mines_only() {
+        let mut board = Board::from_grid("*...\n....\n...*\n", 1);
+        board.flood_discovery(&at(0, 0));
+        let mut clicks = ClickCounter::default();
+
+        clicks.count(ActionKind::Flag, at(3, 0), &board);
+        clicks.count(ActionKind::Flag, at(3, 2), &board);
+        clicks.count(ActionKind::Flag, at(1, 0), &board);
+        board.try_toggle_mark(at(0, 2), PlayerId(0));
+        // Lifting a flag is wasted too
+        clicks.count(ActionKind::Flag, at(0, 2), &board);
+
+        assert_eq!((clicks.right, clicks.useful, clicks.wasted), (4, 1, 3));
+    }
+
+    #[test]
+    fn chords_are_useful_when_they_uncover_tiles() {
+        let mut board = Board::from_grid("*...\n....\n...*\n", 1);
+        board.flood_discovery(&at(0, 0));
+        let mut clicks = ClickCounter::default();
+
+        // Missing its flag
+        clicks.count(ActionKind::Chord, at(2, 1), &board);
+        board.try_toggle_mark(at(3, 0), PlayerId(0));
+        clicks.count(ActionKind::Chord, at(2, 1), &board);
+        // Nothing left around it to uncover
+        clicks.count(ActionKind::Chord, at(1, 0), &board);
+        clicks.count(ActionKind::Hint, at(1, 0), &board);
+
+        assert_eq!((clicks.chord, clicks.useful, clicks.wasted), (3, 1, 2));
+        assert_eq!(clicks.total(), 3);
+        assert_eq!(clicks.efficiency(3), Some(1.));
+    }
+}
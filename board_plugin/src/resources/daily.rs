@@ -0,0 +1,268 @@
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::generator;
+use crate::resources::tile_map::TileMap;
+use crate::resources::{BoardOptions, BoardShape, GameRules, Neighborhood};
+use crate::storage;
+
+const DAILY_SIZE: (u16, u16) = (30, 16);
+const DAILY_MINES: u16 = 99;
+const HISTORY_VERSION: u16 = 1;
+
+/// Days since 1970-01-01 in UTC, so everyone shares the same daily board
+pub fn today() -> u32 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    (seconds / 86_400) as u32
+}
+
+/// `YYYY-MM-DD` for a day counted from 1970-01-01
+pub fn date_string(day: u32) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let z = day as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+/// The board of the day. Only the first attempt of each day is scored,
+/// later ones are practice.
+#[derive(Debug, Copy, Clone, Resource)]
+pub struct DailyChallenge {
+    pub day: u32,
+    pub scored: bool,
+}
+
+impl DailyChallenge {
+    pub fn new(day: u32, history: &DailyHistory) -> Self {
+        Self {
+            day,
+            scored: !history.has_attempt(day),
+        }
+    }
+
+    pub fn today(history: &DailyHistory) -> Self {
+        Self::new(today(), history)
+    }
+
+    /// SplitMix64 of the day, stable across versions and platforms
+    pub fn seed(&self) -> u64 {
+        let mut z = (self.day as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Expert sized board with the seed of the day
+    pub fn apply_to(&self, options: &mut BoardOptions) {
+        options.map_size = DAILY_SIZE;
+        options.bomb_count = DAILY_MINES;
+        options.seed = Some(self.seed());
+        options.generator = generator::UNIFORM.to_string();
+        options.layout = None;
+        options.start = None;
+        options.safe_start = true;
+        options.rules = GameRules::Classic;
+        options.shape = BoardShape::Square;
+        options.wrap = false;
+        options.neighborhood = Neighborhood::Adjacent;
+        options.layers = 1;
+        options.cell_mines = 1;
+        options.lives = 1;
+        options.practice = false;
+        options.mask = None;
+    }
+
+    pub fn is_daily_board(&self, tile_map: &TileMap) -> bool {
+        tile_map.seed == Some(self.seed())
+            && (tile_map.width, tile_map.height) == DAILY_SIZE
+            && tile_map.bomb_count == DAILY_MINES
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub day: u32,
+    pub won: bool,
+    /// Milliseconds from the start of the game to its end, 0 for attempts
+    /// left unfinished
+    pub time: u32,
+    /// Hints the player asked for, assisted wins don't extend the streak
+    #[serde(default)]
+    pub hints: u32,
+}
+
+impl DailyResult {
+    pub fn is_assisted(&self) -> bool {
+        self.hints > 0
+    }
+
+    fn extends_streak(&self) -> bool {
+        self.won && !self.is_assisted()
+    }
+}
+
+/// Scored daily attempts, stored in the data directory
+#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+pub struct DailyHistory {
+    pub version: u16,
+    pub results: Vec<DailyResult>,
+    /// Consecutive days won without hints, up to the last scored attempt
+    pub streak: u32,
+    pub longest_streak: u32,
+}
+
+impl Default for DailyHistory {
+    fn default() -> Self {
+        Self {
+            version: HISTORY_VERSION,
+            results: Vec::new(),
+            streak: 0,
+            longest_streak: 0,
+        }
+    }
+}
+
+impl DailyHistory {
+    pub fn path() -> PathBuf {
+        storage::data_file("daily.json")
+    }
+
+    pub fn load() -> Self {
+        storage::load_json(&Self::path())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save_json(&Self::path(), self)
+    }
+
+    pub fn has_attempt(&self, day: u32) -> bool {
+        self.result(day).is_some()
+    }
+
+    pub fn result(&self, day: u32) -> Option<&DailyResult> {
+        self.results.iter().find(|result| result.day == day)
+    }
+
+    /// Starts the scored attempt of a day as a loss, so leaving the game
+    /// before it ends still counts. `false` if the day already has one.
+    pub fn begin(&mut self, day: u32) -> bool {
+        if self.has_attempt(day) {
+            return false;
+        }
+        self.results.push(DailyResult {
+            day,
+            won: false,
+            time: 0,
+            hints: 0,
+        });
+        self.update_streaks();
+        true
+    }
+
+    /// Settles the attempt started by `begin` with how the game ended
+    pub fn finish(&mut self, result: DailyResult) {
+        match self.results.iter_mut().find(|r| r.day == result.day) {
+            Some(attempt) => *attempt = result,
+            None => self.results.push(result),
+        }
+        self.update_streaks();
+    }
+
+    /// Streaks counted again from the results, the longest one is kept even
+    /// if older results are gone
+    fn update_streaks(&mut self) {
+        let mut streak = 0;
+        let mut previous: Option<&DailyResult> = None;
+        for result in self.results.iter() {
+            let won_yesterday = previous.is_some_and(|previous| {
+                previous.extends_streak() && previous.day + 1 == result.day
+            });
+            streak = match (result.extends_streak(), won_yesterday) {
+                (false, _) => 0,
+                (true, true) => streak + 1,
+                (true, false) => 1,
+            };
+            self.longest_streak = self.longest_streak.max(streak);
+            previous = Some(result);
+        }
+        self.streak = streak;
+    }
+
+    /// Streak as of `day`: it is lost once a whole day goes by without a win
+    pub fn current_streak(&self, day: u32) -> u32 {
+        match self.results.last() {
+            Some(last) if last.extends_streak() && last.day + 1 >= day => self.streak,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn won(day: u32, hints: u32) -> DailyResult {
+        DailyResult {
+            day,
+            won: true,
+            time: 60_000,
+            hints,
+        }
+    }
+
+    #[test]
+    fn attempts_count_once_and_as_lost_until_finished() {
+        let mut history = DailyHistory::default();
+        assert!(history.begin(10));
+        history.finish(won(10, 0));
+        assert!(history.begin(11));
+        assert!(!history.begin(11));
+        assert!(!DailyChallenge::new(11, &history).scored);
+        // Day 11 was left unfinished
+        assert!(!history.result(11).unwrap().won);
+        assert_eq!(history.streak, 0);
+        assert_eq!(history.longest_streak, 1);
+    }
+
+    #[test]
+    fn finishing_keeps_the_streak_going() {
+        let mut history = DailyHistory::default();
+        for day in 10..13 {
+            history.begin(day);
+            assert_eq!(history.streak, 0);
+            history.finish(won(day, 0));
+        }
+        assert_eq!(history.streak, 3);
+        assert_eq!(history.current_streak(13), 3);
+        assert_eq!(history.current_streak(14), 0);
+    }
+
+    #[test]
+    fn assisted_wins_break_the_streak() {
+        let mut history = DailyHistory::default();
+        history.begin(10);
+        history.finish(won(10, 0));
+        history.begin(11);
+        history.finish(won(11, 2));
+        assert_eq!(history.streak, 0);
+        assert_eq!(history.current_streak(12), 0);
+        history.begin(12);
+        history.finish(won(12, 0));
+        assert_eq!(history.streak, 1);
+        assert_eq!(history.longest_streak, 1);
+    }
+}
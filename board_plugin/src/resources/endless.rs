@@ -0,0 +1,425 @@
+//! Board without edges for endless mode. Mines are generated one chunk at a
+//! time from the world seed, so any chunk can be rebuilt at any time and
+//! only the ones that have been looked at are kept in memory.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use bevy::log;
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::resources::tile::Tile;
+use crate::storage;
+
+/// Tiles along each side of a chunk
+pub const CHUNK_SIZE: i32 = 16;
+/// Share of mines among the tiles, close to an intermediate board
+pub const DEFAULT_DENSITY: f64 = 0.16;
+/// Tiles a single reveal may uncover. Openings are unbounded in an endless
+/// world, the rest of a huge one is left for further clicks.
+const MAX_CASCADE: usize = 4096;
+const RECORD_VERSION: u16 = 1;
+
+/// Tile position in the endless world, unbounded in every direction
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct WorldCoordinates {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl WorldCoordinates {
+    pub const ORIGIN: Self = Self { x: 0, y: 0 };
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub const fn chunk(&self) -> ChunkCoordinates {
+        ChunkCoordinates {
+            x: self.x.div_euclid(CHUNK_SIZE),
+            y: self.y.div_euclid(CHUNK_SIZE),
+        }
+    }
+
+    /// The eight surrounding tiles
+    pub fn neighbors(self) -> impl Iterator<Item = Self> {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&offset| offset != (0, 0))
+            .map(move |(dx, dy)| Self::new(self.x + dx, self.y + dy))
+    }
+}
+
+impl Display for WorldCoordinates {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+/// Position of a chunk, in chunks
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Reflect, Serialize, Deserialize)]
+pub struct ChunkCoordinates {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl ChunkCoordinates {
+    /// Bottom left tile of the chunk
+    pub const fn origin(&self) -> WorldCoordinates {
+        WorldCoordinates::new(self.x * CHUNK_SIZE, self.y * CHUNK_SIZE)
+    }
+
+    pub fn tiles(self) -> impl Iterator<Item = WorldCoordinates> {
+        let origin = self.origin();
+        (0..CHUNK_SIZE).flat_map(move |y| {
+            (0..CHUNK_SIZE).map(move |x| WorldCoordinates::new(origin.x + x, origin.y + y))
+        })
+    }
+}
+
+/// Mines of a chunk, one bit per tile and one row per entry
+#[derive(Debug, Copy, Clone)]
+struct Chunk([u16; CHUNK_SIZE as usize]);
+
+impl Chunk {
+    fn generate(seed: u64, density: f64, coordinates: ChunkCoordinates) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(chunk_seed(seed, coordinates));
+        let origin = coordinates.origin();
+        let mut rows = [0; CHUNK_SIZE as usize];
+        for (y, row) in rows.iter_mut().enumerate() {
+            for x in 0..CHUNK_SIZE {
+                // Always draw, so the safe start does not shift the rest
+                let mine = rng.gen_bool(density);
+                let tile = WorldCoordinates::new(origin.x + x, origin.y + y as i32);
+                if mine && !is_start_area(tile) {
+                    *row |= 1 << x;
+                }
+            }
+        }
+        Self(rows)
+    }
+
+    fn is_mine(&self, x: i32, y: i32) -> bool {
+        self.0[y as usize] & (1 << x) != 0
+    }
+}
+
+/// The game starts on the origin, which is an opening
+fn is_start_area(coordinates: WorldCoordinates) -> bool {
+    coordinates.x.abs() <= 1 && coordinates.y.abs() <= 1
+}
+
+/// SplitMix64 of the world seed mixed with the chunk position, stable
+/// across versions and platforms
+fn chunk_seed(seed: u64, coordinates: ChunkCoordinates) -> u64 {
+    let position = ((coordinates.x as u32 as u64) << 32) | coordinates.y as u32 as u64;
+    let mix = |z: u64| {
+        let z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    mix(seed ^ mix(position))
+}
+
+/// State of an endless game. The score is the number of tiles revealed
+/// before hitting a mine.
+#[derive(Debug, Clone, Resource)]
+pub struct EndlessWorld {
+    pub seed: u64,
+    pub density: f64,
+    chunks: HashMap<ChunkCoordinates, Chunk>,
+    pub revealed: HashSet<WorldCoordinates>,
+    pub flagged: HashSet<WorldCoordinates>,
+    /// The mine that ended the game
+    pub exploded: Option<WorldCoordinates>,
+    /// Chunks whose tiles changed since the last `take_changed_chunks`
+    changed: HashSet<ChunkCoordinates>,
+}
+
+impl EndlessWorld {
+    pub fn new(seed: u64, density: f64) -> Self {
+        Self {
+            seed,
+            density,
+            chunks: HashMap::new(),
+            revealed: HashSet::new(),
+            flagged: HashSet::new(),
+            exploded: None,
+            changed: HashSet::new(),
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        self.revealed.len() as u32
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.exploded.is_some()
+    }
+
+    pub fn is_mine_at(&mut self, coordinates: WorldCoordinates) -> bool {
+        let chunk = coordinates.chunk();
+        let (seed, density) = (self.seed, self.density);
+        let origin = chunk.origin();
+        self.chunks
+            .entry(chunk)
+            .or_insert_with(|| Chunk::generate(seed, density, chunk))
+            .is_mine(coordinates.x - origin.x, coordinates.y - origin.y)
+    }
+
+    pub fn tile_at(&mut self, coordinates: WorldCoordinates) -> Tile {
+        if self.is_mine_at(coordinates) {
+            return Tile::Bomb(1);
+        }
+        let count = coordinates
+            .neighbors()
+            .filter(|&neighbor| self.is_mine_at(neighbor))
+            .count() as u8;
+        match count {
+            0 => Tile::Empty,
+            count => Tile::BombNeighbor(count),
+        }
+    }
+
+    pub fn is_revealed(&self, coordinates: &WorldCoordinates) -> bool {
+        self.revealed.contains(coordinates)
+    }
+
+    pub fn is_flagged(&self, coordinates: &WorldCoordinates) -> bool {
+        self.flagged.contains(coordinates)
+    }
+
+    /// Uncovers a tile, and the opening around it if it is empty. Returns
+    /// the number of tiles revealed, hitting a mine ends the game.
+    pub fn reveal(&mut self, coordinates: WorldCoordinates) -> usize {
+        if self.is_over() || self.is_revealed(&coordinates) || self.is_flagged(&coordinates) {
+            return 0;
+        }
+        if self.is_mine_at(coordinates) {
+            log::info!("Boom! Mine at {}", coordinates);
+            self.exploded = Some(coordinates);
+            self.changed.insert(coordinates.chunk());
+            return 0;
+        }
+
+        let mut revealed = 0;
+        let mut queue = VecDeque::from([coordinates]);
+        while let Some(coordinates) = queue.pop_front() {
+            if revealed >= MAX_CASCADE {
+                break;
+            }
+            if self.is_flagged(&coordinates) || !self.revealed.insert(coordinates) {
+                continue;
+            }
+            revealed += 1;
+            self.changed.insert(coordinates.chunk());
+            if self.tile_at(coordinates).is_empty() {
+                queue.extend(
+                    coordinates
+                        .neighbors()
+                        .filter(|neighbor| !self.is_revealed(neighbor)),
+                );
+            }
+        }
+        revealed
+    }
+
+    /// Reveals the covered neighbors of a number once it has as many flags
+    /// around it
+    pub fn chord(&mut self, coordinates: WorldCoordinates) -> usize {
+        if !self.is_revealed(&coordinates) {
+            return 0;
+        }
+        let count = match self.tile_at(coordinates) {
+            Tile::BombNeighbor(count) => count as usize,
+            _ => return 0,
+        };
+        let neighbors: Vec<_> = coordinates.neighbors().collect();
+        let flags = neighbors.iter().filter(|n| self.is_flagged(n)).count();
+        if flags != count {
+            return 0;
+        }
+        neighbors
+            .into_iter()
+            .map(|neighbor| self.reveal(neighbor))
+            .sum()
+    }
+
+    /// Flags or unflags a covered tile, returns whether it changed
+    pub fn toggle_flag(&mut self, coordinates: WorldCoordinates) -> bool {
+        if self.is_over() || self.is_revealed(&coordinates) {
+            return false;
+        }
+        if !self.flagged.remove(&coordinates) {
+            self.flagged.insert(coordinates);
+        }
+        self.changed.insert(coordinates.chunk());
+        true
+    }
+
+    pub fn take_changed_chunks(&mut self) -> HashSet<ChunkCoordinates> {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+/// Best endless score, stored in the data directory
+#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+pub struct EndlessRecord {
+    pub version: u16,
+    pub best: u32,
+    pub games: u32,
+}
+
+impl Default for EndlessRecord {
+    fn default() -> Self {
+        Self {
+            version: RECORD_VERSION,
+            best: 0,
+            games: 0,
+        }
+    }
+}
+
+impl EndlessRecord {
+    pub fn path() -> PathBuf {
+        storage::data_file("endless.json")
+    }
+
+    pub fn load() -> Self {
+        storage::load_json(&Self::path())
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::save_json(&Self::path(), self)
+    }
+
+    /// Counts a finished game, returns whether it set a new best
+    pub fn record(&mut self, score: u32) -> bool {
+        self.games += 1;
+        let best = score > self.best;
+        if best {
+            self.best = score;
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// World without mines but the one given
+    fn world_with_mine(mine: WorldCoordinates) -> EndlessWorld {
+        let mut world = EndlessWorld::new(0, 0.);
+        let (chunk, origin) = (mine.chunk(), mine.chunk().origin());
+        let mut rows = [0; CHUNK_SIZE as usize];
+        rows[(mine.y - origin.y) as usize] |= 1 << (mine.x - origin.x);
+        world.chunks.insert(chunk, Chunk(rows));
+        world
+    }
+
+    #[test]
+    fn chunks_repeat_per_seed_and_position() {
+        let chunks = [(0, 0), (-1, 0), (0, -1), (-3, -7), (-100_000, 5)]
+            .map(|(x, y)| ChunkCoordinates { x, y });
+        for chunk in chunks {
+            let first = Chunk::generate(42, 0.5, chunk);
+            assert_eq!(first.0, Chunk::generate(42, 0.5, chunk).0);
+            assert_ne!(first.0, Chunk::generate(43, 0.5, chunk).0);
+        }
+        // Mirrored chunks don't share their mines
+        let (left, right) = (
+            ChunkCoordinates { x: -1, y: 0 },
+            ChunkCoordinates { x: 1, y: 0 },
+        );
+        assert_ne!(
+            Chunk::generate(42, 0.5, left).0,
+            Chunk::generate(42, 0.5, right).0
+        );
+
+        // Tiles left of and below the origin fall in negative chunks, the
+        // order chunks are visited in doesn't matter
+        assert_eq!(
+            WorldCoordinates::new(-1, -16).chunk(),
+            ChunkCoordinates { x: -1, y: -1 }
+        );
+        assert_eq!(
+            WorldCoordinates::new(-17, 15).chunk(),
+            ChunkCoordinates { x: -2, y: 0 }
+        );
+        let tiles: Vec<_> = (-40..40)
+            .flat_map(|y| (-40..40).map(move |x| WorldCoordinates::new(x, y)))
+            .collect();
+        let (mut forward, mut backward) = (EndlessWorld::new(7, 0.2), EndlessWorld::new(7, 0.2));
+        let mines: Vec<_> = tiles.iter().map(|tile| forward.is_mine_at(*tile)).collect();
+        let reversed: Vec<_> = tiles
+            .iter()
+            .rev()
+            .map(|tile| backward.is_mine_at(*tile))
+            .collect();
+        assert!(mines.iter().rev().eq(reversed.iter()));
+    }
+
+    #[test]
+    fn the_start_area_holds_no_mine() {
+        let mut world = EndlessWorld::new(1, 1.);
+        for tile in WorldCoordinates::ORIGIN.neighbors() {
+            assert!(!world.is_mine_at(tile));
+        }
+        assert!(!world.is_mine_at(WorldCoordinates::ORIGIN));
+        assert!(world.is_mine_at(WorldCoordinates::new(2, 0)));
+        assert!(world.is_mine_at(WorldCoordinates::new(-2, -2)));
+
+        // The origin is an opening, its neighbors border the mines
+        assert_eq!(world.tile_at(WorldCoordinates::ORIGIN), Tile::Empty);
+        assert_eq!(
+            world.tile_at(WorldCoordinates::new(1, 1)),
+            Tile::BombNeighbor(5)
+        );
+        assert_eq!(world.reveal(WorldCoordinates::ORIGIN), 9);
+        assert!(!world.is_over());
+    }
+
+    #[test]
+    fn reveals_stop_at_the_cascade_limit() {
+        let mut world = EndlessWorld::new(1, 0.);
+        assert_eq!(world.reveal(WorldCoordinates::ORIGIN), MAX_CASCADE);
+        assert_eq!(world.score(), MAX_CASCADE as u32);
+        // A further click goes on from the edge of the opening
+        let edge = (1..)
+            .map(|x| WorldCoordinates::new(x, 0))
+            .find(|tile| !world.is_revealed(tile))
+            .unwrap();
+        assert_eq!(world.reveal(edge), MAX_CASCADE);
+    }
+
+    #[test]
+    fn chords_need_as_many_flags_as_mines() {
+        let mine = WorldCoordinates::new(-4, -1);
+        let number = WorldCoordinates::new(-5, -1);
+        let mut world = world_with_mine(mine);
+        assert_eq!(world.tile_at(number), Tile::BombNeighbor(1));
+        assert_eq!(world.reveal(number), 1);
+
+        assert_eq!(world.chord(number), 0);
+        world.toggle_flag(mine);
+        world.toggle_flag(WorldCoordinates::new(-4, 0));
+        assert_eq!(world.chord(number), 0);
+        assert_eq!(world.score(), 1);
+
+        world.toggle_flag(WorldCoordinates::new(-4, 0));
+        assert!(world.chord(number) > 0);
+        assert!(!world.is_over());
+        assert!(world.is_revealed(&WorldCoordinates::new(-6, -1)));
+        assert!(!world.is_revealed(&mine));
+        // Covered tiles aren't chorded
+        assert_eq!(world.chord(WorldCoordinates::new(100, 100)), 0);
+    }
+}
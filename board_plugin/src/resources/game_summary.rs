@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{Coordinates, PlayerId};
+use crate::resources::clicks::ClickCounter;
+use crate::resources::tile_map::TileMap;
+
+#[derive(Debug, Default, Copy, Clone, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub player: PlayerId,
+    pub tiles_revealed: u32,
+    pub correct_flags: u32,
+    pub hit_mine: bool,
+}
+
+/// End of game report, inserted once the board is cleared or a mine goes
+/// off. Its presence also stops the board from taking more input.
+#[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+pub struct GameSummary {
+    pub cleared: bool,
+    pub players: Vec<PlayerStats>,
+    /// Milliseconds the game lasted, only known for local games
+    #[serde(default)]
+    pub time: Option<u32>,
+    #[serde(default)]
+    pub clicks: Option<ClickCounter>,
+    /// Hints the player asked for, only given in local games
+    #[serde(default)]
+    pub hints: u32,
+}
+
+impl GameSummary {
+    /// `revealed_by` pairs revealed tiles with the player who revealed them,
+    /// tiles revealed on the board's behalf are left out
+    pub fn new(
+        tile_map: &TileMap,
+        revealed_by: impl IntoIterator<Item = (Coordinates, PlayerId)>,
+        marks: impl IntoIterator<Item = (Coordinates, PlayerId)>,
+        exploded_by: Option<PlayerId>,
+    ) -> Self {
+        let mut players: BTreeMap<PlayerId, PlayerStats> = BTreeMap::new();
+
+        for (coordinates, player) in revealed_by {
+            if !tile_map.is_bomb_at(coordinates) {
+                player_stats(&mut players, player).tiles_revealed += 1;
+            }
+        }
+        for (coordinates, player) in marks {
+            let player_stats = player_stats(&mut players, player);
+            if tile_map.is_bomb_at(coordinates) {
+                player_stats.correct_flags += 1;
+            }
+        }
+        if let Some(player) = exploded_by {
+            player_stats(&mut players, player).hit_mine = true;
+        }
+
+        Self {
+            cleared: exploded_by.is_none(),
+            players: players.into_values().collect(),
+            time: None,
+            clicks: None,
+            hints: 0,
+        }
+    }
+
+    pub fn mine_hit_by(&self) -> Option<PlayerId> {
+        self.players
+            .iter()
+            .find(|stats| stats.hit_mine)
+            .map(|stats| stats.player)
+    }
+
+    /// Whether the player got help from hints
+    pub fn is_assisted(&self) -> bool {
+        self.hints > 0
+    }
+}
+
+fn player_stats(
+    players: &mut BTreeMap<PlayerId, PlayerStats>,
+    player: PlayerId,
+) -> &mut PlayerStats {
+    players.entry(player).or_insert(PlayerStats {
+        player,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,534 @@
+//! Mine layout generators. `BoardOptions::generator` names the one used
+//! for new boards, host apps can add their own to `MineGenerators`.
+
+use std::path::Path;
+
+use bevy::log;
+use bevy::prelude::Resource;
+use bevy::utils::HashMap;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::components::Coordinates;
+use crate::resources::cells::CellSet;
+use crate::resources::layout::LayoutError;
+use crate::resources::tile::Tile;
+use crate::resources::tile_map::TileMap;
+use crate::resources::topology::TopologyKind;
+use crate::resources::BoardOptions;
+
+pub const UNIFORM: &str = "uniform";
+pub const SAFE_ZONE: &str = "safe-zone";
+pub const NO_GUESS: &str = "no-guess";
+pub const CLUSTERED: &str = "clustered";
+pub const GRADIENT: &str = "gradient";
+pub const FROM_FILE: &str = "from-file";
+
+/// Board a generator places mines on
+#[derive(Debug, Clone)]
+pub struct GeneratorContext {
+    pub width: u16,
+    pub height: u16,
+    /// Cells to place mines in, see `BoardOptions::mine_cells`
+    pub bomb_count: u16,
+    pub topology: TopologyKind,
+    /// Cells of the rectangle that are not part of the board
+    pub holes: CellSet,
+    /// Tile the player opens with: the chosen start, or the board center
+    pub start: Coordinates,
+    /// Most mines a cell holds, `TileMap::generate` stacks the extra mines
+    /// on the picked cells afterwards
+    pub cell_mines: u8,
+}
+
+impl GeneratorContext {
+    pub fn new(options: &BoardOptions) -> Self {
+        let (width, height) = options.grid_size();
+        let topology = options.topology();
+        Self {
+            width,
+            height,
+            bomb_count: options.mine_cells(),
+            topology,
+            holes: options.hole_set(),
+            start: options
+                .start
+                .unwrap_or_else(|| topology.topology().center((width, height))),
+            cell_mines: options.cell_mines.max(1),
+        }
+    }
+
+    pub fn contains(&self, coordinates: Coordinates) -> bool {
+        self.topology
+            .topology()
+            .contains(coordinates, (self.width, self.height))
+            && !self.holes.contains(&coordinates)
+    }
+
+    /// Whether `coordinates` lies within `radius` steps of the start
+    pub fn is_near_start(&self, coordinates: Coordinates, radius: u16) -> bool {
+        self.topology
+            .topology()
+            .distance(coordinates, self.start, (self.width, self.height))
+            <= radius
+    }
+}
+
+/// Places the mines of new boards. Generators only pick mine positions,
+/// `TileMap::place_mines` derives the numbers from them.
+pub trait MineGenerator: Send + Sync {
+    /// Distinct mine positions inside the board. All randomness must come
+    /// from `rng` so that seeded boards can be rebuilt.
+    fn generate(&self, context: &GeneratorContext, rng: &mut ChaCha8Rng) -> Vec<Coordinates>;
+}
+
+/// Registered generators by name
+#[derive(Resource)]
+pub struct MineGenerators {
+    generators: HashMap<String, Box<dyn MineGenerator>>,
+}
+
+impl Default for MineGenerators {
+    /// The built-in generators. `FromFile` needs its file and is registered
+    /// by the app once it is loaded.
+    fn default() -> Self {
+        let mut generators = Self {
+            generators: HashMap::new(),
+        };
+        generators.register(UNIFORM, Uniform);
+        generators.register(SAFE_ZONE, SafeZone::default());
+        generators.register(NO_GUESS, NoGuess::default());
+        generators.register(CLUSTERED, Clustered::default());
+        generators.register(GRADIENT, Gradient::default());
+        generators
+    }
+}
+
+impl MineGenerators {
+    /// Adds `generator`, replacing any generator already registered as `name`
+    pub fn register(&mut self, name: impl Into<String>, generator: impl MineGenerator + 'static) {
+        self.generators.insert(name.into(), Box::new(generator));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn MineGenerator> {
+        self.generators
+            .get(name)
+            .map(|generator| generator.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.generators.keys().map(String::as_str)
+    }
+}
+
+/// Every tile is equally likely to hold a mine
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Uniform;
+
+impl MineGenerator for Uniform {
+    fn generate(&self, context: &GeneratorContext, rng: &mut ChaCha8Rng) -> Vec<Coordinates> {
+        place_uniform(context, rng, |_| true, Vec::new())
+    }
+}
+
+/// Uniform placement that keeps the tiles around the start free of mines,
+/// so the first click always opens an area
+#[derive(Debug, Copy, Clone)]
+pub struct SafeZone {
+    pub radius: u16,
+}
+
+impl Default for SafeZone {
+    fn default() -> Self {
+        Self { radius: 1 }
+    }
+}
+
+impl MineGenerator for SafeZone {
+    fn generate(&self, context: &GeneratorContext, rng: &mut ChaCha8Rng) -> Vec<Coordinates> {
+        place_uniform(
+            context,
+            rng,
+            |coordinates| !context.is_near_start(coordinates, self.radius),
+            Vec::new(),
+        )
+    }
+}
+
+/// Safe zone boards that can be cleared from the start without guessing.
+/// Gives up after `attempts` layouts and keeps the last one. Stacked mines
+/// are placed after the check, so boards whose cells hold several mines get
+/// a plain safe zone.
+#[derive(Debug, Copy, Clone)]
+pub struct NoGuess {
+    pub attempts: u32,
+}
+
+impl Default for NoGuess {
+    fn default() -> Self {
+        Self { attempts: 500 }
+    }
+}
+
+impl MineGenerator for NoGuess {
+    fn generate(&self, context: &GeneratorContext, rng: &mut ChaCha8Rng) -> Vec<Coordinates> {
+        if context.cell_mines > 1 {
+            log::warn!("No guess boards hold a single mine per cell, using a safe zone");
+            return SafeZone::default().generate(context, rng);
+        }
+        let mut mines = Vec::new();
+        for _ in 0..self.attempts.max(1) {
+            mines = SafeZone::default().generate(context, rng);
+            let mut tile_map = TileMap::empty_masked(
+                context.topology,
+                context.width,
+                context.height,
+                context.holes.clone(),
+            );
+            tile_map.place_mines(&mines);
+            if is_solvable(&tile_map, context.start) {
+                return mines;
+            }
+        }
+        log::warn!(
+            "No guess free layout found in {} attempts, the board may need a guess",
+            self.attempts
+        );
+        mines
+    }
+}
+
+/// Mines gather in clumps around random centers, leaving wide open areas
+/// between them
+#[derive(Debug, Copy, Clone)]
+pub struct Clustered {
+    /// Average number of mines per cluster
+    pub cluster_size: u16,
+    /// How far mines stray from their cluster center, in tiles
+    pub spread: f32,
+}
+
+impl Default for Clustered {
+    fn default() -> Self {
+        Self {
+            cluster_size: 8,
+            spread: 1.5,
+        }
+    }
+}
+
+impl MineGenerator for Clustered {
+    fn generate(&self, context: &GeneratorContext, rng: &mut ChaCha8Rng) -> Vec<Coordinates> {
+        let count = mine_count(context, |_| true);
+        let centers: Vec<(f32, f32)> = (0..(count / self.cluster_size.max(1)).max(1))
+            .map(|_| {
+                (
+                    rng.gen_range(0..context.width) as f32,
+                    rng.gen_range(0..context.height) as f32,
+                )
+            })
+            .collect();
+
+        let mut mines = Vec::with_capacity(count as usize);
+        let mut placed = CellSet::new(context.width, context.height);
+        for _ in 0..count as u32 * 20 {
+            if mines.len() == count as usize {
+                break;
+            }
+            let (x, y) = centers[rng.gen_range(0..centers.len())];
+            // Sum of uniforms, roughly normal around the center
+            let mut offset = || (rng.gen::<f32>() + rng.gen::<f32>() - 1.) * 2. * self.spread;
+            let (x, y) = ((x + offset()).round(), (y + offset()).round());
+            if x < 0. || y < 0. {
+                continue;
+            }
+            let coordinates = Coordinates {
+                x: x as u16,
+                y: y as u16,
+            };
+            if context.contains(coordinates) && placed.insert(coordinates) {
+                mines.push(coordinates);
+            }
+        }
+        // Crowded clusters are topped up anywhere on the board
+        place_uniform(context, rng, |_| true, mines)
+    }
+}
+
+/// Mine density changes steadily from the left edge to the right edge
+#[derive(Debug, Copy, Clone)]
+pub struct Gradient {
+    /// Relative density of the leftmost column
+    pub left: f32,
+    /// Relative density of the rightmost column
+    pub right: f32,
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Self {
+            left: 0.2,
+            right: 1.8,
+        }
+    }
+}
+
+impl MineGenerator for Gradient {
+    fn generate(&self, context: &GeneratorContext, rng: &mut ChaCha8Rng) -> Vec<Coordinates> {
+        let count = mine_count(context, |_| true);
+        let span = context.width.saturating_sub(1).max(1) as f32;
+        let weights: Vec<f32> = (0..context.width)
+            .map(|x| (self.left + (self.right - self.left) * x as f32 / span).max(0.))
+            .collect();
+        let total: f32 = weights.iter().sum();
+
+        let mut mines = Vec::with_capacity(count as usize);
+        let mut placed = CellSet::new(context.width, context.height);
+        for _ in 0..count as u32 * 20 {
+            if mines.len() == count as usize || total <= 0. {
+                break;
+            }
+            let mut target = rng.gen::<f32>() * total;
+            let x = weights
+                .iter()
+                .position(|weight| {
+                    target -= weight;
+                    target < 0.
+                })
+                .unwrap_or(weights.len() - 1);
+            let coordinates = Coordinates {
+                x: x as u16,
+                y: rng.gen_range(0..context.height),
+            };
+            if context.contains(coordinates) && placed.insert(coordinates) {
+                mines.push(coordinates);
+            }
+        }
+        place_uniform(context, rng, |_| true, mines)
+    }
+}
+
+/// Mines of a board file, the same on every game. Unlike
+/// `BoardOptions::set_layout` the board keeps the options' size: the
+/// options' mine count is ignored and mines outside the board are dropped.
+#[derive(Debug, Clone)]
+pub struct FromFile {
+    pub mines: Vec<Coordinates>,
+}
+
+impl FromFile {
+    /// Reads an `.mbf` file or a text grid
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LayoutError> {
+        Ok(Self {
+            mines: TileMap::load(path)?.mines(),
+        })
+    }
+}
+
+impl MineGenerator for FromFile {
+    fn generate(&self, context: &GeneratorContext, _: &mut ChaCha8Rng) -> Vec<Coordinates> {
+        let mut placed = CellSet::new(context.width, context.height);
+        // Generators pick cells, stacked mines of the file count once
+        self.mines
+            .iter()
+            .copied()
+            .filter(|mine| context.contains(*mine) && placed.insert(*mine))
+            .collect()
+    }
+}
+
+/// Mines to place, capped so that at least one allowed tile stays free
+fn mine_count(context: &GeneratorContext, allowed: impl Fn(Coordinates) -> bool) -> u16 {
+    let free = (0..context.height)
+        .flat_map(|y| (0..context.width).map(move |x| Coordinates { x, y }))
+        .filter(|coordinates| context.contains(*coordinates) && allowed(*coordinates))
+        .count();
+    context.bomb_count.min(free.saturating_sub(1) as u16)
+}
+
+/// Adds uniformly placed mines to `mines` on allowed tiles until there are
+/// enough of them
+fn place_uniform(
+    context: &GeneratorContext,
+    rng: &mut ChaCha8Rng,
+    allowed: impl Fn(Coordinates) -> bool,
+    mut mines: Vec<Coordinates>,
+) -> Vec<Coordinates> {
+    let count = mine_count(context, &allowed) as usize;
+    let mut placed = CellSet::new(context.width, context.height);
+    placed.extend(mines.iter().copied());
+    while mines.len() < count {
+        let coordinates = Coordinates {
+            x: rng.gen_range(0..context.width),
+            y: rng.gen_range(0..context.height),
+        };
+        if context.contains(coordinates) && allowed(coordinates) && placed.insert(coordinates) {
+            mines.push(coordinates);
+        }
+    }
+    mines
+}
+
+/// Spreads `total` mines over the cells picked by a generator: every cell
+/// gets one, the rest land at random on cells with room left for
+/// `cell_mines`. Stacked mines are repeated coordinates.
+pub fn stack_mines(
+    cells: &[Coordinates],
+    total: u16,
+    cell_mines: u8,
+    rng: &mut ChaCha8Rng,
+) -> Vec<Coordinates> {
+    let mut room: Vec<Coordinates> = cells
+        .iter()
+        .flat_map(|cell| std::iter::repeat(*cell).take(cell_mines.saturating_sub(1) as usize))
+        .collect();
+    let extra = (total as usize).saturating_sub(cells.len()).min(room.len());
+    let (stacked, _) = room.partial_shuffle(rng, extra);
+
+    let mut mines = cells.to_vec();
+    mines.extend_from_slice(stacked);
+    mines
+}
+
+/// Plays the board from `start` using only deductions from single numbers
+/// and from pairs of overlapping numbers, and tells whether that clears it
+fn is_solvable(tile_map: &TileMap, start: Coordinates) -> bool {
+    let mut revealed = CellSet::new(tile_map.width, tile_map.height);
+    let mut flagged = CellSet::new(tile_map.width, tile_map.height);
+    if tile_map.is_bomb_at(start) {
+        return false;
+    }
+    revealed.extend(tile_map.flood_fill(start, |_| false));
+
+    loop {
+        if revealed.len() == tile_map.safe_count() {
+            return true;
+        }
+
+        // Remaining mines and unknown neighbors of every revealed number
+        let constraints: Vec<(u8, Vec<Coordinates>)> = revealed
+            .iter()
+            .filter_map(|coordinates| match tile_map.tile_at(coordinates) {
+                Some(Tile::BombNeighbor(count)) => Some((coordinates, count)),
+                _ => None,
+            })
+            .filter_map(|(coordinates, count)| {
+                let neighbors = tile_map
+                    .get_neighbor_coordinates(coordinates)
+                    .filter(|neighbor| tile_map.contains(*neighbor));
+                let mut unknown = Vec::new();
+                let mut flags = 0;
+                for neighbor in neighbors {
+                    if flagged.contains(&neighbor) {
+                        flags += 1;
+                    } else if !revealed.contains(&neighbor) {
+                        unknown.push(neighbor);
+                    }
+                }
+                (!unknown.is_empty()).then(|| (count - flags, unknown))
+            })
+            .collect();
+
+        let mut safe = Vec::new();
+        let mut mines = Vec::new();
+        for (remaining, unknown) in constraints.iter() {
+            if *remaining == 0 {
+                safe.extend(unknown.iter().copied());
+            } else if *remaining as usize == unknown.len() {
+                mines.extend(unknown.iter().copied());
+            }
+        }
+        if safe.is_empty() && mines.is_empty() {
+            for (a_remaining, a) in constraints.iter() {
+                for (b_remaining, b) in constraints.iter() {
+                    if a.len() >= b.len() || !a.iter().all(|cell| b.contains(cell)) {
+                        continue;
+                    }
+                    // `a` is inside `b`, the rest of `b` holds the difference
+                    let rest = b.iter().filter(|cell| !a.contains(cell)).copied();
+                    let difference = b_remaining.saturating_sub(*a_remaining) as usize;
+                    if difference == 0 {
+                        safe.extend(rest);
+                    } else if difference == b.len() - a.len() {
+                        mines.extend(rest);
+                    }
+                }
+            }
+        }
+        if safe.is_empty() && mines.is_empty() {
+            return false;
+        }
+
+        flagged.extend(mines);
+        for coordinates in safe {
+            if !revealed.contains(&coordinates) {
+                revealed.extend(tile_map.flood_fill(coordinates, |_| false));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn context() -> GeneratorContext {
+        GeneratorContext::new(&BoardOptions {
+            map_size: (16, 16),
+            bomb_count: 40,
+            ..Default::default()
+        })
+    }
+
+    fn generate(generator: &dyn MineGenerator, seed: u64) -> Vec<Coordinates> {
+        generator.generate(&context(), &mut ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn seeds_repeat_their_layout() {
+        let generators = MineGenerators::default();
+        for name in generators.names() {
+            let generator = generators.get(name).unwrap();
+            let mines = generate(generator, 7);
+            assert_eq!(mines.len(), 40, "{}", name);
+            assert_eq!(mines, generate(generator, 7), "{}", name);
+            assert_ne!(mines, generate(generator, 8), "{}", name);
+        }
+    }
+
+    #[test]
+    fn safe_zones_hold_no_mines() {
+        let context = context();
+        let generators: [&dyn MineGenerator; 2] = [&SafeZone::default(), &NoGuess::default()];
+        for generator in generators {
+            for seed in 0..10 {
+                let mines = generate(generator, seed);
+                assert!(mines.iter().all(|mine| !context.is_near_start(*mine, 1)));
+            }
+        }
+    }
+
+    #[test]
+    fn file_mines_outside_the_board_are_dropped() {
+        let inside = Coordinates { x: 3, y: 15 };
+        let generator = FromFile {
+            mines: vec![inside, Coordinates { x: 16, y: 0 }, inside],
+        };
+        assert_eq!(generate(&generator, 1), vec![inside]);
+    }
+
+    #[test]
+    fn solvable_layouts_need_no_guess() {
+        // The numbers under the mine only tell its neighbors apart together
+        let tile_map: TileMap = ".*.\n...\n...\n...\n".parse().unwrap();
+        assert!(is_solvable(&tile_map, Coordinates { x: 1, y: 0 }));
+        assert!(!is_solvable(&tile_map, Coordinates { x: 1, y: 3 }));
+
+        // Both numbers see the same two tiles, one of them the mine
+        let tile_map: TileMap = "*.\n..\n..\n".parse().unwrap();
+        assert!(!is_solvable(&tile_map, Coordinates { x: 0, y: 0 }));
+    }
+}
@@ -0,0 +1,22 @@
+use bevy::prelude::Resource;
+use bevy::tasks::Task;
+
+use crate::components::Coordinates;
+use crate::solver::{Hint, SolverError, VisibleBoard};
+
+/// Hints given on the current board, a game with any is assisted
+#[derive(Debug, Default, Copy, Clone, Resource)]
+pub struct HintCounter {
+    pub used: u32,
+    /// Tile the last hint points at, until it is uncovered or flagged
+    pub target: Option<Coordinates>,
+}
+
+/// Hint being worked out on the async compute pool, the solver can take
+/// seconds on big boards
+#[derive(Resource)]
+pub struct HintSearch {
+    /// What the player could see when the search started
+    pub board: VisibleBoard,
+    pub task: Task<Result<Option<Hint>, SolverError>>,
+}
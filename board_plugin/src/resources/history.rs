@@ -0,0 +1,259 @@
+use bevy::prelude::Resource;
+
+use crate::components::{Coordinates, PlayerId};
+use crate::resources::board::Board;
+
+/// A single change to the play state of a board
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Change {
+    /// A tile got uncovered, by a click or by the opening around it
+    Reveal(Coordinates),
+    /// A mine cost a life
+    Detonate(Coordinates),
+    /// The flag of a tile went from one level to another, 0 is no flag
+    Flag {
+        coordinates: Coordinates,
+        player: PlayerId,
+        from: u8,
+        to: u8,
+    },
+}
+
+impl Change {
+    pub fn coordinates(&self) -> Coordinates {
+        match *self {
+            Self::Reveal(coordinates) | Self::Detonate(coordinates) => coordinates,
+            Self::Flag { coordinates, .. } => coordinates,
+        }
+    }
+
+    fn apply(&self, board: &mut Board) {
+        match *self {
+            Self::Reveal(coordinates) => {
+                board.coordinates_discovered.insert(coordinates);
+            }
+            Self::Detonate(coordinates) => {
+                board.detonated.insert(coordinates);
+            }
+            Self::Flag {
+                coordinates,
+                player,
+                to,
+                ..
+            } => board.set_flag_level(coordinates, player, to),
+        }
+    }
+
+    fn revert(&self, board: &mut Board) {
+        match *self {
+            Self::Reveal(coordinates) => {
+                board.coordinates_discovered.remove(&coordinates);
+            }
+            Self::Detonate(coordinates) => {
+                board.detonated.remove(&coordinates);
+            }
+            Self::Flag {
+                coordinates,
+                player,
+                from,
+                ..
+            } => board.set_flag_level(coordinates, player, from),
+        }
+    }
+}
+
+/// Undo and redo stacks of a practice game. Every reveal, chord or flag
+/// click is one move, however many tiles it changed.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct UndoHistory {
+    done: Vec<Vec<Change>>,
+    undone: Vec<Vec<Change>>,
+    /// Changes of the move being played
+    pending: Vec<Change>,
+}
+
+impl UndoHistory {
+    pub fn record(&mut self, change: Change) {
+        self.pending.push(change);
+    }
+
+    /// Closes the move being played, a new move drops the undone ones
+    pub fn commit(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        self.done.push(std::mem::take(&mut self.pending));
+        self.undone.clear();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Takes back the last move, returns what it changed
+    pub fn undo(&mut self, board: &mut Board) -> Option<Vec<Change>> {
+        let changes = self.done.pop()?;
+        for change in changes.iter().rev() {
+            change.revert(board);
+        }
+        self.undone.push(changes.clone());
+        Some(changes)
+    }
+
+    /// Plays the last undone move again, returns what it changed
+    pub fn redo(&mut self, board: &mut Board) -> Option<Vec<Change>> {
+        let changes = self.undone.pop()?;
+        for change in changes.iter() {
+            change.apply(board);
+        }
+        self.done.push(changes.clone());
+        Some(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "*...\n....\n...*\n";
+    const PLAYER: PlayerId = PlayerId(0);
+
+    type State = (
+        Vec<Coordinates>,
+        Vec<(Coordinates, PlayerId)>,
+        Vec<(Coordinates, u8)>,
+        Vec<Coordinates>,
+    );
+
+    fn state(board: &Board) -> State {
+        (
+            board.coordinates_discovered.iter().collect(),
+            board.coordinates_marked.iter().collect(),
+            board.flag_levels.iter().collect(),
+            board.detonated.iter().collect(),
+        )
+    }
+
+    /// Uncovers a tile as a single move, along with its opening
+    fn reveal(board: &mut Board, history: &mut UndoHistory, coordinates: Coordinates) {
+        let before = board.coordinates_discovered.clone();
+        board.coordinates_discovered.insert(coordinates);
+        if board.tile_map.is_bomb_at(coordinates) {
+            history.record(Change::Detonate(coordinates));
+            board.hit_mine(coordinates);
+        } else if board.tile_map.is_empty_at(coordinates) {
+            board.flood_discovery(&coordinates);
+        }
+        let discovered: Vec<_> = board.coordinates_discovered.iter().collect();
+        for coordinates in discovered {
+            if !before.contains(&coordinates) {
+                history.record(Change::Reveal(coordinates));
+            }
+        }
+        history.commit();
+    }
+
+    /// Clicks the flag of a tile once, as a single move
+    fn toggle_flag(board: &mut Board, history: &mut UndoHistory, coordinates: Coordinates) {
+        let from = board.flag_mines(&coordinates);
+        let to = board.try_toggle_mark(coordinates, PLAYER).unwrap_or(0);
+        history.record(Change::Flag {
+            coordinates,
+            player: PLAYER,
+            from,
+            to,
+        });
+        history.commit();
+    }
+
+    #[test]
+    fn openings_are_undone_at_once() {
+        let mut board = Board::from_grid(GRID, 1);
+        let mut history = UndoHistory::default();
+        let empty = state(&board);
+
+        reveal(&mut board, &mut history, Coordinates { x: 1, y: 0 });
+        assert!(board.coordinates_discovered.len() > 1);
+        let opened = state(&board);
+
+        let changes = history.undo(&mut board).unwrap();
+        assert_eq!(changes.len(), opened.0.len());
+        assert_eq!(state(&board), empty);
+        assert!(!history.can_undo());
+        assert!(history.undo(&mut board).is_none());
+
+        history.redo(&mut board).unwrap();
+        assert_eq!(state(&board), opened);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn flag_levels_step_back_one_click_at_a_time() {
+        let mut board = Board::from_grid(GRID, 1);
+        board.tile_map.cell_mines = 2;
+        let mut history = UndoHistory::default();
+        let mine = Coordinates { x: 0, y: 2 };
+
+        let mut states = vec![state(&board)];
+        for level in [Some(1), Some(2), None] {
+            toggle_flag(&mut board, &mut history, mine);
+            assert_eq!(board.flag_levels.get(&mine), level);
+            states.push(state(&board));
+        }
+        for expected in states.iter().rev().skip(1) {
+            history.undo(&mut board).unwrap();
+            assert_eq!(&state(&board), expected);
+        }
+        for expected in states.iter().skip(1) {
+            history.redo(&mut board).unwrap();
+            assert_eq!(&state(&board), expected);
+        }
+    }
+
+    #[test]
+    fn detonations_give_back_their_life() {
+        let mut board = Board::from_grid(GRID, 2);
+        let mut history = UndoHistory::default();
+        reveal(&mut board, &mut history, Coordinates { x: 1, y: 0 });
+        let before = state(&board);
+
+        reveal(&mut board, &mut history, Coordinates { x: 3, y: 0 });
+        assert_eq!(board.lives_left(), 1);
+        let hit = state(&board);
+
+        history.undo(&mut board).unwrap();
+        assert_eq!(state(&board), before);
+        assert_eq!(board.lives_left(), 2);
+        history.redo(&mut board).unwrap();
+        assert_eq!(state(&board), hit);
+        assert_eq!(board.lives_left(), 1);
+    }
+
+    #[test]
+    fn a_new_move_drops_the_undone_ones() {
+        let mut board = Board::from_grid(GRID, 1);
+        let mut history = UndoHistory::default();
+        toggle_flag(&mut board, &mut history, Coordinates { x: 0, y: 2 });
+        toggle_flag(&mut board, &mut history, Coordinates { x: 3, y: 0 });
+        history.undo(&mut board).unwrap();
+        history.undo(&mut board).unwrap();
+        assert!(history.can_redo());
+
+        toggle_flag(&mut board, &mut history, Coordinates { x: 1, y: 1 });
+        assert!(!history.can_redo());
+        assert!(history.redo(&mut board).is_none());
+        assert_eq!(
+            board.coordinates_marked.iter().collect::<Vec<_>>(),
+            vec![(Coordinates { x: 1, y: 1 }, PLAYER)]
+        );
+
+        // Moves that change nothing leave the undone ones alone
+        history.undo(&mut board).unwrap();
+        history.commit();
+        assert!(history.can_redo());
+    }
+}
@@ -0,0 +1,244 @@
+//! Board layouts stored outside of the game: the standard binary `.mbf`
+//! format and a text grid with one row per line, `*` for mines and `.` for
+//! safe tiles, top row first
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::components::Coordinates;
+use crate::resources::tile_map::TileMap;
+
+pub const MBF_EXTENSION: &str = "mbf";
+
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(io::Error),
+    /// The `.mbf` data ends before the announced mines
+    Truncated,
+    Empty,
+    TooLarge,
+    UnevenRows,
+    InvalidCell(char),
+    MineOutOfBounds(Coordinates),
+}
+
+impl Display for LayoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Io(e) => write!(f, "{}", e),
+            LayoutError::Truncated => write!(f, "truncated board file"),
+            LayoutError::Empty => write!(f, "empty board"),
+            LayoutError::TooLarge => write!(f, "board too large for the format"),
+            LayoutError::UnevenRows => write!(f, "rows have different lengths"),
+            LayoutError::InvalidCell(c) => write!(f, "invalid cell '{}'", c),
+            LayoutError::MineOutOfBounds(coordinates) => {
+                write!(f, "mine {} is outside of the board", coordinates)
+            }
+        }
+    }
+}
+
+impl Error for LayoutError {}
+
+impl From<io::Error> for LayoutError {
+    fn from(e: io::Error) -> Self {
+        LayoutError::Io(e)
+    }
+}
+
+impl TileMap {
+    /// Reads `width: u8, height: u8, mine count: u16 BE` followed by an
+    /// `(x, y)` byte pair per mine, rows counted from the top
+    pub fn from_mbf(bytes: &[u8]) -> Result<Self, LayoutError> {
+        let header = bytes.get(..4).ok_or(LayoutError::Truncated)?;
+        let (width, height) = (header[0] as u16, header[1] as u16);
+        let mine_count = u16::from_be_bytes([header[2], header[3]]) as usize;
+        if width == 0 || height == 0 {
+            return Err(LayoutError::Empty);
+        }
+
+        let pairs = bytes
+            .get(4..4 + mine_count * 2)
+            .ok_or(LayoutError::Truncated)?;
+        let mut mines = Vec::with_capacity(mine_count);
+        for pair in pairs.chunks(2) {
+            let (x, row) = (pair[0] as u16, pair[1] as u16);
+            if x >= width || row >= height {
+                return Err(LayoutError::MineOutOfBounds(Coordinates { x, y: row }));
+            }
+            mines.push(Coordinates {
+                x,
+                y: height - 1 - row,
+            });
+        }
+        Ok(Self::from_mines(width, height, &mines))
+    }
+
+    pub fn to_mbf(&self) -> Result<Vec<u8>, LayoutError> {
+        let mines = self.mines();
+        if self.width > u8::MAX as u16 || self.height > u8::MAX as u16 {
+            return Err(LayoutError::TooLarge);
+        }
+
+        let mut bytes = vec![self.width as u8, self.height as u8];
+        bytes.extend((mines.len() as u16).to_be_bytes());
+        for mine in mines {
+            bytes.extend([mine.x as u8, (self.height - 1 - mine.y) as u8]);
+        }
+        Ok(bytes)
+    }
+
+    /// Loads a `.mbf` file, or a text grid for any other extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LayoutError> {
+        let path = path.as_ref();
+        if is_mbf(path) {
+            Self::from_mbf(&std::fs::read(path)?)
+        } else {
+            std::fs::read_to_string(path)?.parse()
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LayoutError> {
+        let path = path.as_ref();
+        if is_mbf(path) {
+            std::fs::write(path, self.to_mbf()?)?;
+        } else {
+            std::fs::write(path, self.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn is_mbf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(MBF_EXTENSION))
+}
+
+impl FromStr for TileMap {
+    type Err = LayoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<&str> = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err(LayoutError::Empty);
+        }
+        if width > u16::MAX as usize || rows.len() > u16::MAX as usize {
+            return Err(LayoutError::TooLarge);
+        }
+        if rows.iter().any(|row| row.chars().count() != width) {
+            return Err(LayoutError::UnevenRows);
+        }
+
+        let height = rows.len() as u16;
+        let mut mines = Vec::new();
+        for (row, line) in rows.iter().enumerate() {
+            for (x, cell) in line.chars().enumerate() {
+                match cell {
+                    '*' => mines.push(Coordinates {
+                        x: x as u16,
+                        y: height - 1 - row as u16,
+                    }),
+                    '.' => (),
+                    c => return Err(LayoutError::InvalidCell(c)),
+                }
+            }
+        }
+        Ok(Self::from_mines(width as u16, height, &mines))
+    }
+}
+
+impl Display for TileMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for line in self.rows().rev() {
+            let row: String = line
+                .iter()
+                .map(|tile| if tile.is_bomb() { '*' } else { '.' })
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "*...\n..*.\n...*\n";
+
+    #[test]
+    fn text_round_trip() {
+        let tile_map: TileMap = GRID.parse().unwrap();
+        assert_eq!(tile_map.size(), (4, 3));
+        assert_eq!(tile_map.bomb_count, 3);
+        // The top row comes first
+        assert!(tile_map.is_bomb_at(Coordinates { x: 0, y: 2 }));
+        assert_eq!(tile_map.to_string(), GRID);
+    }
+
+    #[test]
+    fn mbf_round_trip() {
+        let tile_map: TileMap = GRID.parse().unwrap();
+        let bytes = tile_map.to_mbf().unwrap();
+        assert_eq!(&bytes[..4], &[4, 3, 0, 3]);
+        let read = TileMap::from_mbf(&bytes).unwrap();
+        assert_eq!(read.mines(), tile_map.mines());
+        assert_eq!(read.to_mbf().unwrap(), bytes);
+    }
+
+    #[test]
+    fn truncated_mbf_is_rejected() {
+        assert!(matches!(
+            TileMap::from_mbf(&[4, 3, 0]),
+            Err(LayoutError::Truncated)
+        ));
+        // Two mines announced, one given
+        assert!(matches!(
+            TileMap::from_mbf(&[4, 3, 0, 2, 1, 1]),
+            Err(LayoutError::Truncated)
+        ));
+        assert!(matches!(
+            TileMap::from_mbf(&[0, 3, 0, 0]),
+            Err(LayoutError::Empty)
+        ));
+    }
+
+    #[test]
+    fn mbf_mines_outside_the_board_are_rejected() {
+        assert!(matches!(
+            TileMap::from_mbf(&[4, 3, 0, 1, 4, 0]),
+            Err(LayoutError::MineOutOfBounds(Coordinates { x: 4, y: 0 }))
+        ));
+        assert!(matches!(
+            TileMap::from_mbf(&[4, 3, 0, 1, 0, 3]),
+            Err(LayoutError::MineOutOfBounds(Coordinates { x: 0, y: 3 }))
+        ));
+    }
+
+    #[test]
+    fn malformed_grids_are_rejected() {
+        assert!(matches!(
+            "*..\n..\n".parse::<TileMap>(),
+            Err(LayoutError::UnevenRows)
+        ));
+        assert!(matches!(
+            "*.x\n...\n".parse::<TileMap>(),
+            Err(LayoutError::InvalidCell('x'))
+        ));
+        assert!(matches!("\n\n".parse::<TileMap>(), Err(LayoutError::Empty)));
+    }
+
+    #[test]
+    fn boards_too_large_for_mbf_are_rejected() {
+        let tile_map = TileMap::from_mines(256, 1, &[]);
+        assert!(matches!(tile_map.to_mbf(), Err(LayoutError::TooLarge)));
+    }
+}
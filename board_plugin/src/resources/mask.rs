@@ -0,0 +1,311 @@
+//! Boards that aren't rectangles. A mask lists the cells of the board's
+//! rectangle that don't exist; it is read from a text grid with `#` for
+//! cells and `.` or spaces for holes, top row first, or from a PNG
+//! silhouette with one pixel per cell.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use bevy::render::render_resource::TextureFormat;
+use bevy::render::texture::{CompressedImageFormats, Image, ImageType};
+use serde::{Deserialize, Serialize};
+
+use crate::components::Coordinates;
+use crate::resources::cells::CellSet;
+
+pub const PNG_EXTENSION: &str = "png";
+
+/// Channel value from which an opaque pixel counts as white
+const WHITE: u8 = 224;
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BoardMask {
+    pub width: u16,
+    pub height: u16,
+    /// Cells missing from the board
+    pub holes: Vec<Coordinates>,
+}
+
+#[derive(Debug)]
+pub enum MaskError {
+    Io(io::Error),
+    Image(String),
+    Empty,
+    TooLarge,
+    InvalidCell(char),
+    /// The mask's size differs from the board's
+    SizeMismatch {
+        mask: (u16, u16),
+        board: (u16, u16),
+    },
+    HoleOutside(Coordinates),
+}
+
+impl Display for MaskError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskError::Io(e) => write!(f, "{}", e),
+            MaskError::Image(e) => write!(f, "invalid image: {}", e),
+            MaskError::Empty => write!(f, "mask has no cells"),
+            MaskError::TooLarge => write!(f, "mask too large"),
+            MaskError::InvalidCell(c) => write!(f, "invalid mask cell '{}'", c),
+            MaskError::SizeMismatch { mask, board } => write!(
+                f,
+                "mask is {}x{}, the board {}x{}",
+                mask.0, mask.1, board.0, board.1
+            ),
+            MaskError::HoleOutside(hole) => write!(f, "hole {} is outside of the mask", hole),
+        }
+    }
+}
+
+impl Error for MaskError {}
+
+impl From<io::Error> for MaskError {
+    fn from(e: io::Error) -> Self {
+        MaskError::Io(e)
+    }
+}
+
+impl BoardMask {
+    /// Mask keeping the cells for which `is_cell` returns `true`
+    pub fn from_fn(width: u16, height: u16, is_cell: impl Fn(Coordinates) -> bool) -> Self {
+        let holes = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Coordinates { x, y }))
+            .filter(|coordinates| !is_cell(*coordinates))
+            .collect();
+        Self {
+            width,
+            height,
+            holes,
+        }
+    }
+
+    /// Number of cells the board keeps
+    pub fn cell_count(&self) -> usize {
+        self.width as usize * self.height as usize - self.holes.len()
+    }
+
+    /// The holes as a set, for quick lookups
+    pub fn hole_set(&self) -> CellSet {
+        let mut holes = CellSet::new(self.width, self.height);
+        holes.extend(self.holes.iter().copied());
+        holes
+    }
+
+    /// Silhouette of a PNG image: opaque pixels that aren't white are
+    /// cells, the image's top row is the board's top row
+    pub fn from_png(bytes: &[u8]) -> Result<Self, MaskError> {
+        let image = Image::from_buffer(
+            bytes,
+            ImageType::Extension(PNG_EXTENSION),
+            CompressedImageFormats::NONE,
+            true,
+        )
+        .map_err(|e| MaskError::Image(e.to_string()))?;
+        let image = image
+            .convert(TextureFormat::Rgba8UnormSrgb)
+            .ok_or_else(|| MaskError::Image("unsupported pixel format".to_string()))?;
+        let size = image.texture_descriptor.size;
+        if size.width > u16::MAX as u32 || size.height > u16::MAX as u32 {
+            return Err(MaskError::TooLarge);
+        }
+
+        let (width, height) = (size.width as u16, size.height as u16);
+        let mask = Self::from_fn(width, height, |coordinates| {
+            let row = (height - 1 - coordinates.y) as usize;
+            let index = (row * width as usize + coordinates.x as usize) * 4;
+            let pixel = &image.data[index..index + 4];
+            pixel[3] >= 128 && pixel[..3].iter().any(|channel| *channel < WHITE)
+        });
+        mask.check()
+    }
+
+    /// Loads a PNG silhouette, or a text grid for any other extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MaskError> {
+        let path = path.as_ref();
+        let is_png = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(PNG_EXTENSION));
+        if is_png {
+            Self::from_png(&std::fs::read(path)?)
+        } else {
+            std::fs::read_to_string(path)?.parse()
+        }
+    }
+
+    /// Checks a mask from elsewhere, like the server, against the size of
+    /// the board it is played on
+    pub fn check_size(&self, size: (u16, u16)) -> Result<(), MaskError> {
+        if (self.width, self.height) != size {
+            return Err(MaskError::SizeMismatch {
+                mask: (self.width, self.height),
+                board: size,
+            });
+        }
+        match self
+            .holes
+            .iter()
+            .find(|hole| hole.x >= self.width || hole.y >= self.height)
+        {
+            Some(hole) => Err(MaskError::HoleOutside(*hole)),
+            None => Ok(()),
+        }
+    }
+
+    fn check(self) -> Result<Self, MaskError> {
+        match self.cell_count() {
+            0 => Err(MaskError::Empty),
+            _ => Ok(self),
+        }
+    }
+}
+
+impl FromStr for BoardMask {
+    type Err = MaskError;
+
+    /// Rows shorter than the longest one end with holes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<Vec<char>> = s
+            .lines()
+            .map(|line| line.trim_end().chars().collect())
+            .collect();
+        // Blank lines around the grid are not part of it
+        let first = rows.iter().position(|row| !row.is_empty());
+        let last = rows.iter().rposition(|row| !row.is_empty());
+        let rows = match (first, last) {
+            (Some(first), Some(last)) => &rows[first..=last],
+            _ => return Err(MaskError::Empty),
+        };
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if width > u16::MAX as usize || rows.len() > u16::MAX as usize {
+            return Err(MaskError::TooLarge);
+        }
+        if let Some(c) = rows
+            .iter()
+            .flatten()
+            .find(|c| !matches!(c, '#' | '.' | ' '))
+        {
+            return Err(MaskError::InvalidCell(*c));
+        }
+
+        let height = rows.len() as u16;
+        let mask = Self::from_fn(width as u16, height, |coordinates| {
+            let row = &rows[(height - 1 - coordinates.y) as usize];
+            row.get(coordinates.x as usize) == Some(&'#')
+        });
+        mask.check()
+    }
+}
+
+impl Display for BoardMask {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let holes = self.hole_set();
+        for y in (0..self.height).rev() {
+            let row: String = (0..self.width)
+                .map(|x| match holes.contains(&Coordinates { x, y }) {
+                    true => '.',
+                    false => '#',
+                })
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&pixels.concat()).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mask: BoardMask = "#.#\n###\n".parse().unwrap();
+        assert_eq!((mask.width, mask.height), (3, 2));
+        // The top row comes first
+        assert_eq!(mask.holes, vec![Coordinates { x: 1, y: 1 }]);
+        assert_eq!(mask.to_string(), "#.#\n###\n");
+
+        // Short rows end with holes, blank lines around the grid are dropped
+        let mask: BoardMask = "\n##\n#\n\n".parse().unwrap();
+        assert_eq!(mask.to_string(), "##\n#.\n");
+    }
+
+    #[test]
+    fn invalid_text_masks_are_rejected() {
+        assert!(matches!(
+            "#x\n".parse::<BoardMask>(),
+            Err(MaskError::InvalidCell('x'))
+        ));
+        assert!(matches!(
+            ". .\n".parse::<BoardMask>(),
+            Err(MaskError::Empty)
+        ));
+        assert!(matches!("\n\n".parse::<BoardMask>(), Err(MaskError::Empty)));
+    }
+
+    #[test]
+    fn png_cells_are_opaque_and_not_white() {
+        let (black, white) = ([0, 0, 0, 255], [255, 255, 255, 255]);
+        let pixels = [
+            // Top row: faint, barely opaque, almost white and red
+            [0, 0, 0, 127],
+            [0, 0, 0, 128],
+            [230, 230, 230, 255],
+            [200, 0, 0, 255],
+            // Bottom row
+            black,
+            white,
+            [223, 250, 250, 255],
+            black,
+        ];
+        let mask = BoardMask::from_png(&png(4, 2, &pixels)).unwrap();
+        assert_eq!(mask.to_string(), ".#.#\n#.##\n");
+
+        assert!(matches!(
+            BoardMask::from_png(&png(2, 1, &[white, white])),
+            Err(MaskError::Empty)
+        ));
+        assert!(matches!(
+            BoardMask::from_png(b"not a png"),
+            Err(MaskError::Image(_))
+        ));
+    }
+
+    #[test]
+    fn masks_must_fit_their_board() {
+        let mask: BoardMask = "#.#\n###\n".parse().unwrap();
+        assert!(mask.check_size((3, 2)).is_ok());
+        assert!(matches!(
+            mask.check_size((2, 3)),
+            Err(MaskError::SizeMismatch {
+                mask: (3, 2),
+                board: (2, 3)
+            })
+        ));
+
+        let hole = Coordinates { x: 3, y: 0 };
+        let outside = BoardMask {
+            holes: vec![hole],
+            ..mask
+        };
+        assert!(matches!(
+            outside.check_size((3, 2)),
+            Err(MaskError::HoleOutside(found)) if found == hole
+        ));
+    }
+}
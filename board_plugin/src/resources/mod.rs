@@ -1,8 +1,23 @@
 pub use board_assets::*;
 pub use board_options::*;
 
+mod analysis;
 pub mod board;
 pub mod board_assets;
 mod board_options;
+pub mod cells;
+pub mod clicks;
+pub mod daily;
+pub mod endless;
+pub mod game_summary;
+pub mod generator;
+pub mod hints;
+pub mod history;
+pub mod layout;
+pub mod mask;
+pub mod share_code;
+pub mod stats;
 pub mod tile;
 pub mod tile_map;
+pub mod topology;
+pub mod versus;
@@ -0,0 +1,589 @@
+//! Short URL-safe codes describing a board, to challenge friends on the
+//! exact same layout.
+//!
+//! A code is the base64url encoding of a version byte, a flags byte, the
+//! width, height and mine count as big endian u16, the optional layer
+//! count, the optional start tile, the optional byte of mines a cell holds
+//! at most, the optional neighborhood byte, the optional bitmap of the
+//! mask's holes, then either the generation seed or a bitmap of the mines.
+//! Bitmaps hold one bit per cell in row order, bottom row first; the mines
+//! bitmap spans the layers side by side and has two bits per cell when
+//! cells hold several mines.
+//!
+//! Every bit of the flags byte is taken. A later optional field needs a new
+//! version whose codes carry a second flags byte right after the first, and
+//! version 1 codes keep being read as they are.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use bevy::prelude::Resource;
+
+use crate::components::Coordinates;
+use crate::resources::generator;
+use crate::resources::mask::BoardMask;
+use crate::resources::tile_map::TileMap;
+use crate::resources::topology::TopologyKind;
+use crate::resources::{BoardOptions, BoardShape, Neighborhood};
+
+/// Codes of other versions are rejected, see the module docs before adding
+/// a field
+const SHARE_CODE_VERSION: u8 = 1;
+const SEEDED: u8 = 0b01;
+const HAS_START: u8 = 0b10;
+const HEX: u8 = 0b100;
+const WRAP: u8 = 0b1000;
+const MASKED: u8 = 0b10000;
+const LAYERED: u8 = 0b100000;
+const MULTI_MINE: u8 = 0b1000000;
+const NEIGHBORHOOD: u8 = 0b10000000;
+
+/// Neighborhoods by their byte in a code
+const NEIGHBORHOODS: [Neighborhood; 4] = [
+    Neighborhood::Adjacent,
+    Neighborhood::Orthogonal,
+    Neighborhood::Knight,
+    Neighborhood::Extended,
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CodeLayout {
+    /// Mines placed by the uniform generator
+    Seed(u64),
+    Mines(Vec<Coordinates>),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ShareCode {
+    /// Width of a single layer
+    pub width: u16,
+    pub height: u16,
+    pub bomb_count: u16,
+    pub shape: BoardShape,
+    pub wrap: bool,
+    pub neighborhood: Neighborhood,
+    pub layers: u16,
+    /// Most mines a single cell holds
+    pub cell_mines: u8,
+    /// Holes of a single layer
+    pub mask: Option<BoardMask>,
+    pub start: Option<Coordinates>,
+    pub layout: CodeLayout,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ShareCodeError {
+    InvalidEncoding,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidBoard,
+}
+
+impl Display for ShareCodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareCodeError::InvalidEncoding => write!(f, "not a board code"),
+            ShareCodeError::UnsupportedVersion(version) => {
+                write!(f, "board code version {} is not supported", version)
+            }
+            ShareCodeError::Truncated => write!(f, "board code is incomplete"),
+            ShareCodeError::InvalidBoard => write!(f, "board code describes an invalid board"),
+        }
+    }
+}
+
+impl Error for ShareCodeError {}
+
+impl ShareCode {
+    /// Code of `tile_map`, using its seed when it has one
+    pub fn new(tile_map: &TileMap, start: Option<Coordinates>) -> Self {
+        let layers = tile_map.topology.layers();
+        Self {
+            width: tile_map.width / layers,
+            height: tile_map.height,
+            bomb_count: tile_map.bomb_count,
+            shape: match tile_map.topology {
+                TopologyKind::Hex => BoardShape::Hex,
+                _ => BoardShape::Square,
+            },
+            wrap: tile_map.topology == TopologyKind::Torus,
+            neighborhood: match tile_map.topology {
+                TopologyKind::Square4 => Neighborhood::Orthogonal,
+                TopologyKind::Knight => Neighborhood::Knight,
+                TopologyKind::Square24 => Neighborhood::Extended,
+                _ => Neighborhood::Adjacent,
+            },
+            layers,
+            cell_mines: tile_map.cell_mines,
+            mask: tile_map.mask(),
+            start,
+            layout: match tile_map.seed {
+                Some(seed) => CodeLayout::Seed(seed),
+                None => CodeLayout::Mines(tile_map.mines()),
+            },
+        }
+    }
+
+    /// Sets up the options to play the coded board
+    pub fn apply_to(&self, options: &mut BoardOptions) {
+        options.map_size = (self.width, self.height);
+        options.bomb_count = self.bomb_count;
+        options.shape = self.shape;
+        options.wrap = self.wrap;
+        options.neighborhood = self.neighborhood;
+        options.layers = self.layers;
+        options.cell_mines = self.cell_mines;
+        options.mask = self.mask.clone();
+        options.safe_start = self.start.is_some();
+        options.start = self.start;
+        match &self.layout {
+            CodeLayout::Seed(seed) => {
+                options.seed = Some(*seed);
+                options.generator = generator::UNIFORM.to_string();
+                options.layout = None;
+            }
+            CodeLayout::Mines(mines) => {
+                options.seed = None;
+                options.layout = Some(mines.clone());
+            }
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut flags = 0;
+        if matches!(self.layout, CodeLayout::Seed(_)) {
+            flags |= SEEDED;
+        }
+        if self.start.is_some() {
+            flags |= HAS_START;
+        }
+        if self.shape == BoardShape::Hex {
+            flags |= HEX;
+        }
+        if self.wrap {
+            flags |= WRAP;
+        }
+        if self.mask.is_some() {
+            flags |= MASKED;
+        }
+        if self.layers > 1 {
+            flags |= LAYERED;
+        }
+        if self.cell_mines > 1 {
+            flags |= MULTI_MINE;
+        }
+        if self.neighborhood != Neighborhood::Adjacent {
+            flags |= NEIGHBORHOOD;
+        }
+
+        let mut bytes = vec![SHARE_CODE_VERSION, flags];
+        for value in [self.width, self.height, self.bomb_count] {
+            bytes.extend(value.to_be_bytes());
+        }
+        if self.layers > 1 {
+            bytes.extend(self.layers.to_be_bytes());
+        }
+        if let Some(start) = self.start {
+            bytes.extend(start.x.to_be_bytes());
+            bytes.extend(start.y.to_be_bytes());
+        }
+        if self.cell_mines > 1 {
+            bytes.push(self.cell_mines);
+        }
+        if self.neighborhood != Neighborhood::Adjacent {
+            let index = NEIGHBORHOODS.iter().position(|n| *n == self.neighborhood);
+            bytes.push(index.unwrap_or_default() as u8);
+        }
+        if let Some(mask) = &self.mask {
+            bytes.extend(bitmap(&mask.holes, self.width, self.height));
+        }
+        match &self.layout {
+            CodeLayout::Seed(seed) => bytes.extend(seed.to_be_bytes()),
+            CodeLayout::Mines(mines) if self.cell_mines > 1 => {
+                bytes.extend(count_map(mines, self.width * self.layers, self.height))
+            }
+            CodeLayout::Mines(mines) => {
+                bytes.extend(bitmap(mines, self.width * self.layers, self.height))
+            }
+        }
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+}
+
+/// Two bits per cell holding how many times the cell is listed
+fn count_map(cells: &[Coordinates], width: u16, height: u16) -> Vec<u8> {
+    let mut counts = vec![0u8; count_map_len(width, height)];
+    for cell in cells.iter() {
+        let index = cell.y as usize * width as usize + cell.x as usize;
+        counts[index / 4] += 1 << (index % 4 * 2);
+    }
+    counts
+}
+
+fn count_map_len(width: u16, height: u16) -> usize {
+    (width as usize * height as usize).div_ceil(4)
+}
+
+/// Cells repeated as many times as their count
+fn read_count_map(counts: &[u8], width: u16, height: u16) -> Vec<Coordinates> {
+    (0..width as usize * height as usize)
+        .flat_map(|index| {
+            let count = (counts[index / 4] >> (index % 4 * 2)) & 0b11;
+            let cell = Coordinates {
+                x: (index % width as usize) as u16,
+                y: (index / width as usize) as u16,
+            };
+            std::iter::repeat(cell).take(count as usize)
+        })
+        .collect()
+}
+
+fn bitmap(cells: &[Coordinates], width: u16, height: u16) -> Vec<u8> {
+    let mut bitmap = vec![0u8; bitmap_len(width, height)];
+    for cell in cells.iter() {
+        let index = cell.y as usize * width as usize + cell.x as usize;
+        bitmap[index / 8] |= 1 << (index % 8);
+    }
+    bitmap
+}
+
+fn bitmap_len(width: u16, height: u16) -> usize {
+    (width as usize * height as usize).div_ceil(8)
+}
+
+/// Cells whose bit is set
+fn read_bitmap(bitmap: &[u8], width: u16, height: u16) -> Vec<Coordinates> {
+    (0..width as usize * height as usize)
+        .filter(|index| bitmap[index / 8] & (1 << (index % 8)) != 0)
+        .map(|index| Coordinates {
+            x: (index % width as usize) as u16,
+            y: (index / width as usize) as u16,
+        })
+        .collect()
+}
+
+/// Whether the bits past the first `bits` of a map are unset, a set one
+/// would be a cell outside of the board
+fn padding_is_clear(map: &[u8], bits: usize) -> bool {
+    match (map.last(), bits % 8) {
+        (Some(last), used) if used > 0 => last >> used == 0,
+        _ => true,
+    }
+}
+
+impl Display for ShareCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.encode())
+    }
+}
+
+impl FromStr for ShareCode {
+    type Err = ShareCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(s.trim())
+            .map_err(|_| ShareCodeError::InvalidEncoding)?;
+        let mut reader = bytes.iter().copied();
+        let (version, flags) = match (reader.next(), reader.next()) {
+            (Some(version), Some(flags)) => (version, flags),
+            _ => return Err(ShareCodeError::Truncated),
+        };
+        if version != SHARE_CODE_VERSION {
+            return Err(ShareCodeError::UnsupportedVersion(version));
+        }
+
+        let mut read_u16 = || match (reader.next(), reader.next()) {
+            (Some(hi), Some(lo)) => Ok(u16::from_be_bytes([hi, lo])),
+            _ => Err(ShareCodeError::Truncated),
+        };
+        let (width, height, bomb_count) = (read_u16()?, read_u16()?, read_u16()?);
+        let layers = match flags & LAYERED {
+            0 => 1,
+            _ => read_u16()?,
+        };
+        let start = match flags & HAS_START {
+            0 => None,
+            _ => Some(Coordinates {
+                x: read_u16()?,
+                y: read_u16()?,
+            }),
+        };
+        let shape = match flags & HEX {
+            0 => BoardShape::Square,
+            _ => BoardShape::Hex,
+        };
+        let grid_width = width
+            .checked_mul(layers)
+            .ok_or(ShareCodeError::InvalidBoard)?;
+        let rest: Vec<u8> = reader.collect();
+        let (cell_mines, rest) = match flags & MULTI_MINE {
+            0 => (1, rest.as_slice()),
+            _ => match rest.split_first() {
+                Some((&cell_mines, rest)) => (cell_mines, rest),
+                None => return Err(ShareCodeError::Truncated),
+            },
+        };
+        let (neighborhood, rest) = match flags & NEIGHBORHOOD {
+            0 => (Neighborhood::Adjacent, rest),
+            _ => match rest.split_first() {
+                Some((&index, rest)) => match NEIGHBORHOODS.get(index as usize) {
+                    Some(neighborhood) => (*neighborhood, rest),
+                    None => return Err(ShareCodeError::InvalidBoard),
+                },
+                None => return Err(ShareCodeError::Truncated),
+            },
+        };
+        let cells = grid_width as usize * height as usize;
+        if cells == 0
+            || layers == 0
+            || !(1..=3).contains(&cell_mines)
+            || bomb_count as usize >= cells * cell_mines as usize
+            || start.is_some_and(|start| start.x >= grid_width || start.y >= height)
+        {
+            return Err(ShareCodeError::InvalidBoard);
+        }
+
+        let (mask, rest) = match flags & MASKED {
+            0 => (None, rest),
+            _ => {
+                if rest.len() < bitmap_len(width, height) {
+                    return Err(ShareCodeError::Truncated);
+                }
+                let (bitmap, rest) = rest.split_at(bitmap_len(width, height));
+                if !padding_is_clear(bitmap, width as usize * height as usize) {
+                    return Err(ShareCodeError::InvalidBoard);
+                }
+                let mask = BoardMask {
+                    width,
+                    height,
+                    holes: read_bitmap(bitmap, width, height),
+                };
+                let holes = mask.hole_set();
+                let on_hole = |cell: &Coordinates| {
+                    holes.contains(&Coordinates {
+                        x: cell.x % width,
+                        y: cell.y,
+                    })
+                };
+                if bomb_count as usize >= mask.cell_count() * layers as usize * cell_mines as usize
+                    || start.as_ref().is_some_and(on_hole)
+                {
+                    return Err(ShareCodeError::InvalidBoard);
+                }
+                (Some(mask), rest)
+            }
+        };
+
+        let layout = if flags & SEEDED != 0 {
+            let seed: [u8; 8] = rest.try_into().map_err(|_| ShareCodeError::Truncated)?;
+            CodeLayout::Seed(u64::from_be_bytes(seed))
+        } else {
+            let mines = match cell_mines {
+                1 if rest.len() == bitmap_len(grid_width, height) => {
+                    read_bitmap(rest, grid_width, height)
+                }
+                2.. if rest.len() == count_map_len(grid_width, height) => {
+                    read_count_map(rest, grid_width, height)
+                }
+                _ => return Err(ShareCodeError::Truncated),
+            };
+            let bits = match cell_mines {
+                1 => cells,
+                _ => cells * 2,
+            };
+            if !padding_is_clear(rest, bits) {
+                return Err(ShareCodeError::InvalidBoard);
+            }
+            let overfull = mines
+                .windows(cell_mines as usize + 1)
+                .any(|window| window.iter().all(|mine| *mine == window[0]));
+            let holes = mask.as_ref().map(BoardMask::hole_set);
+            let on_hole = holes.is_some_and(|holes| {
+                mines.iter().any(|mine| {
+                    holes.contains(&Coordinates {
+                        x: mine.x % width,
+                        y: mine.y,
+                    })
+                })
+            });
+            if mines.len() != bomb_count as usize || on_hole || overfull {
+                return Err(ShareCodeError::InvalidBoard);
+            }
+            CodeLayout::Mines(mines)
+        };
+
+        Ok(Self {
+            width,
+            height,
+            bomb_count,
+            shape,
+            wrap: flags & WRAP != 0,
+            neighborhood,
+            layers,
+            cell_mines,
+            mask,
+            start,
+            layout,
+        })
+    }
+}
+
+/// Code being typed in the paste dialog
+#[derive(Debug, Clone, Default, Resource)]
+pub struct ShareCodeInput {
+    pub text: String,
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(layout: CodeLayout) -> ShareCode {
+        ShareCode {
+            width: 4,
+            height: 3,
+            bomb_count: 2,
+            shape: BoardShape::Square,
+            wrap: false,
+            neighborhood: Neighborhood::Adjacent,
+            layers: 1,
+            cell_mines: 1,
+            mask: None,
+            start: None,
+            layout,
+        }
+    }
+
+    fn mines() -> CodeLayout {
+        CodeLayout::Mines(vec![Coordinates { x: 1, y: 0 }, Coordinates { x: 3, y: 2 }])
+    }
+
+    /// Decodes `bytes` as if they were written by `encode`
+    fn decode(bytes: &[u8]) -> Result<ShareCode, ShareCodeError> {
+        URL_SAFE_NO_PAD.encode(bytes).parse()
+    }
+
+    fn bytes(code: &ShareCode) -> Vec<u8> {
+        URL_SAFE_NO_PAD.decode(code.encode()).unwrap()
+    }
+
+    #[test]
+    fn seeded_round_trip() {
+        let code = code(CodeLayout::Seed(0x0123_4567_89ab_cdef));
+        assert_eq!(code.encode().parse(), Ok(code));
+    }
+
+    #[test]
+    fn mines_round_trip() {
+        let code = code(mines());
+        assert_eq!(code.encode().parse(), Ok(code));
+    }
+
+    #[test]
+    fn every_optional_field_round_trips() {
+        let code = ShareCode {
+            neighborhood: Neighborhood::Knight,
+            layers: 2,
+            cell_mines: 2,
+            mask: Some(BoardMask {
+                width: 4,
+                height: 3,
+                holes: vec![Coordinates { x: 0, y: 0 }],
+            }),
+            start: Some(Coordinates { x: 2, y: 1 }),
+            // Mines on the second layer and two in one cell
+            layout: CodeLayout::Mines(vec![
+                Coordinates { x: 1, y: 0 },
+                Coordinates { x: 1, y: 0 },
+                Coordinates { x: 6, y: 2 },
+            ]),
+            bomb_count: 3,
+            ..code(mines())
+        };
+        assert_eq!(code.encode().parse(), Ok(code));
+    }
+
+    #[test]
+    fn truncated_codes_are_rejected() {
+        let bytes = bytes(&ShareCode {
+            start: Some(Coordinates { x: 2, y: 1 }),
+            ..code(mines())
+        });
+        for len in 0..bytes.len() {
+            assert_eq!(decode(&bytes[..len]), Err(ShareCodeError::Truncated));
+        }
+        assert_eq!(decode(&bytes), Ok(decode(&bytes).unwrap()));
+    }
+
+    #[test]
+    fn unknown_versions_and_garbage_are_rejected() {
+        let mut bytes = bytes(&code(mines()));
+        bytes[0] = SHARE_CODE_VERSION + 1;
+        assert_eq!(
+            decode(&bytes),
+            Err(ShareCodeError::UnsupportedVersion(SHARE_CODE_VERSION + 1))
+        );
+        assert_eq!(
+            "not a code!".parse::<ShareCode>(),
+            Err(ShareCodeError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_cells_are_rejected() {
+        let start = ShareCode {
+            start: Some(Coordinates { x: 4, y: 0 }),
+            ..code(mines())
+        };
+        assert_eq!(
+            start.encode().parse::<ShareCode>(),
+            Err(ShareCodeError::InvalidBoard)
+        );
+
+        // A mine in the padding bits past the last cell goes missing
+        let mut bytes = bytes(&code(mines()));
+        let last = bytes.len() - 1;
+        bytes[last] |= 0b1000_0000;
+        assert_eq!(decode(&bytes), Err(ShareCodeError::InvalidBoard));
+    }
+
+    #[test]
+    fn mines_on_holes_are_rejected() {
+        let code = ShareCode {
+            mask: Some(BoardMask {
+                width: 4,
+                height: 3,
+                holes: vec![Coordinates { x: 1, y: 0 }],
+            }),
+            ..code(mines())
+        };
+        assert_eq!(
+            code.encode().parse::<ShareCode>(),
+            Err(ShareCodeError::InvalidBoard)
+        );
+    }
+
+    #[test]
+    fn coded_start_on_a_mine_falls_back() {
+        let mut options = BoardOptions::default();
+        let code = ShareCode {
+            start: Some(Coordinates { x: 1, y: 0 }),
+            ..code(mines())
+        };
+        code.apply_to(&mut options);
+        let tile_map = TileMap::from_mines(4, 3, &[Coordinates { x: 1, y: 0 }]);
+        let start = options.start_coordinates(&tile_map).unwrap();
+        assert!(tile_map.is_empty_at(start));
+
+        let tile_map = TileMap::from_mines(4, 3, &[Coordinates { x: 3, y: 2 }]);
+        assert_eq!(
+            options.start_coordinates(&tile_map),
+            Some(Coordinates { x: 1, y: 0 })
+        );
+    }
+}
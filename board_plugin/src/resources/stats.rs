@@ -0,0 +1,556 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use bevy::log;
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::resources::clicks::ClickCounter;
+use crate::resources::tile_map::TileMap;
+use crate::resources::topology::TopologyKind;
+use crate::storage;
+
+/// Version written by this build. Older files are migrated when loaded,
+/// see `MIGRATIONS`.
+pub const STATS_VERSION: u32 = 7;
+
+/// `MIGRATIONS[i]` upgrades a version `i + 1` file to version `i + 2`
+const MIGRATIONS: &[fn(&mut Value)] = &[
+    add_click_totals,
+    add_topology,
+    add_holes,
+    add_cell_mines,
+    add_lives,
+    add_assisted,
+];
+
+/// Version 2 counts clicks, wins from earlier versions have none
+fn add_click_totals(value: &mut Value) {
+    let configs = value.get_mut("configs").and_then(Value::as_array_mut);
+    for config in configs.into_iter().flatten() {
+        if let Some(config) = config.as_object_mut() {
+            config.insert("clicked_wins".to_string(), 0.into());
+            config.insert("clicked_win_bbbv".to_string(), 0.into());
+            config.insert("clicked_win_clicks".to_string(), 0.into());
+            config.insert("clicked_win_wasted".to_string(), 0.into());
+            config.insert("best_efficiency".to_string(), Value::Null);
+        }
+    }
+}
+
+/// Version 3 tells grids apart, earlier versions only had square boards
+fn add_topology(value: &mut Value) {
+    let configs = value.get_mut("configs").and_then(Value::as_array_mut);
+    for config in configs.into_iter().flatten() {
+        if let Some(config) = config.get_mut("config").and_then(Value::as_object_mut) {
+            config.insert("topology".to_string(), "Square8".into());
+        }
+    }
+}
+
+/// Version 4 tells masked boards apart, earlier versions only had full
+/// rectangles
+fn add_holes(value: &mut Value) {
+    let configs = value.get_mut("configs").and_then(Value::as_array_mut);
+    for config in configs.into_iter().flatten() {
+        if let Some(config) = config.get_mut("config").and_then(Value::as_object_mut) {
+            config.insert("holes".to_string(), 0.into());
+        }
+    }
+}
+
+/// Version 5 tells boards whose cells hold several mines apart, earlier
+/// versions held one mine per cell
+fn add_cell_mines(value: &mut Value) {
+    let configs = value.get_mut("configs").and_then(Value::as_array_mut);
+    for config in configs.into_iter().flatten() {
+        if let Some(config) = config.get_mut("config").and_then(Value::as_object_mut) {
+            config.insert("cell_mines".to_string(), 1.into());
+        }
+    }
+}
+
+/// Version 6 tells games with several lives apart, earlier versions ended
+/// on the first mine
+fn add_lives(value: &mut Value) {
+    let configs = value.get_mut("configs").and_then(Value::as_array_mut);
+    for config in configs.into_iter().flatten() {
+        if let Some(config) = config.get_mut("config").and_then(Value::as_object_mut) {
+            config.insert("lives".to_string(), 1.into());
+        }
+    }
+}
+
+/// Version 7 keeps games played with hints apart, earlier versions had no
+/// hints
+fn add_assisted(value: &mut Value) {
+    let configs = value.get_mut("configs").and_then(Value::as_array_mut);
+    for config in configs.into_iter().flatten() {
+        if let Some(config) = config.as_object_mut() {
+            config.insert("assisted_games".to_string(), 0.into());
+            config.insert("assisted_wins".to_string(), 0.into());
+        }
+    }
+}
+
+/// Board dimensions, mine count, grid and mask size, statistics are kept
+/// per configuration
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct BoardConfig {
+    /// Width of a single layer
+    pub width: u16,
+    pub height: u16,
+    pub mines: u16,
+    pub topology: TopologyKind,
+    /// Cells of the rectangle left out by the mask
+    pub holes: u32,
+    /// Most mines a single cell holds
+    pub cell_mines: u8,
+    /// Mine hits the game allowed
+    pub lives: u8,
+}
+
+impl BoardConfig {
+    pub fn of(tile_map: &TileMap, lives: u8) -> Self {
+        Self {
+            width: tile_map.width / tile_map.topology.layers(),
+            height: tile_map.height,
+            mines: tile_map.bomb_count,
+            topology: tile_map.topology,
+            holes: tile_map.hole_count() as u32,
+            cell_mines: tile_map.cell_mines,
+            lives,
+        }
+    }
+}
+
+impl Display for BoardConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} {} mines", self.width, self.height, self.mines)?;
+        if self.topology != TopologyKind::Square8 {
+            write!(f, ", {}", self.topology)?;
+        }
+        if self.holes > 0 {
+            write!(f, ", {} holes", self.holes)?;
+        }
+        if self.cell_mines > 1 {
+            write!(f, ", up to {} per cell", self.cell_mines)?;
+        }
+        match self.lives {
+            1 => Ok(()),
+            lives => write!(f, ", {} lives", lives),
+        }
+    }
+}
+
+/// A finished game
+#[derive(Debug, Copy, Clone)]
+pub struct GameRecord {
+    pub won: bool,
+    /// Milliseconds from the start of the game to its end
+    pub time: u32,
+    pub bbbv: u32,
+    /// Missing for games whose clicks were not counted, like replays
+    pub clicks: Option<ClickCounter>,
+    /// Hints asked for, any makes the game assisted
+    pub hints: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigStats {
+    pub config: BoardConfig,
+    pub wins: u32,
+    pub losses: u32,
+    /// Fastest win in milliseconds
+    pub best_time: Option<u32>,
+    /// Sum of the times of every win, in milliseconds
+    pub total_win_time: u64,
+    /// Sum of the 3BV of every win
+    pub total_win_bbbv: u64,
+    pub best_bbbv_per_second: Option<f32>,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// Wins whose clicks were counted, the `clicked_win_*` sums cover only
+    /// those
+    pub clicked_wins: u32,
+    pub clicked_win_bbbv: u64,
+    pub clicked_win_clicks: u64,
+    pub clicked_win_wasted: u64,
+    pub best_efficiency: Option<f32>,
+    /// Games played with hints, they are left out of every other figure
+    pub assisted_games: u32,
+    pub assisted_wins: u32,
+}
+
+impl ConfigStats {
+    fn new(config: BoardConfig) -> Self {
+        Self {
+            config,
+            wins: 0,
+            losses: 0,
+            best_time: None,
+            total_win_time: 0,
+            total_win_bbbv: 0,
+            best_bbbv_per_second: None,
+            current_streak: 0,
+            longest_streak: 0,
+            clicked_wins: 0,
+            clicked_win_bbbv: 0,
+            clicked_win_clicks: 0,
+            clicked_win_wasted: 0,
+            best_efficiency: None,
+            assisted_games: 0,
+            assisted_wins: 0,
+        }
+    }
+
+    pub fn played(&self) -> u32 {
+        self.wins + self.losses
+    }
+
+    /// Share of games won, in `[0, 1]`
+    pub fn win_rate(&self) -> f32 {
+        match self.played() {
+            0 => 0.,
+            played => self.wins as f32 / played as f32,
+        }
+    }
+
+    /// Average winning time in milliseconds
+    pub fn average_time(&self) -> Option<u32> {
+        (self.wins > 0).then(|| (self.total_win_time / self.wins as u64) as u32)
+    }
+
+    /// 3BV cleared per second over all wins
+    pub fn bbbv_per_second(&self) -> Option<f32> {
+        (self.total_win_time > 0)
+            .then(|| self.total_win_bbbv as f32 / (self.total_win_time as f32 / 1000.))
+    }
+
+    /// 3BV per click over all wins with counted clicks
+    pub fn efficiency(&self) -> Option<f32> {
+        (self.clicked_win_clicks > 0)
+            .then(|| self.clicked_win_bbbv as f32 / self.clicked_win_clicks as f32)
+    }
+
+    /// Average wasted clicks per win with counted clicks
+    pub fn wasted_per_win(&self) -> Option<f32> {
+        (self.clicked_wins > 0).then(|| self.clicked_win_wasted as f32 / self.clicked_wins as f32)
+    }
+
+    fn record(&mut self, record: GameRecord) {
+        if record.hints > 0 {
+            self.assisted_games += 1;
+            self.assisted_wins += record.won as u32;
+            return;
+        }
+        if !record.won {
+            self.losses += 1;
+            self.current_streak = 0;
+            return;
+        }
+
+        self.wins += 1;
+        self.current_streak += 1;
+        self.longest_streak = self.longest_streak.max(self.current_streak);
+        self.best_time = Some(
+            self.best_time
+                .map_or(record.time, |best| best.min(record.time)),
+        );
+        self.total_win_time += record.time as u64;
+        self.total_win_bbbv += record.bbbv as u64;
+        if record.time > 0 {
+            let bbbv_per_second = record.bbbv as f32 / (record.time as f32 / 1000.);
+            self.best_bbbv_per_second = Some(
+                self.best_bbbv_per_second
+                    .map_or(bbbv_per_second, |best| best.max(bbbv_per_second)),
+            );
+        }
+        if let Some(clicks) = record.clicks {
+            self.clicked_wins += 1;
+            self.clicked_win_bbbv += record.bbbv as u64;
+            self.clicked_win_clicks += clicks.total() as u64;
+            self.clicked_win_wasted += clicks.wasted as u64;
+            if let Some(efficiency) = clicks.efficiency(record.bbbv) {
+                self.best_efficiency = Some(
+                    self.best_efficiency
+                        .map_or(efficiency, |best| best.max(efficiency)),
+                );
+            }
+        }
+    }
+}
+
+/// Results of every finished single player game, stored in the data
+/// directory
+#[derive(Debug, Clone, Serialize, Deserialize, Resource)]
+pub struct GameStats {
+    pub version: u32,
+    pub configs: Vec<ConfigStats>,
+    /// Set when the file was written by a newer build, it is then left
+    /// untouched
+    #[serde(skip)]
+    read_only: bool,
+}
+
+impl Default for GameStats {
+    fn default() -> Self {
+        Self {
+            version: STATS_VERSION,
+            configs: Vec::new(),
+            read_only: false,
+        }
+    }
+}
+
+impl GameStats {
+    pub fn path() -> PathBuf {
+        storage::data_file("stats.json")
+    }
+
+    /// Loads the stats, migrating files of older versions
+    pub fn load() -> Self {
+        let path = Self::path();
+        match storage::load_json(&path) {
+            Value::Null => Self::default(),
+            value => Self::from_value(value).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid stats {}: {}", path.display(), e);
+                Self::default()
+            }),
+        }
+    }
+
+    pub fn from_json(text: &str) -> serde_json::Result<Self> {
+        Self::from_value(serde_json::from_str(text)?)
+    }
+
+    fn from_value(mut value: Value) -> serde_json::Result<Self> {
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(1) as u32;
+        if version > STATS_VERSION {
+            log::warn!(
+                "Stats file version {} is newer than this build, it will not be updated",
+                version
+            );
+            return Ok(Self {
+                read_only: true,
+                ..Self::default()
+            });
+        }
+
+        for migration in MIGRATIONS.iter().skip(version.saturating_sub(1) as usize) {
+            migration(&mut value);
+        }
+        value["version"] = STATS_VERSION.into();
+        serde_json::from_value(value)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        storage::save_json(&Self::path(), self)
+    }
+
+    pub fn config(&self, config: BoardConfig) -> Option<&ConfigStats> {
+        self.configs.iter().find(|stats| stats.config == config)
+    }
+
+    pub fn record(&mut self, config: BoardConfig, record: GameRecord) {
+        let index = match self.configs.iter().position(|stats| stats.config == config) {
+            Some(index) => index,
+            None => {
+                self.configs.push(ConfigStats::new(config));
+                self.configs.len() - 1
+            }
+        };
+        self.configs[index].record(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: &str = r#"{
+        "configs": [{
+            "config": { "width": 9, "height": 9, "mines": 10 },
+            "wins": 3, "losses": 2, "best_time": 14000,
+            "total_win_time": 60000, "total_win_bbbv": 90,
+            "best_bbbv_per_second": 2.5,
+            "current_streak": 1, "longest_streak": 2
+        }]
+    }"#;
+
+    const V4: &str = r#"{
+        "version": 4,
+        "configs": [{
+            "config": {
+                "width": 16, "height": 16, "mines": 40,
+                "topology": "Hex", "holes": 12
+            },
+            "wins": 1, "losses": 0, "best_time": 52000,
+            "total_win_time": 52000, "total_win_bbbv": 120,
+            "best_bbbv_per_second": 2.3,
+            "current_streak": 1, "longest_streak": 1,
+            "clicked_wins": 1, "clicked_win_bbbv": 120,
+            "clicked_win_clicks": 150, "clicked_win_wasted": 4,
+            "best_efficiency": 0.8
+        }]
+    }"#;
+
+    fn beginner() -> BoardConfig {
+        BoardConfig {
+            width: 9,
+            height: 9,
+            mines: 10,
+            topology: TopologyKind::Square8,
+            holes: 0,
+            cell_mines: 1,
+            lives: 1,
+        }
+    }
+
+    fn game(won: bool, time: u32) -> GameRecord {
+        GameRecord {
+            won,
+            time,
+            bbbv: 20,
+            clicks: None,
+            hints: 0,
+        }
+    }
+
+    #[test]
+    fn version_1_is_migrated() {
+        let stats = GameStats::from_json(V1).unwrap();
+        assert_eq!(stats.version, STATS_VERSION);
+        assert!(!stats.read_only);
+
+        let config = &stats.configs[0];
+        assert_eq!(config.config, beginner());
+        assert_eq!((config.wins, config.losses), (3, 2));
+        assert_eq!(config.best_time, Some(14000));
+        assert_eq!(config.clicked_wins, 0);
+        assert_eq!(config.best_efficiency, None);
+        assert_eq!((config.assisted_games, config.assisted_wins), (0, 0));
+    }
+
+    #[test]
+    fn version_4_keeps_its_grid_and_holes() {
+        let stats = GameStats::from_json(V4).unwrap();
+        assert_eq!(stats.version, STATS_VERSION);
+
+        let config = &stats.configs[0];
+        assert_eq!(config.config.topology, TopologyKind::Hex);
+        assert_eq!(config.config.holes, 12);
+        assert_eq!(config.config.cell_mines, 1);
+        assert_eq!(config.config.lives, 1);
+        assert_eq!(config.clicked_win_clicks, 150);
+        assert_eq!(config.best_efficiency, Some(0.8));
+        assert_eq!((config.assisted_games, config.assisted_wins), (0, 0));
+    }
+
+    #[test]
+    fn newer_files_are_left_alone() {
+        let newer = format!(
+            r#"{{ "version": {}, "configs": [], "unknown": true }}"#,
+            STATS_VERSION + 1
+        );
+        let mut stats = GameStats::from_json(&newer).unwrap();
+        assert!(stats.read_only);
+        assert!(stats.configs.is_empty());
+
+        // Saving is a no-op, so the path is never touched
+        stats.record(beginner(), game(true, 10000));
+        assert!(stats.save().is_ok());
+    }
+
+    #[test]
+    fn records_track_streaks_and_best_times() {
+        let mut stats = GameStats::default();
+        stats.record(beginner(), game(true, 30000));
+        stats.record(beginner(), game(true, 20000));
+        stats.record(beginner(), game(true, 25000));
+        let config_stats = stats.config(beginner()).unwrap();
+        assert_eq!(config_stats.best_time, Some(20000));
+        assert_eq!(config_stats.average_time(), Some(25000));
+        assert_eq!(config_stats.best_bbbv_per_second, Some(1.));
+        assert_eq!(
+            (config_stats.current_streak, config_stats.longest_streak),
+            (3, 3)
+        );
+
+        stats.record(beginner(), game(false, 5000));
+        stats.record(beginner(), game(true, 40000));
+        let config_stats = stats.config(beginner()).unwrap();
+        assert_eq!(
+            (config_stats.current_streak, config_stats.longest_streak),
+            (1, 3)
+        );
+        assert_eq!((config_stats.wins, config_stats.losses), (4, 1));
+        assert_eq!(config_stats.best_time, Some(20000));
+
+        let other = BoardConfig {
+            lives: 3,
+            ..beginner()
+        };
+        stats.record(other, game(true, 1000));
+        assert_eq!(stats.configs.len(), 2);
+        assert_eq!(stats.config(beginner()).unwrap().best_time, Some(20000));
+    }
+
+    #[test]
+    fn assisted_games_are_kept_apart() {
+        let mut stats = GameStats::default();
+        stats.record(beginner(), game(true, 30000));
+        let assisted = GameRecord {
+            hints: 2,
+            ..game(true, 1000)
+        };
+        stats.record(beginner(), assisted);
+        stats.record(
+            beginner(),
+            GameRecord {
+                won: false,
+                ..assisted
+            },
+        );
+
+        let config_stats = stats.config(beginner()).unwrap();
+        assert_eq!(
+            (config_stats.assisted_games, config_stats.assisted_wins),
+            (2, 1)
+        );
+        assert_eq!((config_stats.wins, config_stats.losses), (1, 0));
+        assert_eq!(config_stats.best_time, Some(30000));
+        assert_eq!(config_stats.current_streak, 1);
+    }
+
+    #[test]
+    fn counted_clicks_add_up() {
+        let mut stats = GameStats::default();
+        let clicks = ClickCounter {
+            left: 20,
+            right: 3,
+            chord: 2,
+            useful: 22,
+            wasted: 3,
+        };
+        stats.record(
+            beginner(),
+            GameRecord {
+                clicks: Some(clicks),
+                ..game(true, 10000)
+            },
+        );
+        stats.record(beginner(), game(true, 10000));
+
+        let config_stats = stats.config(beginner()).unwrap();
+        assert_eq!(config_stats.clicked_wins, 1);
+        assert_eq!(config_stats.clicked_win_clicks, 25);
+        assert_eq!(config_stats.efficiency(), Some(0.8));
+        assert_eq!(config_stats.wasted_per_win(), Some(3.));
+    }
+}
@@ -1,18 +1,29 @@
 use bevy::prelude::Resource;
 #[cfg(feature = "debug")]
 use colored::Colorize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Resource)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Resource, Serialize, Deserialize)]
 // #[reflect(Resource)]
 pub enum Tile {
-    Bomb,
+    /// Cell holding one or more mines
+    Bomb(u8),
+    /// Cell next to mines, with the total number of mines around it
     BombNeighbor(u8),
     Empty,
 }
 
 impl Tile {
     pub const fn is_bomb(&self) -> bool {
-        matches!(self, Self::Bomb)
+        matches!(self, Self::Bomb(_))
+    }
+
+    /// Number of mines in the cell
+    pub const fn mines(&self) -> u8 {
+        match self {
+            Self::Bomb(mines) => *mines,
+            _ => 0,
+        }
     }
     pub const fn is_empty(&self) -> bool {
         matches!(self, Self::Empty)
@@ -23,7 +34,8 @@ impl Tile {
         format!(
             "{}",
             match self {
-                Tile::Bomb => "*".bright_red(),
+                Tile::Bomb(1) => "*".bright_red(),
+                Tile::Bomb(mines) => mines.to_string().bright_red().bold(),
                 Tile::BombNeighbor(v) => match v {
                     1 => "1".cyan(),
                     2 => "2".green(),
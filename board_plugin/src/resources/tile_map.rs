@@ -1,51 +1,209 @@
 use crate::components::Coordinates;
+use crate::queue::Queue;
+use crate::resources::cells::CellSet;
+use crate::resources::generator::{self, GeneratorContext, MineGenerator, MineGenerators, Uniform};
+use crate::resources::mask::BoardMask;
 use crate::resources::tile::Tile;
-use rand::{thread_rng, Rng};
-
-use std::ops::{Deref, DerefMut};
-
-const NEIGHBOR_COORDS: [(i8, i8); 8] = [
-    // Bottom left
-    (-1, -1),
-    // Bottom
-    (0, -1),
-    // Bottom right
-    (1, -1),
-    // Left
-    (-1, 0),
-    // Right
-    (1, 0),
-    // Top Left
-    (-1, 1),
-    // Top
-    (0, 1),
-    // Top right
-    (1, 1),
-];
+use crate::resources::topology::TopologyKind;
+use crate::resources::BoardOptions;
+use bevy::log;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 #[derive(Debug, Clone)]
 pub struct TileMap {
+    /// Total number of mines
     pub bomb_count: u16,
+    /// Cells holding mines, fewer than `bomb_count` when cells hold several
+    pub mine_cells: u16,
+    /// Most mines a single cell can hold
+    pub cell_mines: u8,
     pub height: u16,
     pub width: u16,
-    map: Vec<Vec<Tile>>,
+    /// Seed the uniform generator placed the mines with, `None` for other
+    /// layouts
+    pub seed: Option<u64>,
+    /// Grid the tiles are laid out on, it decides which tiles touch
+    pub topology: TopologyKind,
+    /// Cells of the rectangle that are not part of the board
+    holes: CellSet,
+    /// Tiles in row order, bottom row first. Holes are stored as empty
+    /// tiles but never show up through the accessors.
+    map: Vec<Tile>,
 }
 
 impl TileMap {
     pub fn empty(width: u16, height: u16) -> Self {
-        let map = (0..height)
-            .into_iter()
-            .map(|_| (0..width).into_iter().map(|_| Tile::Empty).collect())
-            .collect();
+        Self::empty_on(TopologyKind::default(), width, height)
+    }
+
+    pub fn empty_on(topology: TopologyKind, width: u16, height: u16) -> Self {
+        Self::empty_masked(topology, width, height, CellSet::new(width, height))
+    }
+
+    /// Map without mines whose cells in `holes` don't exist
+    pub fn empty_masked(topology: TopologyKind, width: u16, height: u16, holes: CellSet) -> Self {
+        let map = vec![Tile::Empty; width as usize * height as usize];
 
         Self {
             bomb_count: 0,
+            mine_cells: 0,
+            cell_mines: 1,
             height,
             width,
+            seed: None,
+            topology,
+            holes,
             map,
         }
     }
 
+    /// Map without mines shaped by the options: their size, grid and mask
+    pub fn blank(options: &BoardOptions) -> Self {
+        let (width, height) = options.grid_size();
+        let mut tile_map =
+            Self::empty_masked(options.topology(), width, height, options.hole_set());
+        tile_map.cell_mines = options.cell_mines.max(1);
+        tile_map
+    }
+
+    /// Map described by the options, using the built-in generators
+    pub fn from_options(options: &BoardOptions) -> Self {
+        Self::generate(options, &MineGenerators::default())
+    }
+
+    /// Map described by the options: their fixed layout if they have one,
+    /// mines placed by their generator otherwise
+    pub fn generate(options: &BoardOptions, generators: &MineGenerators) -> Self {
+        let mut tile_map = Self::blank(options);
+        if let Some(mines) = &options.layout {
+            tile_map.place_mines(mines);
+            return tile_map;
+        }
+
+        let seed = options.seed.unwrap_or_else(|| thread_rng().gen());
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let context = GeneratorContext::new(options);
+        let mut mines = match generators.get(&options.generator) {
+            Some(mine_generator) => mine_generator.generate(&context, &mut rng),
+            None => {
+                log::warn!(
+                    "Unknown mine generator {}, using uniform",
+                    options.generator
+                );
+                Uniform.generate(&context, &mut rng)
+            }
+        };
+        if tile_map.cell_mines > 1 {
+            mines =
+                generator::stack_mines(&mines, options.bomb_count, tile_map.cell_mines, &mut rng);
+        }
+
+        tile_map.place_mines(&mines);
+        // Share codes rebuild seeded boards with the uniform generator
+        if options.generator == generator::UNIFORM {
+            tile_map.seed = Some(seed);
+        }
+        tile_map
+    }
+
+    /// Map with mines at the given coordinates, used to rebuild known layouts
+    pub fn from_mines(width: u16, height: u16, mines: &[Coordinates]) -> Self {
+        Self::from_mines_on(TopologyKind::default(), width, height, mines)
+    }
+
+    /// Map with mines at the given coordinates on any grid
+    pub fn from_mines_on(
+        topology: TopologyKind,
+        width: u16,
+        height: u16,
+        mines: &[Coordinates],
+    ) -> Self {
+        let mut tile_map = Self::empty_on(topology, width, height);
+        tile_map.place_mines(mines);
+        tile_map
+    }
+
+    /// Adds a mine at each of the given coordinates, skipping holes, tiles
+    /// outside of the map and cells already holding `cell_mines` mines.
+    /// Repeated coordinates stack mines in the same cell. The numbers are
+    /// derived in a single pass over the mines' neighbors.
+    pub fn place_mines(&mut self, mines: &[Coordinates]) {
+        let mut counts = vec![0u8; self.map.len()];
+        let mut neighbors = Vec::new();
+        for mine in mines.iter() {
+            let index = match self.index(*mine) {
+                Some(index) if self.map[index].mines() < self.cell_mines => index,
+                _ => continue,
+            };
+            let held = self.map[index].mines();
+            if held == 0 {
+                self.mine_cells += 1;
+            }
+            self.map[index] = Tile::Bomb(held + 1);
+            self.bomb_count += 1;
+            neighbors.clear();
+            self.neighbors_into(*mine, &mut neighbors);
+            for neighbor in neighbors.iter() {
+                counts[self.index_unchecked(*neighbor)] += 1;
+            }
+        }
+        for (tile, count) in self.map.iter_mut().zip(counts) {
+            match tile {
+                Tile::Bomb(_) => (),
+                Tile::BombNeighbor(previous) => *previous += count,
+                Tile::Empty if count > 0 => *tile = Tile::BombNeighbor(count),
+                Tile::Empty => (),
+            }
+        }
+    }
+
+    /// Position of every mine, cells holding several mines are repeated
+    pub fn mines(&self) -> Vec<Coordinates> {
+        self.map
+            .iter()
+            .enumerate()
+            .flat_map(|(index, tile)| {
+                std::iter::repeat(self.coordinates_of(index)).take(tile.mines() as usize)
+            })
+            .collect()
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Position of the tile in the flat storage
+    pub fn index(&self, coordinates: Coordinates) -> Option<usize> {
+        self.contains(coordinates)
+            .then(|| self.index_unchecked(coordinates))
+    }
+
+    fn index_unchecked(&self, coordinates: Coordinates) -> usize {
+        coordinates.y as usize * self.width as usize + coordinates.x as usize
+    }
+
+    pub fn coordinates_of(&self, index: usize) -> Coordinates {
+        Coordinates {
+            x: (index % self.width as usize) as u16,
+            y: (index / self.width as usize) as u16,
+        }
+    }
+
+    /// Rows of tiles, bottom row first. Holes show up as empty tiles,
+    /// check `contains` to tell them apart.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[Tile]> {
+        self.map.chunks(self.width.max(1) as usize)
+    }
+
+    /// Replaces a tile without updating its neighbors, for clients learning
+    /// the layout one tile at a time
+    pub fn set_tile(&mut self, coordinates: Coordinates, tile: Tile) {
+        if let Some(index) = self.index(coordinates) {
+            self.map[index] = tile;
+        }
+    }
+
     #[cfg(feature = "debug")]
     pub fn console_output(&self) -> String {
         let mut buffer = format!(
@@ -55,7 +213,7 @@ impl TileMap {
         let line: String = (0..=(self.width + 1)).into_iter().map(|_| '-').collect();
         buffer = format!("{}{}\n", buffer, line);
 
-        for line in self.iter().rev() {
+        for line in self.rows().rev() {
             buffer = format!("{}|", buffer);
             for tile in line.iter() {
                 buffer = format!("{}{}", buffer, tile.console_output());
@@ -65,85 +223,164 @@ impl TileMap {
         format!("{}{}", buffer, line)
     }
 
+    /// Tiles touching `coordinates`, all of them inside the map
     pub fn get_neighbor_coordinates(
         &self,
         coordinates: Coordinates,
     ) -> impl Iterator<Item = Coordinates> {
-        NEIGHBOR_COORDS
-            .iter()
-            .copied()
-            .map(move |tuple| coordinates + tuple)
+        let mut neighbors = Vec::new();
+        self.neighbors_into(coordinates, &mut neighbors);
+        neighbors.into_iter()
     }
 
-    pub fn is_empty_at(&self, coordinates: Coordinates) -> bool {
-        if coordinates.x >= self.width || coordinates.y >= self.height {
-            return false;
-        };
-        self.map[coordinates.y as usize][coordinates.x as usize].is_empty()
+    /// Appends the tiles touching `coordinates` to `neighbors`, lets hot
+    /// loops reuse one buffer
+    pub fn neighbors_into(&self, coordinates: Coordinates, neighbors: &mut Vec<Coordinates>) {
+        let start = neighbors.len();
+        self.topology
+            .topology()
+            .neighbors(coordinates, self.size(), neighbors);
+        if !self.holes.is_empty() {
+            // Only the neighbors just added are checked
+            let mut index = 0;
+            neighbors.retain(|neighbor| {
+                index += 1;
+                index <= start || !self.holes.contains(neighbor)
+            });
+        }
     }
 
-    pub fn is_bomb_at(&self, coordinates: Coordinates) -> bool {
-        if coordinates.x >= self.width || coordinates.y >= self.height {
-            return false;
-        };
-        self.map[coordinates.y as usize][coordinates.x as usize].is_bomb()
+    /// Whether the tile is part of the board: inside of the grid and not a
+    /// hole
+    pub fn contains(&self, coordinates: Coordinates) -> bool {
+        self.topology.topology().contains(coordinates, self.size())
+            && !self.holes.contains(&coordinates)
     }
 
-    pub fn bomb_count_at(&self, coordinates: Coordinates) -> u8 {
-        if self.is_bomb_at(coordinates) {
-            return 0;
-        }
-        let res = self
-            .get_neighbor_coordinates(coordinates)
-            .filter(|coord| self.is_bomb_at(*coord))
-            .count();
-        res as u8
-    }
-
-    pub fn set_bombs(&mut self, bomb_count: u16) {
-        self.bomb_count = bomb_count;
-        let mut bombs_left = bomb_count;
-        let mut rng = thread_rng();
-
-        while bombs_left > 0 {
-            let (x, y) = (
-                rng.gen_range(0..self.width) as usize,
-                rng.gen_range(0..self.height) as usize,
-            );
-
-            if let Tile::Empty = self.map[y][x] {
-                self[y][x] = Tile::Bomb;
-                bombs_left -= 1;
+    /// Mask of the holes, `None` when the whole rectangle is played
+    pub fn mask(&self) -> Option<BoardMask> {
+        // Every layer has the same holes
+        let width = self.width / self.topology.layers();
+        (!self.holes.is_empty()).then(|| BoardMask {
+            width,
+            height: self.height,
+            holes: self.holes.iter().filter(|hole| hole.x < width).collect(),
+        })
+    }
+
+    /// Number of cells of the rectangle that are not part of the board
+    pub fn hole_count(&self) -> usize {
+        self.holes.len()
+    }
+
+    /// Every tile of the board, in row order
+    pub fn coordinates(&self) -> impl Iterator<Item = Coordinates> + '_ {
+        (0..self.map.len())
+            .map(|index| self.coordinates_of(index))
+            .filter(|coordinates| !self.holes.contains(coordinates))
+    }
+
+    pub fn tile_at(&self, coordinates: Coordinates) -> Option<Tile> {
+        self.index(coordinates).map(|index| self.map[index])
+    }
+
+    /// Coordinates uncovered by revealing `coordinates`: the tile itself and,
+    /// if it is empty, the whole opening around it. Tiles for which
+    /// `is_flagged` returns `true` stay covered.
+    pub fn flood_fill(
+        &self,
+        coordinates: Coordinates,
+        is_flagged: impl Fn(&Coordinates) -> bool,
+    ) -> Vec<Coordinates> {
+        let mut queue = Queue::from([coordinates]);
+        let mut visited = CellSet::new(self.width, self.height);
+        visited.insert(coordinates);
+        let mut discovered = Vec::new();
+        let mut neighbors = Vec::new();
+
+        while let Some(current_coordinates) = queue.dequeue() {
+            if !self.contains(current_coordinates) || is_flagged(&current_coordinates) {
+                continue;
+            }
+
+            discovered.push(current_coordinates);
+
+            if !self.is_empty_at(current_coordinates) {
+                continue;
             }
-            // Place bomb neighbors
-            for y in 0..self.height {
-                for x in 0..self.width {
-                    let coords = Coordinates { x, y };
-                    if self.is_bomb_at(coords) {
-                        continue;
-                    }
-                    let num = self.bomb_count_at(coords);
-                    if num == 0 {
-                        continue;
-                    }
-                    let tile = &mut self[y as usize][x as usize];
-                    *tile = Tile::BombNeighbor(num);
+
+            neighbors.clear();
+            self.neighbors_into(current_coordinates, &mut neighbors);
+            for neighbor_coordinates in neighbors.iter() {
+                if visited.insert(*neighbor_coordinates) {
+                    queue.enqueue(*neighbor_coordinates);
                 }
             }
         }
+
+        discovered
+    }
+
+    /// Covered neighbors uncovered by chording on the number at `coordinates`.
+    /// Chording only goes through once the flags around the number match it.
+    /// `flag_mines` gives the mines a flag stands for, 0 without a flag.
+    pub fn chord_targets(
+        &self,
+        coordinates: Coordinates,
+        is_discovered: impl Fn(&Coordinates) -> bool,
+        flag_mines: impl Fn(&Coordinates) -> u8,
+    ) -> Vec<Coordinates> {
+        let count = match self.tile_at(coordinates) {
+            Some(Tile::BombNeighbor(count)) if is_discovered(&coordinates) => count,
+            _ => return Vec::new(),
+        };
+
+        let neighbors: Vec<Coordinates> = self.get_neighbor_coordinates(coordinates).collect();
+        let flags: u16 = neighbors
+            .iter()
+            .map(|neighbor| flag_mines(neighbor) as u16)
+            .sum();
+        if flags != count as u16 {
+            return Vec::new();
+        }
+
+        neighbors
+            .into_iter()
+            .filter(|neighbor| flag_mines(neighbor) == 0 && !is_discovered(neighbor))
+            .collect()
+    }
+
+    /// First empty tile in row order, used as the opening for safe starts
+    pub fn first_empty(&self) -> Option<Coordinates> {
+        self.coordinates()
+            .find(|coordinates| self.is_empty_at(*coordinates))
     }
-}
 
-impl Deref for TileMap {
-    type Target = Vec<Vec<Tile>>;
+    pub fn is_empty_at(&self, coordinates: Coordinates) -> bool {
+        self.tile_at(coordinates).is_some_and(|tile| tile.is_empty())
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.map
+    pub fn is_bomb_at(&self, coordinates: Coordinates) -> bool {
+        self.tile_at(coordinates).is_some_and(|tile| tile.is_bomb())
+    }
+
+    /// Number of mines in the cell
+    pub fn mines_at(&self, coordinates: Coordinates) -> u8 {
+        self.tile_at(coordinates).map_or(0, |tile| tile.mines())
+    }
+
+    /// Total of the mines around a tile without mines
+    pub fn bomb_count_at(&self, coordinates: Coordinates) -> u8 {
+        if !self.contains(coordinates) || self.is_bomb_at(coordinates) {
+            return 0;
+        }
+        self.get_neighbor_coordinates(coordinates)
+            .map(|neighbor| self.mines_at(neighbor))
+            .sum()
     }
-}
 
-impl DerefMut for TileMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.map
+    /// Number of tiles without a mine
+    pub fn safe_count(&self) -> usize {
+        self.map.len() - self.holes.len() - self.mine_cells as usize
     }
 }
@@ -0,0 +1,725 @@
+//! Grid topologies: which tiles touch, which tiles exist and where tiles
+//! sit on screen
+
+use std::fmt::{self, Display, Formatter};
+
+use bevy::prelude::{FromReflect, Reflect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::components::Coordinates;
+
+/// Shape of the grid a board is laid out on. Positions are in board space:
+/// pixels from the bottom left corner of the board.
+pub trait Topology: Send + Sync {
+    /// Appends the neighbors of `coordinates` on a board of `size` to
+    /// `neighbors`. Every neighbor is inside the board and listed once.
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    );
+
+    /// Whether the tile is part of a board of `size`
+    fn contains(&self, coordinates: Coordinates, size: (u16, u16)) -> bool {
+        coordinates.x < size.0 && coordinates.y < size.1
+    }
+
+    /// Tile in the middle of a board of `size`
+    fn center(&self, size: (u16, u16)) -> Coordinates {
+        Coordinates {
+            x: size.0 / 2,
+            y: size.1 / 2,
+        }
+    }
+
+    /// Fewest steps from neighbor to neighbor between two tiles
+    fn distance(&self, a: Coordinates, b: Coordinates, size: (u16, u16)) -> u16;
+
+    /// Center of the tile
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2;
+
+    /// Extent of a single tile's sprite
+    fn tile_sprite_size(&self, tile_size: f32) -> Vec2 {
+        Vec2::splat(tile_size)
+    }
+
+    /// Tile under `position`, `None` outside of the board
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates>;
+
+    /// Extent of the whole board
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2;
+}
+
+/// The built-in topologies
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Reflect)]
+pub enum TopologyKind {
+    /// Square tiles touching on sides and corners
+    #[default]
+    Square8,
+    /// Square tiles touching on sides only
+    Square4,
+    /// Square tiles touching a knight's move away
+    Knight,
+    /// Square tiles touching up to two tiles away, in a 5x5 square
+    Square24,
+    /// Hexagonal tiles in offset rows
+    Hex,
+    /// Square tiles wrapping around at the edges
+    Torus,
+    /// Stacked square layers, tiles touch across adjacent layers
+    Layers(Layers),
+}
+
+impl TopologyKind {
+    pub fn topology(&self) -> &dyn Topology {
+        match self {
+            TopologyKind::Square8 => &Square8,
+            TopologyKind::Square4 => &Square4,
+            TopologyKind::Knight => &Knight,
+            TopologyKind::Square24 => &Square24,
+            TopologyKind::Hex => &Hex,
+            TopologyKind::Torus => &Torus,
+            TopologyKind::Layers(layers) => layers,
+        }
+    }
+
+    /// Number of layers the board is made of
+    pub fn layers(&self) -> u16 {
+        match self {
+            TopologyKind::Layers(layers) => layers.count,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for TopologyKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TopologyKind::Square8 => "square",
+            TopologyKind::Square4 => "orthogonal",
+            TopologyKind::Knight => "knight",
+            TopologyKind::Square24 => "5x5",
+            TopologyKind::Hex => "hex",
+            TopologyKind::Torus => "torus",
+            TopologyKind::Layers(layers) => return write!(f, "{} layers", layers.count),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+const SQUARE8_OFFSETS: [(i16, i16); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+const SQUARE4_OFFSETS: [(i16, i16); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+const KNIGHT_OFFSETS: [(i16, i16); 8] = [
+    (-1, -2),
+    (1, -2),
+    (-2, -1),
+    (2, -1),
+    (-2, 1),
+    (2, 1),
+    (-1, 2),
+    (1, 2),
+];
+
+/// Every offset of the 5x5 square around a tile but its center
+const SQUARE24_OFFSETS: [(i16, i16); 24] = {
+    let mut offsets = [(0, 0); 24];
+    let mut i = 0;
+    let mut dy = -2;
+    while dy <= 2 {
+        let mut dx = -2;
+        while dx <= 2 {
+            if dx != 0 || dy != 0 {
+                offsets[i] = (dx, dy);
+                i += 1;
+            }
+            dx += 1;
+        }
+        dy += 1;
+    }
+    offsets
+};
+
+/// Neighbors of `coordinates` at the given offsets, those past an edge are
+/// left out
+fn push_offsets(
+    coordinates: Coordinates,
+    size: (u16, u16),
+    offsets: &[(i16, i16)],
+    neighbors: &mut Vec<Coordinates>,
+) {
+    neighbors.extend(
+        offsets
+            .iter()
+            .filter_map(|&(dx, dy)| coordinates.offset(dx, dy))
+            .filter(|neighbor| neighbor.x < size.0 && neighbor.y < size.1),
+    );
+}
+
+fn square_center(coordinates: Coordinates, tile_size: f32) -> Vec2 {
+    Vec2::new(
+        coordinates.x as f32 * tile_size + tile_size / 2.,
+        coordinates.y as f32 * tile_size + tile_size / 2.,
+    )
+}
+
+fn square_tile_at(position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+    let cell = (position / tile_size).floor();
+    let in_board = cell.x >= 0. && cell.y >= 0. && cell.x < size.0 as f32 && cell.y < size.1 as f32;
+    in_board.then_some(Coordinates {
+        x: cell.x as u16,
+        y: cell.y as u16,
+    })
+}
+
+fn square_board_size(size: (u16, u16), tile_size: f32) -> Vec2 {
+    Vec2::new(size.0 as f32 * tile_size, size.1 as f32 * tile_size)
+}
+
+/// The classic grid
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Square8;
+
+impl Topology for Square8 {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        push_offsets(coordinates, size, &SQUARE8_OFFSETS, neighbors);
+    }
+
+    fn distance(&self, a: Coordinates, b: Coordinates, _: (u16, u16)) -> u16 {
+        a.x.abs_diff(b.x).max(a.y.abs_diff(b.y))
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        square_center(coordinates, tile_size)
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        square_tile_at(position, size, tile_size)
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        square_board_size(size, tile_size)
+    }
+}
+
+/// Square grid where diagonal tiles don't touch
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Square4;
+
+impl Topology for Square4 {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        push_offsets(coordinates, size, &SQUARE4_OFFSETS, neighbors);
+    }
+
+    fn distance(&self, a: Coordinates, b: Coordinates, _: (u16, u16)) -> u16 {
+        a.x.abs_diff(b.x) + a.y.abs_diff(b.y)
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        square_center(coordinates, tile_size)
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        square_tile_at(position, size, tile_size)
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        square_board_size(size, tile_size)
+    }
+}
+
+/// Square grid where tiles touch a knight's move away, the tiles right
+/// around a tile don't touch it
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Knight;
+
+impl Topology for Knight {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        push_offsets(coordinates, size, &KNIGHT_OFFSETS, neighbors);
+    }
+
+    /// Knight moves on an unbounded board, edges can make the way longer
+    fn distance(&self, a: Coordinates, b: Coordinates, _: (u16, u16)) -> u16 {
+        let (dx, dy) = (a.x.abs_diff(b.x) as i32, a.y.abs_diff(b.y) as i32);
+        let (x, y) = (dx.max(dy), dx.min(dy));
+        let moves = match (x, y) {
+            (1, 0) => 3,
+            (2, 2) => 4,
+            _ => {
+                let delta = x - y;
+                let step = if y > delta { 3 } else { 4 };
+                delta - 2 * (delta - y).div_euclid(step)
+            }
+        };
+        moves as u16
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        square_center(coordinates, tile_size)
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        square_tile_at(position, size, tile_size)
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        square_board_size(size, tile_size)
+    }
+}
+
+/// Square grid where tiles touch up to two tiles away, numbers count up to
+/// 24 mines
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Square24;
+
+impl Topology for Square24 {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        push_offsets(coordinates, size, &SQUARE24_OFFSETS, neighbors);
+    }
+
+    fn distance(&self, a: Coordinates, b: Coordinates, _: (u16, u16)) -> u16 {
+        a.x.abs_diff(b.x).max(a.y.abs_diff(b.y)).div_ceil(2)
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        square_center(coordinates, tile_size)
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        square_tile_at(position, size, tile_size)
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        square_board_size(size, tile_size)
+    }
+}
+
+/// Square grid whose opposite edges touch: the tiles right of the last
+/// column are those of the first column, and likewise for rows
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Torus;
+
+impl Topology for Torus {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        let start = neighbors.len();
+        for (dx, dy) in SQUARE8_OFFSETS {
+            let neighbor = Coordinates {
+                x: (coordinates.x as i32 + dx as i32).rem_euclid(size.0 as i32) as u16,
+                y: (coordinates.y as i32 + dy as i32).rem_euclid(size.1 as i32) as u16,
+            };
+            // Narrow boards reach the same tile from both sides
+            if neighbor != coordinates && !neighbors[start..].contains(&neighbor) {
+                neighbors.push(neighbor);
+            }
+        }
+    }
+
+    fn distance(&self, a: Coordinates, b: Coordinates, size: (u16, u16)) -> u16 {
+        // Either straight across or around the seam, whichever is shorter
+        let (dx, dy) = (a.x.abs_diff(b.x), a.y.abs_diff(b.y));
+        let dx = dx.min(size.0.saturating_sub(dx));
+        let dy = dy.min(size.1.saturating_sub(dy));
+        dx.max(dy)
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        square_center(coordinates, tile_size)
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        square_tile_at(position, size, tile_size)
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        square_board_size(size, tile_size)
+    }
+}
+
+/// Pointy topped hexagons, odd rows are shifted right by half a tile.
+/// `tile_size` is the width of a hexagon.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Hex;
+
+const HEX_EVEN_ROW_OFFSETS: [(i16, i16); 6] = [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+const HEX_ODD_ROW_OFFSETS: [(i16, i16); 6] = [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+impl Hex {
+    /// Distance from the center of a hexagon to its corners
+    pub fn radius(tile_size: f32) -> f32 {
+        tile_size / 3f32.sqrt()
+    }
+
+    /// Vertical distance between the centers of two rows
+    pub fn row_height(tile_size: f32) -> f32 {
+        Self::radius(tile_size) * 1.5
+    }
+
+    /// Axial coordinates of a tile, in which neighbors differ by one of
+    /// six fixed steps
+    fn axial(coordinates: Coordinates) -> (i32, i32) {
+        let (x, y) = (coordinates.x as i32, coordinates.y as i32);
+        (x - (y - (y & 1)) / 2, y)
+    }
+}
+
+impl Topology for Hex {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        let offsets = match coordinates.y % 2 {
+            0 => &HEX_EVEN_ROW_OFFSETS,
+            _ => &HEX_ODD_ROW_OFFSETS,
+        };
+        push_offsets(coordinates, size, offsets, neighbors);
+    }
+
+    fn distance(&self, a: Coordinates, b: Coordinates, _: (u16, u16)) -> u16 {
+        let ((aq, ar), (bq, br)) = (Self::axial(a), Self::axial(b));
+        let (dq, dr) = (aq - bq, ar - br);
+        ((dq.abs() + dr.abs() + (dq + dr).abs()) / 2) as u16
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        let shift = (coordinates.y % 2) as f32 * tile_size / 2.;
+        Vec2::new(
+            coordinates.x as f32 * tile_size + tile_size / 2. + shift,
+            coordinates.y as f32 * Self::row_height(tile_size) + Self::radius(tile_size),
+        )
+    }
+
+    fn tile_sprite_size(&self, tile_size: f32) -> Vec2 {
+        Vec2::new(tile_size, 2. * Self::radius(tile_size))
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        // Axial coordinates relative to the center of the first tile, then
+        // rounded to the nearest hexagon in cube coordinates
+        let radius = Self::radius(tile_size);
+        let (x, y) = (position.x - tile_size / 2., position.y - radius);
+        let r = 2. / 3. * y / radius;
+        let q = (3f32.sqrt() / 3. * x - y / 3.) / radius;
+        let s = -q - r;
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+
+        let (q, row) = (rq as i32, rr as i32);
+        let column = q + (row - (row & 1)) / 2;
+        let in_board = column >= 0 && row >= 0 && column < size.0 as i32 && row < size.1 as i32;
+        in_board.then_some(Coordinates {
+            x: column as u16,
+            y: row as u16,
+        })
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        let shift = if size.1 > 1 { tile_size / 2. } else { 0. };
+        Vec2::new(
+            size.0 as f32 * tile_size + shift,
+            size.1.saturating_sub(1) as f32 * Self::row_height(tile_size)
+                + 2. * Self::radius(tile_size),
+        )
+    }
+}
+
+/// Square layers stacked on top of each other, each tile touches up to 26
+/// others: the 8 around it and the 9 facing it on each adjacent layer.
+///
+/// Layers are stored side by side: the tile at `(x, y)` of layer `z` has
+/// the coordinates `(z * width + x, y)`, so boards are `count * width`
+/// tiles wide. On screen the layers are separated by a one tile gap.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Reflect, FromReflect)]
+pub struct Layers {
+    pub count: u16,
+    /// Width of a single layer
+    pub width: u16,
+}
+
+impl Layers {
+    /// Layer of a tile and its column within the layer
+    pub fn split(&self, coordinates: Coordinates) -> (u16, u16) {
+        (coordinates.x / self.width, coordinates.x % self.width)
+    }
+
+    /// Column of the tile on screen, counting the gaps between layers
+    fn screen_column(&self, coordinates: Coordinates) -> u16 {
+        let (layer, x) = self.split(coordinates);
+        layer * (self.width + 1) + x
+    }
+}
+
+impl Topology for Layers {
+    fn neighbors(
+        &self,
+        coordinates: Coordinates,
+        size: (u16, u16),
+        neighbors: &mut Vec<Coordinates>,
+    ) {
+        let (layer, x) = self.split(coordinates);
+        for dz in -1..=1i32 {
+            let z = layer as i32 + dz;
+            if z < 0 || z >= self.count as i32 {
+                continue;
+            }
+            for dy in -1..=1i32 {
+                for dx in -1..=1i32 {
+                    let (nx, ny) = (x as i32 + dx, coordinates.y as i32 + dy);
+                    let inside = nx >= 0 && nx < self.width as i32 && ny >= 0 && ny < size.1 as i32;
+                    if inside && (dx, dy, dz) != (0, 0, 0) {
+                        neighbors.push(Coordinates {
+                            x: (z * self.width as i32 + nx) as u16,
+                            y: ny as u16,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn center(&self, size: (u16, u16)) -> Coordinates {
+        Coordinates {
+            x: self.count / 2 * self.width + self.width / 2,
+            y: size.1 / 2,
+        }
+    }
+
+    fn distance(&self, a: Coordinates, b: Coordinates, _: (u16, u16)) -> u16 {
+        let ((az, ax), (bz, bx)) = (self.split(a), self.split(b));
+        az.abs_diff(bz).max(ax.abs_diff(bx)).max(a.y.abs_diff(b.y))
+    }
+
+    fn tile_center(&self, coordinates: Coordinates, tile_size: f32) -> Vec2 {
+        let column = self.screen_column(coordinates);
+        square_center(
+            Coordinates {
+                x: column,
+                y: coordinates.y,
+            },
+            tile_size,
+        )
+    }
+
+    fn tile_at(&self, position: Vec2, size: (u16, u16), tile_size: f32) -> Option<Coordinates> {
+        let screen_width = (self.width + 1) * self.count;
+        let cell = square_tile_at(position, (screen_width, size.1), tile_size)?;
+        let (layer, x) = (cell.x / (self.width + 1), cell.x % (self.width + 1));
+        // The gaps between layers hold no tile
+        (x < self.width).then_some(Coordinates {
+            x: layer * self.width + x,
+            y: cell.y,
+        })
+    }
+
+    fn board_size(&self, size: (u16, u16), tile_size: f32) -> Vec2 {
+        let columns = (self.width + 1) * self.count - 1;
+        square_board_size((columns, size.1), tile_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{tile::Tile, tile_map::TileMap};
+
+    fn neighbors(topology: &dyn Topology, x: u16, y: u16, size: (u16, u16)) -> Vec<Coordinates> {
+        let mut neighbors = Vec::new();
+        topology.neighbors(Coordinates { x, y }, size, &mut neighbors);
+        neighbors
+    }
+
+    fn tiles(size: (u16, u16)) -> impl Iterator<Item = Coordinates> {
+        (0..size.1).flat_map(move |y| (0..size.0).map(move |x| Coordinates { x, y }))
+    }
+
+    /// Every tile touches its neighbors back
+    fn assert_symmetric(topology: &dyn Topology, size: (u16, u16)) {
+        for tile in tiles(size) {
+            for neighbor in neighbors(topology, tile.x, tile.y, size) {
+                assert!(topology.contains(neighbor, size));
+                assert!(neighbors(topology, neighbor.x, neighbor.y, size).contains(&tile));
+            }
+        }
+    }
+
+    #[test]
+    fn hex_neighbors_at_edges_and_corners() {
+        let size = (5, 4);
+        let count = |x, y| neighbors(&Hex, x, y, size).len();
+        assert_eq!(count(2, 1), 6);
+        assert_eq!(count(2, 2), 6);
+        // Even rows lean left, odd rows right
+        assert_eq!(count(0, 0), 2);
+        assert_eq!(count(4, 0), 3);
+        assert_eq!(count(0, 1), 5);
+        assert_eq!(count(4, 1), 3);
+        assert_eq!(count(0, 2), 3);
+        assert_eq!(count(4, 3), 2);
+        assert_symmetric(&Hex, size);
+    }
+
+    #[test]
+    fn hex_tile_at_finds_the_tile_under_the_cursor() {
+        let (size, tile_size) = ((5, 4), 30.);
+        let radius = Hex::radius(tile_size);
+        for tile in tiles(size) {
+            let center = Hex.tile_center(tile, tile_size);
+            assert_eq!(Hex.tile_at(center, size, tile_size), Some(tile));
+            // Just inside the top corner and the side edges
+            let inside = [
+                Vec2::new(0., radius * 0.95),
+                Vec2::new(tile_size * 0.48, 0.),
+                Vec2::new(-tile_size * 0.48, 0.),
+            ];
+            for offset in inside {
+                assert_eq!(Hex.tile_at(center + offset, size, tile_size), Some(tile));
+            }
+        }
+
+        // Across the right edge of a tile lies its right neighbor
+        let center = Hex.tile_center(Coordinates { x: 1, y: 1 }, tile_size);
+        assert_eq!(
+            Hex.tile_at(center + Vec2::new(tile_size * 0.52, 0.), size, tile_size),
+            Some(Coordinates { x: 2, y: 1 })
+        );
+        // Odd rows are shifted, their first tile starts half a tile in
+        let row_start = Vec2::new(tile_size * 0.2, center.y);
+        assert_eq!(Hex.tile_at(row_start, size, tile_size), None);
+        assert_eq!(Hex.tile_at(Vec2::new(-1., 1.), size, tile_size), None);
+        let board = Hex.board_size(size, tile_size);
+        assert_eq!(Hex.tile_at(board + Vec2::ONE, size, tile_size), None);
+    }
+
+    #[test]
+    fn layer_neighbors_stay_within_adjacent_layers() {
+        let layers = Layers { count: 3, width: 4 };
+        let size = (12, 3);
+        let count = |x, y| neighbors(&layers, x, y, size).len();
+        // Middle layer: its own ring plus the full blocks above and below
+        assert_eq!(count(5, 1), 26);
+        assert_eq!(count(4, 0), 11);
+        // Outer layers only have one layer next to them
+        assert_eq!(count(1, 1), 17);
+        assert_eq!(count(0, 0), 7);
+        assert_eq!(count(11, 2), 7);
+
+        // The last column of a layer doesn't touch the first one of the next
+        let edge = neighbors(&layers, 3, 1, size);
+        assert_eq!(edge.len(), 11);
+        assert!(!edge.iter().any(|c| c.x == 4 || c.x == 8));
+        assert!(edge.contains(&Coordinates { x: 7, y: 1 }));
+        // Nor does the first layer reach the last
+        assert!(neighbors(&layers, 1, 1, size).iter().all(|c| c.x < 8));
+        assert_symmetric(&layers, size);
+    }
+
+    #[test]
+    fn layer_gaps_hold_no_tile() {
+        let (layers, size, tile_size) = (Layers { count: 3, width: 4 }, (12, 3), 10.);
+        for tile in tiles(size) {
+            let center = layers.tile_center(tile, tile_size);
+            assert_eq!(layers.tile_at(center, size, tile_size), Some(tile));
+        }
+        // The gap sits right of the last column of the first layer
+        let last = layers.tile_center(Coordinates { x: 3, y: 1 }, tile_size);
+        let gap = last + Vec2::new(tile_size, 0.);
+        assert_eq!(layers.tile_at(gap, size, tile_size), None);
+        assert_eq!(
+            layers.tile_at(gap + Vec2::new(tile_size, 0.), size, tile_size),
+            Some(Coordinates { x: 4, y: 1 })
+        );
+    }
+
+    #[test]
+    fn orthogonal_neighbors_share_a_side() {
+        let size = (4, 3);
+        let count = |x, y| neighbors(&Square4, x, y, size).len();
+        assert_eq!(count(1, 1), 4);
+        assert_eq!(count(1, 0), 3);
+        assert_eq!(count(0, 0), 2);
+        assert_eq!(count(3, 2), 2);
+        assert!(!neighbors(&Square4, 1, 1, size).contains(&Coordinates { x: 2, y: 2 }));
+        assert_symmetric(&Square4, size);
+    }
+
+    #[test]
+    fn knight_neighbors_are_a_knight_move_away() {
+        let size = (5, 5);
+        let count = |x, y| neighbors(&Knight, x, y, size).len();
+        assert_eq!(count(2, 2), 8);
+        assert_eq!(count(1, 1), 4);
+        assert_eq!(count(1, 0), 3);
+        assert_eq!(count(0, 0), 2);
+        assert_eq!(count(4, 4), 2);
+        // The tiles right around don't touch
+        let center = neighbors(&Knight, 2, 2, size);
+        assert!(center
+            .iter()
+            .all(|c| c.x.abs_diff(2) + c.y.abs_diff(2) == 3));
+        assert_symmetric(&Knight, size);
+    }
+
+    #[test]
+    fn extended_neighbors_fill_a_5x5_square() {
+        let size = (7, 6);
+        let count = |x, y| neighbors(&Square24, x, y, size).len();
+        assert_eq!(count(3, 3), 24);
+        assert_eq!(count(2, 2), 24);
+        assert_eq!(count(1, 1), 15);
+        assert_eq!(count(0, 2), 14);
+        assert_eq!(count(0, 0), 8);
+        assert_eq!(count(6, 5), 8);
+        assert_symmetric(&Square24, size);
+    }
+
+    #[test]
+    fn extended_numbers_count_up_to_24() {
+        let center = Coordinates { x: 2, y: 2 };
+        let mines: Vec<_> = tiles((5, 5)).filter(|tile| *tile != center).collect();
+        let tile_map = TileMap::from_mines_on(TopologyKind::Square24, 5, 5, &mines);
+        assert_eq!(tile_map.bomb_count_at(center), 24);
+        assert_eq!(tile_map.tile_at(center), Some(Tile::BombNeighbor(24)));
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use crate::components::PlayerId;
+
+pub const VERSUS_PLAYERS: usize = 2;
+
+/// How a versus game ended
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum VersusOutcome {
+    Won(PlayerId),
+    /// Every mine was found and the scores are level
+    Draw,
+}
+
+/// Turn and score keeping for the hot-seat "Flags" mode, where players
+/// take turns trying to find mines instead of avoiding them.
+#[derive(Debug, Clone, Resource, Serialize, Deserialize)]
+pub struct VersusState {
+    pub current: PlayerId,
+    pub scores: [u16; VERSUS_PLAYERS],
+    pub outcome: Option<VersusOutcome>,
+    // The safe start opening is revealed on the board's behalf and must not
+    // cost the first player their turn
+    opening_pending: bool,
+}
+
+impl VersusState {
+    pub fn new(opening_pending: bool) -> Self {
+        Self {
+            current: PlayerId(0),
+            scores: [0; VERSUS_PLAYERS],
+            outcome: None,
+            opening_pending,
+        }
+    }
+
+    /// Score needed to win: a strict majority of the mines on the board
+    pub fn winning_score(bomb_count: u16) -> u16 {
+        bomb_count / 2 + 1
+    }
+
+    pub fn score(&self, player: PlayerId) -> u16 {
+        self.scores[player.0 as usize]
+    }
+
+    /// Credits the current player with a found mine. The player keeps the
+    /// turn. Returns the outcome if this mine ended the game.
+    pub fn score_mine(&mut self, bomb_count: u16) -> Option<VersusOutcome> {
+        let player = self.current;
+        self.scores[player.0 as usize] += 1;
+
+        if self.score(player) >= Self::winning_score(bomb_count) {
+            self.outcome = Some(VersusOutcome::Won(player));
+        } else if self.scores.iter().sum::<u16>() >= bomb_count {
+            self.outcome = Some(VersusOutcome::Draw);
+        }
+        self.outcome
+    }
+
+    pub fn pass_turn(&mut self) {
+        if self.opening_pending {
+            self.opening_pending = false;
+            return;
+        }
+        self.current = PlayerId((self.current.0 + 1) % VERSUS_PLAYERS as u8);
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_opening_keeps_the_first_turn() {
+        let mut versus = VersusState::new(true);
+        versus.pass_turn();
+        assert_eq!(versus.current, PlayerId(0));
+        versus.pass_turn();
+        assert_eq!(versus.current, PlayerId(1));
+
+        let mut versus = VersusState::new(false);
+        versus.pass_turn();
+        assert_eq!(versus.current, PlayerId(1));
+    }
+
+    #[test]
+    fn a_majority_wins_and_level_scores_draw() {
+        let mut versus = VersusState::new(false);
+        assert_eq!(versus.score_mine(3), None);
+        assert_eq!(versus.score_mine(3), Some(VersusOutcome::Won(PlayerId(0))));
+
+        let mut versus = VersusState::new(false);
+        assert_eq!(versus.score_mine(2), None);
+        versus.pass_turn();
+        assert_eq!(versus.score_mine(2), Some(VersusOutcome::Draw));
+        assert!(versus.is_over());
+    }
+}
@@ -0,0 +1,196 @@
+//! Exhaustive search of the mine layouts of one frontier component: the
+//! covered cells tied together by the numbers they touch
+
+use crate::components::Coordinates;
+use crate::queue::Queue;
+use crate::solver::SolverError;
+
+/// Search steps allowed over all components of a board before giving up
+pub(super) const MAX_NODES: u64 = 20_000_000;
+
+/// The cells a number touches hold exactly `mines` mines. Cells are
+/// indices into the component.
+#[derive(Debug, Clone)]
+pub(super) struct Constraint {
+    pub cells: Vec<usize>,
+    pub mines: u8,
+}
+
+/// Layout counts of a component, by number of mines
+#[derive(Debug, Clone)]
+pub(super) struct Component {
+    pub cells: Vec<Coordinates>,
+    /// `layouts[k]`: layouts with `k` mines
+    pub layouts: Vec<f64>,
+    /// `mines[k][i]`: layouts with `k` mines in which cell `i` holds one
+    pub mines: Vec<Vec<f64>>,
+}
+
+struct Search<'a> {
+    constraints: &'a [Constraint],
+    /// Constraints each cell takes part in
+    cell_constraints: Vec<Vec<usize>>,
+    /// Cells in the order they are assigned
+    order: Vec<usize>,
+    /// Mines placed and cells still open, per constraint
+    placed: Vec<u8>,
+    open: Vec<u8>,
+    is_mine: Vec<bool>,
+    mine_count: usize,
+    max_mines: usize,
+    nodes: &'a mut u64,
+    component: Component,
+}
+
+/// Counts every layout of `cells` satisfying `constraints` with at most
+/// `max_mines` mines. `nodes` is shared by the components of a board.
+pub(super) fn enumerate(
+    cells: Vec<Coordinates>,
+    constraints: &[Constraint],
+    max_mines: usize,
+    nodes: &mut u64,
+) -> Result<Component, SolverError> {
+    let mut cell_constraints = vec![Vec::new(); cells.len()];
+    for (index, constraint) in constraints.iter().enumerate() {
+        for &cell in constraint.cells.iter() {
+            cell_constraints[cell].push(index);
+        }
+    }
+
+    // Cells are assigned in breadth first order, so constraints fill up and
+    // prune the search early
+    let mut order = Vec::with_capacity(cells.len());
+    let mut queued = vec![false; cells.len()];
+    for start in 0..cells.len() {
+        if queued[start] {
+            continue;
+        }
+        queued[start] = true;
+        let mut queue = Queue::from([start]);
+        while let Some(cell) = queue.dequeue() {
+            order.push(cell);
+            for &constraint in cell_constraints[cell].iter() {
+                for &neighbor in constraints[constraint].cells.iter() {
+                    if !queued[neighbor] {
+                        queued[neighbor] = true;
+                        queue.enqueue(neighbor);
+                    }
+                }
+            }
+        }
+    }
+
+    let max_mines = max_mines.min(cells.len());
+    let mut search = Search {
+        constraints,
+        cell_constraints,
+        order,
+        placed: vec![0; constraints.len()],
+        open: constraints
+            .iter()
+            .map(|constraint| constraint.cells.len() as u8)
+            .collect(),
+        is_mine: vec![false; cells.len()],
+        mine_count: 0,
+        max_mines,
+        nodes,
+        component: Component {
+            layouts: vec![0.; max_mines + 1],
+            mines: vec![vec![0.; cells.len()]; max_mines + 1],
+            cells,
+        },
+    };
+    search.search()?;
+    Ok(search.component)
+}
+
+impl Search<'_> {
+    /// Depth first search over the cells in `order`, trying no mine before a
+    /// mine. Kept off the call stack, components can be thousands of cells
+    /// long.
+    fn search(&mut self) -> Result<(), SolverError> {
+        // Values of the cells assigned so far, in `order`
+        let mut assigned: Vec<bool> = Vec::with_capacity(self.order.len());
+        // Least value left to try for the next cell
+        let mut from = false;
+        self.visit()?;
+        loop {
+            let depth = assigned.len();
+            if depth == self.order.len() {
+                self.count_layout();
+            } else if let Some(mine) = self.first_fit(self.order[depth], from) {
+                self.assign(self.order[depth], mine, true);
+                assigned.push(mine);
+                from = false;
+                self.visit()?;
+                continue;
+            }
+
+            // Back up to the last cell that can still take a mine
+            loop {
+                let mine = match assigned.pop() {
+                    Some(mine) => mine,
+                    None => return Ok(()),
+                };
+                self.assign(self.order[assigned.len()], mine, false);
+                if !mine {
+                    from = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn visit(&mut self) -> Result<(), SolverError> {
+        *self.nodes += 1;
+        if *self.nodes > MAX_NODES {
+            return Err(SolverError::TooComplex);
+        }
+        Ok(())
+    }
+
+    /// First value from `from` on the constraints of `cell` still allow
+    fn first_fit(&self, cell: usize, from: bool) -> Option<bool> {
+        [false, true]
+            .into_iter()
+            .filter(|&mine| mine >= from)
+            .find(|&mine| {
+                (!mine || self.mine_count < self.max_mines)
+                    && self.cell_constraints[cell].iter().all(|&index| {
+                        let placed = self.placed[index] + mine as u8;
+                        let open = self.open[index] - 1;
+                        let needed = self.constraints[index].mines;
+                        placed <= needed && placed + open >= needed
+                    })
+            })
+    }
+
+    /// Places (`set`) or takes back the assignment of a cell
+    fn assign(&mut self, cell: usize, mine: bool, set: bool) {
+        for &index in self.cell_constraints[cell].iter() {
+            if set {
+                self.open[index] -= 1;
+                self.placed[index] += mine as u8;
+            } else {
+                self.open[index] += 1;
+                self.placed[index] -= mine as u8;
+            }
+        }
+        self.is_mine[cell] = mine && set;
+        match (mine, set) {
+            (true, true) => self.mine_count += 1,
+            (true, false) => self.mine_count -= 1,
+            _ => (),
+        }
+    }
+
+    fn count_layout(&mut self) {
+        let mines = self.mine_count;
+        self.component.layouts[mines] += 1.;
+        for (cell, &is_mine) in self.is_mine.iter().enumerate() {
+            if is_mine {
+                self.component.mines[mines][cell] += 1.;
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Hints for stuck players: a safe cell with the reasoning behind it when
+//! it is simple enough to put in words, the safest guess otherwise
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::components::Coordinates;
+use crate::resources::topology::TopologyKind;
+use crate::solver::{solve, Cell, SolverError, VisibleBoard};
+
+/// Why a cell is safe, in terms the player can check on the board
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Reason {
+    /// The number at `at` already touches all of its mines
+    Satisfied { at: Coordinates, count: u8 },
+    /// The mines `first` needs among its covered cells, minus those it can
+    /// place away from `second`, are all the mines `second` needs. The
+    /// covered cells of `second` that `first` does not touch are safe.
+    Pattern {
+        first: (Coordinates, u8),
+        second: (Coordinates, u8),
+        /// Every covered cell involved lies on one edge of the board
+        edge: bool,
+    },
+}
+
+impl Reason {
+    /// The numbers the reasoning starts from
+    pub fn numbers(&self) -> Vec<Coordinates> {
+        match *self {
+            Reason::Satisfied { at, .. } => vec![at],
+            Reason::Pattern { first, second, .. } => vec![first.0, second.0],
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Satisfied { count, .. } => {
+                write!(f, "the {} next to it already touches all its mines", count)
+            }
+            Reason::Pattern {
+                first,
+                second,
+                edge,
+            } => {
+                write!(f, "{}-{} pattern", first.1, second.1)?;
+                if *edge {
+                    write!(f, " along the edge")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Hint {
+    /// No layout of mines matching the board has one at `coordinates`
+    Safe {
+        coordinates: Coordinates,
+        reason: Option<Reason>,
+    },
+    /// No cell is provably safe, this one is the least likely to hold a mine
+    Guess {
+        coordinates: Coordinates,
+        probability: f64,
+    },
+}
+
+impl Hint {
+    pub fn coordinates(&self) -> Coordinates {
+        match *self {
+            Hint::Safe { coordinates, .. } | Hint::Guess { coordinates, .. } => coordinates,
+        }
+    }
+}
+
+/// A hint for the player of `board`, `None` when no cell is left covered.
+/// Safe cells with the simplest reasoning come first.
+pub fn hint(board: &VisibleBoard) -> Result<Option<Hint>, SolverError> {
+    let solution = solve(board)?;
+    let safe = solution
+        .safe_cells()
+        .map(|coordinates| (coordinates, explain(board, coordinates)))
+        .min_by_key(|(_, reason)| match reason {
+            Some(Reason::Satisfied { .. }) => 0,
+            Some(Reason::Pattern { .. }) => 1,
+            None => 2,
+        });
+    if let Some((coordinates, reason)) = safe {
+        return Ok(Some(Hint::Safe {
+            coordinates,
+            reason,
+        }));
+    }
+    Ok(solution
+        .safest()
+        .map(|(coordinates, probability)| Hint::Guess {
+            coordinates,
+            probability,
+        }))
+}
+
+/// A number as far as the player can tell: the mines it still needs once
+/// the ones in plain sight are counted, and the covered cells left to hold
+/// them
+struct Number {
+    at: Coordinates,
+    count: u8,
+    needed: usize,
+    open: Vec<Coordinates>,
+}
+
+impl Number {
+    fn at(board: &VisibleBoard, at: Coordinates) -> Option<Self> {
+        let count = match board.cell(at) {
+            Cell::Revealed(count) => count,
+            _ => return None,
+        };
+        let mut needed = count as usize;
+        let mut open = Vec::new();
+        for neighbor in board.neighbors(at) {
+            match board.cell(neighbor) {
+                Cell::Mine => needed = needed.saturating_sub(1),
+                cell if cell.is_covered() => match is_forced_mine(board, neighbor) {
+                    true => needed = needed.saturating_sub(1),
+                    false => open.push(neighbor),
+                },
+                _ => (),
+            }
+        }
+        Some(Self {
+            at,
+            count,
+            needed,
+            open,
+        })
+    }
+}
+
+/// Whether a number next to the covered cell at `coordinates` needs all
+/// of its covered cells as mines, which a player sees at a glance. Mines
+/// only the solver can tell are left out of the explanations.
+fn is_forced_mine(board: &VisibleBoard, coordinates: Coordinates) -> bool {
+    board.neighbors(coordinates).into_iter().any(|at| {
+        let count = match board.cell(at) {
+            Cell::Revealed(count) => count as usize,
+            _ => return false,
+        };
+        let (mut mines, mut covered) = (0, 0);
+        for neighbor in board.neighbors(at) {
+            match board.cell(neighbor) {
+                Cell::Mine => mines += 1,
+                cell if cell.is_covered() => covered += 1,
+                _ => (),
+            }
+        }
+        mines + covered == count
+    })
+}
+
+/// Explains why the safe cell at `coordinates` is safe, looking at the
+/// numbers around it one or two at a time. `None` when it takes more than
+/// that, like the mine count or a long chain of numbers.
+pub fn explain(board: &VisibleBoard, coordinates: Coordinates) -> Option<Reason> {
+    let numbers: Vec<Number> = board
+        .neighbors(coordinates)
+        .into_iter()
+        .filter_map(|at| Number::at(board, at))
+        .collect();
+    if let Some(number) = numbers.iter().find(|number| number.needed == 0) {
+        return Some(Reason::Satisfied {
+            at: number.at,
+            count: number.count,
+        });
+    }
+
+    for second in numbers.iter() {
+        // Numbers sharing a covered cell with `second`
+        let mut others: Vec<Coordinates> = second
+            .open
+            .iter()
+            .flat_map(|cell| board.neighbors(*cell))
+            .filter(|at| *at != second.at)
+            .collect();
+        others.sort();
+        others.dedup();
+        for first in others.into_iter().filter_map(|at| Number::at(board, at)) {
+            if first.open.contains(&coordinates) {
+                continue;
+            }
+            // `first` puts at least this many mines among the cells of
+            // `second`
+            let outside = first
+                .open
+                .iter()
+                .filter(|cell| !second.open.contains(cell))
+                .count();
+            if first.needed.checked_sub(outside) == Some(second.needed) {
+                let cells: Vec<Coordinates> = first
+                    .open
+                    .iter()
+                    .chain(second.open.iter())
+                    .copied()
+                    .collect();
+                return Some(Reason::Pattern {
+                    first: (first.at, first.count),
+                    second: (second.at, second.count),
+                    edge: along_edge(board, &cells),
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Whether `cells` all lie on the same outer row or column
+fn along_edge(board: &VisibleBoard, cells: &[Coordinates]) -> bool {
+    // Wrapping boards have no edges and layers are side by side
+    if matches!(
+        board.topology,
+        TopologyKind::Torus | TopologyKind::Layers(_)
+    ) {
+        return false;
+    }
+    let (width, height) = board.size();
+    let edges: [&dyn Fn(&Coordinates) -> bool; 4] = [
+        &|cell| cell.x == 0,
+        &|cell| cell.x + 1 == width,
+        &|cell| cell.y == 0,
+        &|cell| cell.y + 1 == height,
+    ];
+    edges.iter().any(|edge| cells.iter().all(edge))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::{at, visible};
+
+    #[test]
+    fn numbers_touching_their_mines_are_satisfied() {
+        // The mine was hit in a lives game and is in plain sight
+        let board = visible(
+            "*..\n...\n", //
+            "**.\n...\n",
+        );
+        assert_eq!(
+            explain(&board, at(2, 1)),
+            Some(Reason::Satisfied {
+                at: at(1, 1),
+                count: 1
+            })
+        );
+
+        // The 1 in the corner has a single covered cell, its mine is as
+        // good as seen
+        let board = visible(
+            "*..\n...\n", //
+            ".*.\n**.\n",
+        );
+        assert!(matches!(
+            explain(&board, at(2, 1)),
+            Some(Reason::Satisfied { count: 1, .. })
+        ));
+        assert!(matches!(
+            hint(&board),
+            Ok(Some(Hint::Safe {
+                reason: Some(Reason::Satisfied { .. }),
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn mines_only_the_solver_knows_are_not_counted() {
+        // The solver proves both mines, but the player has to see the 1-1
+        // on the right to tell the second cell is safe
+        let board = visible(
+            "*.*.\n....\n", //
+            "....\n****\n",
+        );
+        let pattern = Reason::Pattern {
+            first: (at(3, 0), 1),
+            second: (at(2, 0), 1),
+            edge: true,
+        };
+        assert_eq!(explain(&board, at(1, 1)), Some(pattern));
+        assert_eq!(pattern.to_string(), "1-1 pattern along the edge");
+        assert_eq!(pattern.numbers(), [at(3, 0), at(2, 0)]);
+    }
+
+    #[test]
+    fn guesses_come_with_their_odds() {
+        let board = visible(
+            "*.\n..\n", //
+            "..\n**\n",
+        );
+        assert_eq!(
+            hint(&board),
+            Ok(Some(Hint::Guess {
+                coordinates: at(0, 1),
+                probability: 0.5
+            }))
+        );
+
+        // Nothing left covered
+        let board = visible(
+            "*.\n..\n", //
+            "**\n**\n",
+        );
+        assert_eq!(hint(&board), Ok(None));
+    }
+}
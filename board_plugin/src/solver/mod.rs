@@ -0,0 +1,619 @@
+//! Mine probabilities from what the player can see. Every covered cell is
+//! either proven safe, proven a mine, or gets its exact chance of holding
+//! a mine.
+//!
+//! Covered cells next to numbers form the frontier. It is split into
+//! components that share no number, and the layouts of each component are
+//! enumerated and counted by how many mines they use. The other covered
+//! cells hold the rest of the mines in any arrangement, so a combination of
+//! component layouts using `k` mines weighs as much as the ways of placing
+//! the remaining `mines - k` among them.
+
+mod enumerate;
+mod hint;
+mod visible;
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::components::Coordinates;
+
+use enumerate::{Component, Constraint, MAX_NODES};
+
+pub use hint::{explain, hint, Hint, Reason};
+pub use visible::{Cell, VisibleBoard};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolverError {
+    /// No layout of mines matches the board
+    Contradiction,
+    /// The frontier has too many layouts to enumerate
+    TooComplex,
+    Unsupported(String),
+}
+
+impl Display for SolverError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SolverError::Contradiction => write!(f, "no layout of mines matches the board"),
+            SolverError::TooComplex => {
+                write!(f, "more than {} steps to enumerate the board", MAX_NODES)
+            }
+            SolverError::Unsupported(what) => write!(f, "unsupported board: {}", what),
+        }
+    }
+}
+
+impl Error for SolverError {}
+
+/// What can be told about a covered cell
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CellState {
+    /// No layout matching the board has a mine there
+    Safe,
+    /// Every layout matching the board has a mine there
+    Mine,
+    /// Chance of a mine, strictly between 0 and 1
+    Probability(f64),
+}
+
+impl CellState {
+    pub fn mine_probability(&self) -> f64 {
+        match self {
+            CellState::Safe => 0.,
+            CellState::Mine => 1.,
+            CellState::Probability(probability) => *probability,
+        }
+    }
+}
+
+/// The state of every covered cell of a board
+#[derive(Debug, Clone, Default)]
+pub struct Solution {
+    cells: BTreeMap<Coordinates, CellState>,
+}
+
+impl Solution {
+    pub fn get(&self, coordinates: Coordinates) -> Option<CellState> {
+        self.cells.get(&coordinates).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Coordinates, CellState)> + '_ {
+        self.cells
+            .iter()
+            .map(|(coordinates, state)| (*coordinates, *state))
+    }
+
+    pub fn safe_cells(&self) -> impl Iterator<Item = Coordinates> + '_ {
+        self.iter()
+            .filter(|(_, state)| *state == CellState::Safe)
+            .map(|(coordinates, _)| coordinates)
+    }
+
+    pub fn mines(&self) -> impl Iterator<Item = Coordinates> + '_ {
+        self.iter()
+            .filter(|(_, state)| *state == CellState::Mine)
+            .map(|(coordinates, _)| coordinates)
+    }
+
+    /// Covered cell least likely to hold a mine, the first one in row order
+    /// among equals
+    pub fn safest(&self) -> Option<(Coordinates, f64)> {
+        self.iter()
+            .map(|(coordinates, state)| (coordinates, state.mine_probability()))
+            .min_by(|(a, p), (b, q)| p.total_cmp(q).then_with(|| (a.y, a.x).cmp(&(b.y, b.x))))
+    }
+}
+
+/// Works out every covered cell of `board` from its numbers and its mine
+/// count
+pub fn solve(board: &VisibleBoard) -> Result<Solution, SolverError> {
+    let mut known_mines = 0;
+    let mut covered = Vec::new();
+    // Frontier cells and the numbers on them, by index into `frontier`
+    let mut frontier: Vec<Coordinates> = Vec::new();
+    let mut frontier_index: HashMap<Coordinates, usize> = HashMap::new();
+    let mut constraints: Vec<Constraint> = Vec::new();
+
+    for (coordinates, cell) in board.cells() {
+        let count = match cell {
+            Cell::Mine => {
+                known_mines += 1;
+                continue;
+            }
+            Cell::Covered | Cell::Flagged => {
+                covered.push(coordinates);
+                continue;
+            }
+            Cell::Revealed(count) => count,
+            Cell::Hole => continue,
+        };
+        let mut cells = Vec::new();
+        let mut mines = 0;
+        for neighbor in board.neighbors(coordinates) {
+            match board.cell(neighbor) {
+                Cell::Mine => mines += 1,
+                cell if cell.is_covered() => {
+                    let index = *frontier_index.entry(neighbor).or_insert_with(|| {
+                        frontier.push(neighbor);
+                        frontier.len() - 1
+                    });
+                    cells.push(index);
+                }
+                _ => (),
+            }
+        }
+        if mines > count || (cells.is_empty() && mines != count) {
+            return Err(SolverError::Contradiction);
+        }
+        if !cells.is_empty() {
+            constraints.push(Constraint {
+                cells,
+                mines: count - mines,
+            });
+        }
+    }
+
+    let floating = covered.len() - frontier.len();
+    let mut solution = Solution::default();
+    // Cells a single number settles need no search
+    let forced = settle(frontier.len(), &mut constraints)?;
+    let mut open = Vec::new();
+    let mut local = vec![0; frontier.len()];
+    for (cell, state) in forced.iter().enumerate() {
+        match state {
+            Some(true) => {
+                known_mines += 1;
+                solution.cells.insert(frontier[cell], CellState::Mine);
+            }
+            Some(false) => {
+                solution.cells.insert(frontier[cell], CellState::Safe);
+            }
+            None => {
+                local[cell] = open.len();
+                open.push(frontier[cell]);
+            }
+        }
+    }
+    for constraint in constraints.iter_mut() {
+        constraint
+            .cells
+            .iter_mut()
+            .for_each(|cell| *cell = local[*cell]);
+    }
+
+    let remaining = (board.mines as usize)
+        .checked_sub(known_mines)
+        .ok_or(SolverError::Contradiction)?;
+    let mut nodes = 0;
+    let components = split_components(&open, constraints)
+        .into_iter()
+        .map(|(cells, constraints)| {
+            enumerate::enumerate(cells, &constraints, remaining, &mut nodes)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Whether the rest of the mines fit on the cells away from the numbers
+    // once the frontier holds `k`
+    let fits = |k: usize| {
+        remaining
+            .checked_sub(k)
+            .is_some_and(|rest| rest <= floating)
+    };
+    // Which mine totals the frontier can reach. Proofs only rely on these,
+    // the weights may round down to nothing.
+    let supports: Vec<Vec<bool>> = components
+        .iter()
+        .map(|component| {
+            component
+                .layouts
+                .iter()
+                .map(|layouts| *layouts > 0.)
+                .collect()
+        })
+        .collect();
+    let all_support = supports.iter().fold(vec![true], |total, support| {
+        convolve_support(&total, support)
+    });
+    let weights = rest_weights(remaining, floating, &all_support);
+
+    for (index, component) in components.iter().enumerate() {
+        let others = components
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .fold(vec![1.], |total, (_, other)| {
+                convolve(&total, &other.layouts)
+            });
+        let others_support = supports
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index)
+            .fold(vec![true], |total, (_, support)| {
+                convolve_support(&total, support)
+            });
+        let odds = Odds {
+            others: &others,
+            others_support: &others_support,
+            weights: &weights,
+            fits: &fits,
+        };
+        solve_component(component, &odds, &mut solution)?;
+    }
+
+    // Cells away from the numbers all share the same odds
+    let all = components.iter().fold(vec![1.], |total, component| {
+        convolve(&total, &component.layouts)
+    });
+    let (mut total, mut mines) = (0., 0.);
+    let (mut mine_possible, mut safe_possible) = (false, false);
+    for (k, layouts) in all.iter().enumerate() {
+        if !all_support[k] || !fits(k) {
+            continue;
+        }
+        let rest = remaining - k;
+        mine_possible |= rest > 0;
+        safe_possible |= rest < floating;
+        let weight = layouts * weights[k];
+        total += weight;
+        if floating > 0 {
+            mines += weight * rest as f64 / floating as f64;
+        }
+    }
+    if !all_support
+        .iter()
+        .enumerate()
+        .any(|(k, reachable)| *reachable && fits(k))
+    {
+        return Err(SolverError::Contradiction);
+    }
+    let state = cell_state(mine_possible, safe_possible, mines / total);
+    for coordinates in covered {
+        solution.cells.entry(coordinates).or_insert(state);
+    }
+    Ok(solution)
+}
+
+/// Settles the cells of numbers that need all or none of their open cells
+/// to be mines, until no number does. Settled cells are taken out of the
+/// constraints, which are left with the open cells only.
+fn settle(
+    cells: usize,
+    constraints: &mut Vec<Constraint>,
+) -> Result<Vec<Option<bool>>, SolverError> {
+    let mut forced: Vec<Option<bool>> = vec![None; cells];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for constraint in constraints.iter_mut() {
+            let mines = constraint
+                .cells
+                .iter()
+                .filter(|cell| forced[**cell] == Some(true))
+                .count() as u8;
+            constraint.cells.retain(|cell| forced[*cell].is_none());
+            constraint.mines = constraint
+                .mines
+                .checked_sub(mines)
+                .filter(|needed| (*needed as usize) <= constraint.cells.len())
+                .ok_or(SolverError::Contradiction)?;
+            let mine = match constraint.mines {
+                0 => false,
+                needed if needed as usize == constraint.cells.len() => true,
+                _ => continue,
+            };
+            for &cell in constraint.cells.iter() {
+                forced[cell] = Some(mine);
+            }
+            changed |= !constraint.cells.is_empty();
+            constraint.cells.clear();
+            constraint.mines = 0;
+        }
+    }
+    constraints.retain(|constraint| !constraint.cells.is_empty());
+    Ok(forced)
+}
+
+/// What the rest of the board says about the mine count of a component
+struct Odds<'a> {
+    /// Layouts of the other components by mine count
+    others: &'a [f64],
+    /// Mine counts the other components can reach
+    others_support: &'a [bool],
+    /// Weight of every frontier mine total, see `rest_weights`
+    weights: &'a [f64],
+    fits: &'a dyn Fn(usize) -> bool,
+}
+
+/// Groups frontier cells that share a number, with the numbers on them
+fn split_components(
+    frontier: &[Coordinates],
+    constraints: Vec<Constraint>,
+) -> Vec<(Vec<Coordinates>, Vec<Constraint>)> {
+    let mut parents: Vec<usize> = (0..frontier.len()).collect();
+    fn root(parents: &mut [usize], mut cell: usize) -> usize {
+        while parents[cell] != cell {
+            parents[cell] = parents[parents[cell]];
+            cell = parents[cell];
+        }
+        cell
+    }
+    for constraint in constraints.iter() {
+        let first = root(&mut parents, constraint.cells[0]);
+        for &cell in constraint.cells[1..].iter() {
+            let other = root(&mut parents, cell);
+            parents[other] = first;
+        }
+    }
+
+    // Components in order of their first cell, cells renumbered within
+    let mut components: Vec<(Vec<Coordinates>, Vec<Constraint>)> = Vec::new();
+    let mut component_of_root = HashMap::new();
+    let mut local = vec![0; frontier.len()];
+    for cell in 0..frontier.len() {
+        let root = root(&mut parents, cell);
+        let component = *component_of_root.entry(root).or_insert_with(|| {
+            components.push((Vec::new(), Vec::new()));
+            components.len() - 1
+        });
+        local[cell] = components[component].0.len();
+        components[component].0.push(frontier[cell]);
+    }
+    for constraint in constraints {
+        let component = component_of_root[&root(&mut parents, constraint.cells[0])];
+        components[component].1.push(Constraint {
+            cells: constraint.cells.iter().map(|&cell| local[cell]).collect(),
+            mines: constraint.mines,
+        });
+    }
+    components
+}
+
+/// `weights[k]`: ways to place `remaining - k` mines on `floating` cells,
+/// scaled so the largest reachable one is 1
+fn rest_weights(remaining: usize, floating: usize, reachable: &[bool]) -> Vec<f64> {
+    // ln(n!) for n up to `floating`
+    let mut ln_factorials = Vec::with_capacity(floating + 1);
+    ln_factorials.push(0.);
+    for n in 1..=floating {
+        ln_factorials.push(ln_factorials[n - 1] + (n as f64).ln());
+    }
+    let ln_weights: Vec<Option<f64>> = (0..reachable.len())
+        .map(|k| {
+            let rest = reachable[k].then_some(remaining)?;
+            let rest = rest.checked_sub(k).filter(|rest| *rest <= floating)?;
+            Some(ln_factorials[floating] - ln_factorials[rest] - ln_factorials[floating - rest])
+        })
+        .collect();
+    let max = ln_weights
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    ln_weights
+        .into_iter()
+        .map(|ln_weight| ln_weight.map_or(0., |ln_weight| (ln_weight - max).exp()))
+        .collect()
+}
+
+/// Mine counts two independent groups can reach together
+fn convolve_support(a: &[bool], b: &[bool]) -> Vec<bool> {
+    let mut total = vec![false; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            total[i + j] |= x & y;
+        }
+    }
+    total
+}
+
+/// Layouts of two independent groups by total mines, scaled so the
+/// largest is 1 since only ratios matter
+fn convolve(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut total = vec![0.; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            total[i + j] += x * y;
+        }
+    }
+    let max = total.iter().copied().fold(0., f64::max);
+    if max > 0. {
+        total.iter_mut().for_each(|value| *value /= max);
+    }
+    total
+}
+
+/// States of the cells of a component
+fn solve_component(
+    component: &Component,
+    odds: &Odds,
+    solution: &mut Solution,
+) -> Result<(), SolverError> {
+    let cells = component.cells.len();
+    let (mut total, mut mines) = (0., vec![0.; cells]);
+    let mut mine_possible = vec![false; cells];
+    let mut safe_possible = vec![false; cells];
+    let mut any_possible = false;
+    for (k, layouts) in component.layouts.iter().enumerate() {
+        if *layouts == 0. {
+            continue;
+        }
+        let possible = odds
+            .others_support
+            .iter()
+            .enumerate()
+            .any(|(j, reachable)| *reachable && (odds.fits)(k + j));
+        if !possible {
+            continue;
+        }
+        any_possible = true;
+        let weight: f64 = odds
+            .others
+            .iter()
+            .enumerate()
+            .map(|(j, other)| other * odds.weights.get(k + j).copied().unwrap_or(0.))
+            .sum();
+        total += layouts * weight;
+        for cell in 0..cells {
+            let with_mine = component.mines[k][cell];
+            mines[cell] += with_mine * weight;
+            mine_possible[cell] |= with_mine > 0.;
+            safe_possible[cell] |= with_mine < *layouts;
+        }
+    }
+    if !any_possible {
+        return Err(SolverError::Contradiction);
+    }
+    for (cell, coordinates) in component.cells.iter().enumerate() {
+        let state = cell_state(
+            mine_possible[cell],
+            safe_possible[cell],
+            mines[cell] / total,
+        );
+        solution.cells.insert(*coordinates, state);
+    }
+    Ok(())
+}
+
+fn cell_state(mine_possible: bool, safe_possible: bool, probability: f64) -> CellState {
+    match (mine_possible, safe_possible) {
+        (false, _) => CellState::Safe,
+        (true, false) => CellState::Mine,
+        // Layouts too unlikely to weigh anything still keep it open
+        (true, true) => {
+            CellState::Probability(probability.clamp(f64::MIN_POSITIVE, 1. - f64::EPSILON))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::tile::Tile;
+    use crate::resources::tile_map::TileMap;
+    use crate::resources::topology::TopologyKind;
+
+    /// Board with the mines of the `grid` text grid, where the cells marked
+    /// with `*` in `shown` are uncovered
+    pub(super) fn visible(grid: &str, shown: &str) -> VisibleBoard {
+        let tile_map: TileMap = grid.parse().unwrap();
+        let shown: TileMap = shown.parse().unwrap();
+        uncover(&tile_map, shown.mines())
+    }
+
+    pub(super) fn uncover(tile_map: &TileMap, shown: Vec<Coordinates>) -> VisibleBoard {
+        let mut board = VisibleBoard::new(tile_map.topology, tile_map.size(), tile_map.bomb_count);
+        for coordinates in shown {
+            let cell = match tile_map.tile_at(coordinates) {
+                Some(Tile::Bomb(_)) => Cell::Mine,
+                Some(Tile::BombNeighbor(count)) => Cell::Revealed(count),
+                _ => Cell::Revealed(0),
+            };
+            board.set(coordinates, cell);
+        }
+        board
+    }
+
+    pub(super) fn at(x: u16, y: u16) -> Coordinates {
+        Coordinates { x, y }
+    }
+
+    #[test]
+    fn one_two_pattern() {
+        // The 1 puts a mine on its two cells, the 2 needs one more past it
+        let board = visible(
+            "*.*.\n....\n....\n", //
+            "....\n**..\n****\n",
+        );
+        let solution = solve(&board).unwrap();
+        assert_eq!(solution.get(at(2, 2)), Some(CellState::Mine));
+        assert_eq!(solution.get(at(0, 2)), Some(CellState::Probability(0.5)));
+        assert_eq!(solution.get(at(1, 2)), Some(CellState::Probability(0.5)));
+        // Both mines are accounted for
+        assert_eq!(solution.get(at(3, 2)), Some(CellState::Safe));
+        assert_eq!(solution.get(at(3, 1)), Some(CellState::Safe));
+    }
+
+    #[test]
+    fn fully_determined_board() {
+        let board = visible(
+            "*.*\n...\n", //
+            "...\n***\n",
+        );
+        let solution = solve(&board).unwrap();
+        assert_eq!(solution.mines().collect::<Vec<_>>(), [at(0, 1), at(2, 1)]);
+        assert_eq!(solution.safe_cells().collect::<Vec<_>>(), [at(1, 1)]);
+        assert_eq!(solution.safest(), Some((at(1, 1), 0.)));
+    }
+
+    #[test]
+    fn probabilities_add_up_to_the_mines_left() {
+        let board = visible(
+            "*.....*.\n..*.....\n........\n.*...**.\n......*.\n*.......\n",
+            // A mine already uncovered, as with several lives
+            "*.......\n........\n..***...\n..***...\n........\n.*****..\n",
+        );
+        let solution = solve(&board).unwrap();
+        let known = board
+            .cells()
+            .filter(|(_, cell)| *cell == Cell::Mine)
+            .count();
+        assert_eq!(known, 1);
+        let expected = board.mines as f64 - known as f64;
+        let total: f64 = solution
+            .iter()
+            .map(|(_, state)| state.mine_probability())
+            .sum();
+        assert!((total - expected).abs() < 1e-9, "{} != {}", total, expected);
+        assert!(solution
+            .iter()
+            .any(|(_, state)| matches!(state, CellState::Probability(_))));
+    }
+
+    #[test]
+    fn contradictions_are_reported() {
+        let mut board = VisibleBoard::new(TopologyKind::Square8, (2, 1), 1);
+        board.set(at(0, 0), Cell::Revealed(2));
+        assert_eq!(solve(&board).unwrap_err(), SolverError::Contradiction);
+    }
+
+    #[test]
+    fn huge_frontiers_are_too_complex() {
+        // Every other cell of every other row uncovered: the numbers overlap
+        // in a single frontier spanning the board, and each one sees 8
+        // covered cells
+        let (width, height) = (30, 16);
+        let cells = (0..height).flat_map(|y| (0..width).map(move |x| at(x, y)));
+        let shown = |cell: &Coordinates| cell.x % 2 == 0 && cell.y % 2 == 0;
+        let mines: Vec<Coordinates> = cells
+            .clone()
+            .filter(|cell| !shown(cell) && (cell.x * 7 + cell.y * 3) % 5 == 0)
+            .collect();
+        let tile_map = TileMap::from_mines(width, height, &mines);
+        let shown = cells.filter(shown).collect();
+        let board = uncover(&tile_map, shown);
+        assert_eq!(solve(&board).unwrap_err(), SolverError::TooComplex);
+    }
+
+    #[test]
+    fn long_chains_are_solved() {
+        // A row of 1s over a covered row with a mine in every third cell:
+        // a single component thousands of cells deep
+        let width = 3000;
+        let mines: Vec<Coordinates> = (0..width)
+            .filter(|x| x % 3 == 1)
+            .map(|x| at(x, 0))
+            .collect();
+        let tile_map = TileMap::from_mines(width, 2, &mines);
+        let board = uncover(&tile_map, (0..width).map(|x| at(x, 1)).collect());
+        let solution = solve(&board).unwrap();
+        for x in 0..width {
+            let expected = match x % 3 {
+                1 => CellState::Mine,
+                _ => CellState::Safe,
+            };
+            assert_eq!(solution.get(at(x, 0)), Some(expected));
+        }
+    }
+}
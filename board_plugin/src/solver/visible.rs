@@ -0,0 +1,131 @@
+use crate::components::Coordinates;
+use crate::resources::board::Board;
+use crate::resources::tile::Tile;
+use crate::resources::topology::TopologyKind;
+use crate::solver::SolverError;
+
+/// A cell as the player sees it
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Cell {
+    Covered,
+    /// Covered with a flag on it. Flags are the player's guesses, the solver
+    /// works them out again.
+    Flagged,
+    /// Uncovered, with the number of mines around it
+    Revealed(u8),
+    /// Uncovered mine, in games with several lives or after the game is lost
+    Mine,
+    /// Left out of the board by its mask
+    Hole,
+}
+
+impl Cell {
+    pub const fn is_covered(&self) -> bool {
+        matches!(self, Self::Covered | Self::Flagged)
+    }
+}
+
+/// What the player knows about a board: its shape, its mine count and the
+/// uncovered cells. Nothing of the hidden layout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibleBoard {
+    pub topology: TopologyKind,
+    pub width: u16,
+    pub height: u16,
+    /// Mines on the whole board
+    pub mines: u16,
+    cells: Vec<Cell>,
+}
+
+impl VisibleBoard {
+    /// Board of `size` with every cell covered
+    pub fn new(topology: TopologyKind, (width, height): (u16, u16), mines: u16) -> Self {
+        Self {
+            topology,
+            width,
+            height,
+            mines,
+            cells: vec![Cell::Covered; width as usize * height as usize],
+        }
+    }
+
+    /// The board as its player sees it. Only uncovered tiles are read from
+    /// the tile map, boards whose cells hold several mines are not
+    /// supported.
+    pub fn from_board(board: &Board) -> Result<Self, SolverError> {
+        let tile_map = &board.tile_map;
+        if tile_map.cell_mines > 1 {
+            return Err(SolverError::Unsupported(
+                "cells holding several mines".to_string(),
+            ));
+        }
+        let mut visible = Self::new(tile_map.topology, tile_map.size(), tile_map.bomb_count);
+        for index in 0..visible.cells.len() {
+            let coordinates = visible.coordinates_of(index);
+            // The shape of the board is in plain sight
+            if !tile_map.contains(coordinates) {
+                visible.cells[index] = Cell::Hole;
+            }
+        }
+        for (coordinates, _) in board.coordinates_marked.iter() {
+            visible.set(coordinates, Cell::Flagged);
+        }
+        for coordinates in board.coordinates_discovered.iter() {
+            let cell = match tile_map.tile_at(coordinates) {
+                Some(Tile::Bomb(_)) => Cell::Mine,
+                Some(Tile::BombNeighbor(count)) => Cell::Revealed(count),
+                Some(Tile::Empty) => Cell::Revealed(0),
+                None => continue,
+            };
+            visible.set(coordinates, cell);
+        }
+        Ok(visible)
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    fn index(&self, coordinates: Coordinates) -> Option<usize> {
+        (coordinates.x < self.width && coordinates.y < self.height)
+            .then(|| coordinates.y as usize * self.width as usize + coordinates.x as usize)
+    }
+
+    fn coordinates_of(&self, index: usize) -> Coordinates {
+        Coordinates {
+            x: (index % self.width as usize) as u16,
+            y: (index / self.width as usize) as u16,
+        }
+    }
+
+    /// The cell at `coordinates`, cells outside of the grid are holes
+    pub fn cell(&self, coordinates: Coordinates) -> Cell {
+        self.index(coordinates)
+            .map_or(Cell::Hole, |index| self.cells[index])
+    }
+
+    pub fn set(&mut self, coordinates: Coordinates, cell: Cell) {
+        if let Some(index) = self.index(coordinates) {
+            self.cells[index] = cell;
+        }
+    }
+
+    /// Every cell of the board with its coordinates, holes left out
+    pub fn cells(&self) -> impl Iterator<Item = (Coordinates, Cell)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell != Cell::Hole)
+            .map(|(index, cell)| (self.coordinates_of(index), *cell))
+    }
+
+    /// Cells a number at `coordinates` counts, holes left out
+    pub fn neighbors(&self, coordinates: Coordinates) -> Vec<Coordinates> {
+        let mut neighbors = Vec::new();
+        self.topology
+            .topology()
+            .neighbors(coordinates, self.size(), &mut neighbors);
+        neighbors.retain(|neighbor| self.cell(*neighbor) != Cell::Hole);
+        neighbors
+    }
+}
@@ -0,0 +1,56 @@
+//! Where the game keeps its files between runs
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bevy::log;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const APP_DIRECTORY: &str = "rustysweeper";
+
+/// Per-user data directory: `$XDG_DATA_HOME` or `~/.local/share` on Linux,
+/// `%APPDATA%` on Windows and `~/Library/Application Support` on macOS.
+/// Falls back to the working directory when none of them is available.
+pub fn data_dir() -> PathBuf {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    base.unwrap_or_default().join(APP_DIRECTORY)
+}
+
+pub fn data_file(name: &str) -> PathBuf {
+    data_dir().join(name)
+}
+
+/// Reads a JSON file, the default value stands in for a missing or
+/// unreadable one
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> T {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return T::default(),
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", path.display(), e);
+            return T::default();
+        }
+    };
+    serde_json::from_str(&text).unwrap_or_else(|e| {
+        log::warn!("Ignoring invalid {}: {}", path.display(), e);
+        T::default()
+    })
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(value)?)
+}
@@ -0,0 +1,67 @@
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::events::{BoardCompletedEvent, BombExplosionEvent};
+use crate::resources::board::Board;
+use crate::resources::daily::{date_string, DailyChallenge, DailyHistory, DailyResult};
+use crate::resources::hints::HintCounter;
+
+/// Saves the scored daily attempt as lost as soon as its board is dealt, a
+/// game left unfinished stays lost. Boards dealt again turn into practice.
+pub fn begin_daily_attempt(
+    board: Res<Board>,
+    mut challenge: ResMut<DailyChallenge>,
+    mut history: ResMut<DailyHistory>,
+) {
+    if !challenge.scored || !challenge.is_daily_board(&board.tile_map) {
+        return;
+    }
+    challenge.scored = history.begin(challenge.day);
+    if !challenge.scored {
+        return;
+    }
+    log::info!("Daily {} attempt started", date_string(challenge.day));
+    if let Err(e) = history.save() {
+        log::error!("Failed to save the daily history: {}", e);
+    }
+}
+
+/// Stores the outcome of the scored daily attempt, practice games are not
+/// recorded
+pub fn record_daily_result(
+    time: Res<Time>,
+    board: Res<Board>,
+    challenge: Res<DailyChallenge>,
+    hints: Option<Res<HintCounter>>,
+    mut history: ResMut<DailyHistory>,
+    mut bomb_explosion_event_reader: EventReader<BombExplosionEvent>,
+    mut board_completed_event_reader: EventReader<BoardCompletedEvent>,
+) {
+    let exploded = bomb_explosion_event_reader.iter().count() > 0;
+    let completed = board_completed_event_reader.iter().count() > 0;
+    if !exploded && !completed {
+        return;
+    }
+    if !challenge.scored || !challenge.is_daily_board(&board.tile_map) {
+        return;
+    }
+
+    let result = DailyResult {
+        day: challenge.day,
+        won: completed && !exploded,
+        time: board.elapsed(&time).as_millis() as u32,
+        hints: hints.map_or(0, |hints| hints.used),
+    };
+    history.finish(result);
+    log::info!(
+        "Daily {} {} in {:.1}s with {} hints, streak {}",
+        date_string(result.day),
+        if result.won { "won" } else { "lost" },
+        result.time as f32 / 1000.,
+        result.hints,
+        history.streak
+    );
+    if let Err(e) = history.save() {
+        log::error!("Failed to save the daily history: {}", e);
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::components::{EndlessChunk, EndlessHud, TileCover};
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::endless::{
+    ChunkCoordinates, EndlessRecord, EndlessWorld, WorldCoordinates, CHUNK_SIZE, DEFAULT_DENSITY,
+};
+use crate::resources::tile::Tile;
+use crate::resources::topology::TopologyKind;
+use crate::resources::{BoardOptions, TileSize};
+use crate::spawn_tile_face;
+use crate::systems::mark::spawn_flag;
+
+/// Pixels per second the camera moves while an arrow key is held
+const PAN_SPEED: f32 = 600.;
+/// Chunks kept spawned beyond each edge of the window
+const CHUNK_MARGIN: i32 = 1;
+
+/// Spawned chunks of the endless world and how big their tiles are drawn
+#[derive(Debug, Default, Resource)]
+pub struct EndlessView {
+    pub tile_size: f32,
+    pub padding: f32,
+    pub chunks: HashMap<ChunkCoordinates, Entity>,
+}
+
+impl EndlessView {
+    fn chunk_extent(&self) -> f32 {
+        CHUNK_SIZE as f32 * self.tile_size
+    }
+
+    /// Tile under a point of the world, in pixels
+    fn tile_at(&self, position: Vec2) -> WorldCoordinates {
+        let tile = (position / self.tile_size).floor();
+        WorldCoordinates::new(tile.x as i32, tile.y as i32)
+    }
+
+    /// Chunks overlapping the window seen by a camera at `center`, with a
+    /// margin so panning does not show them pop in
+    fn chunks_in_view(&self, center: Vec2, window_size: Vec2) -> Vec<ChunkCoordinates> {
+        let extent = self.chunk_extent();
+        let min = ((center - window_size / 2.) / extent).floor();
+        let max = ((center + window_size / 2.) / extent).floor();
+        let (min_x, min_y) = (min.x as i32 - CHUNK_MARGIN, min.y as i32 - CHUNK_MARGIN);
+        let (max_x, max_y) = (max.x as i32 + CHUNK_MARGIN, max.y as i32 + CHUNK_MARGIN);
+        (min_y..=max_y)
+            .flat_map(|y| (min_x..=max_x).map(move |x| ChunkCoordinates { x, y }))
+            .collect()
+    }
+}
+
+/// Starts an endless game on the opening at the origin
+pub fn create_world(
+    mut commands: Commands,
+    board_options: Option<Res<BoardOptions>>,
+    board_assets: Res<BoardAssets>,
+) {
+    let options = board_options
+        .map(|options| options.clone())
+        .unwrap_or_default();
+    let seed = options.seed.unwrap_or_else(rand::random);
+    log::info!("Endless world seed: {}", seed);
+
+    let mut world = EndlessWorld::new(seed, DEFAULT_DENSITY);
+    world.reveal(WorldCoordinates::ORIGIN);
+    commands.insert_resource(world);
+    commands.insert_resource(EndlessView {
+        tile_size: match options.tile_size {
+            TileSize::Fixed(v) => v,
+            TileSize::Adaptive { max, .. } => max,
+        },
+        padding: options.tile_padding,
+        chunks: HashMap::new(),
+    });
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.),
+                    ..default()
+                },
+                size: Size::width(Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("Endless score"))
+        .insert(EndlessHud)
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: board_assets.font.clone(),
+                        font_size: 18.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center)
+                .with_background_color(Color::rgba(0., 0., 0., 0.75)),
+            );
+        });
+}
+
+/// Arrow keys move the camera over the world
+pub fn pan_camera(
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    let mut direction = Vec2::ZERO;
+    for (key, step) in [
+        (KeyCode::Left, Vec2::NEG_X),
+        (KeyCode::Right, Vec2::X),
+        (KeyCode::Down, Vec2::NEG_Y),
+        (KeyCode::Up, Vec2::Y),
+    ] {
+        if keys.pressed(key) {
+            direction += step;
+        }
+    }
+    if direction == Vec2::ZERO {
+        return;
+    }
+    let offset = direction.normalize() * PAN_SPEED * time.delta_seconds();
+    for mut transform in cameras.iter_mut() {
+        transform.translation += offset.extend(0.);
+    }
+}
+
+pub fn handle_input(
+    windows: Query<&Window>,
+    cameras: Query<&Transform, With<Camera2d>>,
+    view: Res<EndlessView>,
+    mut world: ResMut<EndlessWorld>,
+    record: Option<ResMut<EndlessRecord>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+) {
+    if world.is_over() {
+        return;
+    }
+    let (window, camera) = match (windows.get_single(), cameras.get_single()) {
+        (Ok(window), Ok(camera)) => (window, camera),
+        _ => return,
+    };
+    let cursor_position = match window.cursor_position() {
+        Some(pos) => pos,
+        None => return,
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    let position = cursor_position - window_size / 2. + camera.translation.truncate();
+    let coordinates = view.tile_at(position);
+
+    if mouse_button_input.just_released(MouseButton::Left) {
+        world.reveal(coordinates);
+    }
+    if mouse_button_input.just_released(MouseButton::Right) {
+        world.toggle_flag(coordinates);
+    }
+    if mouse_button_input.just_released(MouseButton::Middle) {
+        world.chord(coordinates);
+    }
+
+    if world.is_over() {
+        let score = world.score();
+        log::info!("Endless game over, {} tiles revealed", score);
+        if let Some(mut record) = record {
+            if record.record(score) {
+                log::info!("New best endless score: {}", score);
+            }
+            if let Err(e) = record.save() {
+                log::error!("Failed to save endless record: {}", e);
+            }
+        }
+    }
+}
+
+/// Spawns the chunks coming into view, respawns the ones that changed and
+/// despawns the ones left behind
+pub fn stream_chunks(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    cameras: Query<&Transform, With<Camera2d>>,
+    mut view: ResMut<EndlessView>,
+    mut world: ResMut<EndlessWorld>,
+    board_assets: Res<BoardAssets>,
+) {
+    let (window, camera) = match (windows.get_single(), cameras.get_single()) {
+        (Ok(window), Ok(camera)) => (window, camera),
+        _ => return,
+    };
+    let window_size = Vec2::new(window.width(), window.height());
+    let in_view = view.chunks_in_view(camera.translation.truncate(), window_size);
+
+    let changed = world.take_changed_chunks();
+    let stale: Vec<_> = view
+        .chunks
+        .keys()
+        .filter(|chunk| changed.contains(chunk) || !in_view.contains(chunk))
+        .copied()
+        .collect();
+    for chunk in stale {
+        if let Some(entity) = view.chunks.remove(&chunk) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for chunk in in_view {
+        if !view.chunks.contains_key(&chunk) {
+            let entity = spawn_chunk(&mut commands, chunk, &view, &mut world, &board_assets);
+            view.chunks.insert(chunk, entity);
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    chunk: ChunkCoordinates,
+    view: &EndlessView,
+    world: &mut EndlessWorld,
+    board_assets: &BoardAssets,
+) -> Entity {
+    let (size, padding) = (view.tile_size, view.padding);
+    let extent = view.chunk_extent();
+    let origin = chunk.origin();
+    let (tile_material, cover_material) = board_assets.tile_materials(TopologyKind::Square8);
+
+    let mut flags = Vec::new();
+    let entity = commands
+        .spawn(SpatialBundle {
+            transform: Transform::from_xyz(origin.x as f32 * size, origin.y as f32 * size, 0.),
+            ..default()
+        })
+        .insert(Name::new(format!("Chunk ({}, {})", chunk.x, chunk.y)))
+        .insert(EndlessChunk(chunk))
+        .with_children(|parent| {
+            parent.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: board_assets.board_material.color,
+                    custom_size: Some(Vec2::splat(extent)),
+                    ..default()
+                },
+                texture: board_assets.board_material.texture.clone(),
+                transform: Transform::from_xyz(extent / 2., extent / 2., 0.),
+                ..default()
+            });
+
+            for coordinates in chunk.tiles() {
+                let center = Vec2::new(
+                    (coordinates.x - origin.x) as f32 * size + size / 2.,
+                    (coordinates.y - origin.y) as f32 * size + size / 2.,
+                );
+                let exploded = world.exploded == Some(coordinates);
+                let covered = !world.is_revealed(&coordinates) && !exploded;
+                let material = if covered {
+                    cover_material
+                } else {
+                    tile_material
+                };
+                let mut cmd = parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: if exploded { Color::RED } else { material.color },
+                        custom_size: Some(Vec2::splat(size - padding)),
+                        ..default()
+                    },
+                    texture: material.texture.clone(),
+                    transform: Transform::from_translation(center.extend(1.)),
+                    ..default()
+                });
+                if covered {
+                    cmd.insert(TileCover);
+                    if world.is_flagged(&coordinates) {
+                        flags.push(cmd.id());
+                    }
+                } else {
+                    let tile = if exploded {
+                        Tile::Bomb(1)
+                    } else {
+                        world.tile_at(coordinates)
+                    };
+                    spawn_tile_face(&mut cmd, tile, size, padding, board_assets);
+                }
+            }
+        })
+        .id();
+
+    for tile in flags {
+        let color = board_assets.flag_material.color;
+        spawn_flag(commands, tile, board_assets, size, color, 1);
+    }
+    entity
+}
+
+pub fn update_hud(
+    world: Res<EndlessWorld>,
+    record: Option<Res<EndlessRecord>>,
+    huds: Query<&Children, With<EndlessHud>>,
+    mut texts: Query<&mut Text>,
+) {
+    let mut line = format!("Score {}", world.score());
+    if let Some(record) = record {
+        line += &format!(" (best {})", record.best);
+    }
+    if world.is_over() {
+        line = format!("Boom! {}\nPress C to leave the board", line);
+    } else {
+        line += "\nArrow keys move around";
+    }
+    let mut texts = texts.iter_many_mut(huds.iter().flatten());
+    while let Some(mut text) = texts.fetch_next() {
+        if text.sections[0].value != line {
+            text.sections[0].value = line.clone();
+        }
+    }
+}
+
+pub fn cleanup_world(
+    mut commands: Commands,
+    chunks: Query<Entity, With<EndlessChunk>>,
+    huds: Query<Entity, With<EndlessHud>>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    for entity in chunks.iter().chain(huds.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+    // Regular boards are centered on the camera
+    for mut transform in cameras.iter_mut() {
+        transform.translation = Vec3::new(0., 0., transform.translation.z);
+    }
+    commands.remove_resource::<EndlessWorld>();
+    commands.remove_resource::<EndlessView>();
+}
@@ -0,0 +1,235 @@
+use bevy::log;
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+use futures_lite::future;
+
+use crate::components::{Coordinates, HintDisplay, HintHighlight};
+use crate::replay::{ActionKind, ReplayRecorder};
+use crate::resources::board::Board;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::hints::{HintCounter, HintSearch};
+use crate::solver::{self, Hint, VisibleBoard};
+
+const SAFE_COLOR: Color = Color::rgba(0.2, 0.9, 0.3, 0.6);
+const GUESS_COLOR: Color = Color::rgba(1., 0.8, 0.1, 0.6);
+/// Numbers the explanation of a hint starts from
+const NUMBER_COLOR: Color = Color::rgba(0.3, 0.6, 1., 0.35);
+
+/// H starts looking for a tile that is safe from what the player can see,
+/// or for the safest guess when there is none. The search runs on the async
+/// compute pool, `show_found_hint` picks up its result.
+pub fn give_hint(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    board: Res<Board>,
+    board_assets: Res<BoardAssets>,
+    search: Option<Res<HintSearch>>,
+    highlights: Query<Entity, With<HintHighlight>>,
+    mut displays: Query<&mut Text, With<HintDisplay>>,
+) {
+    if !keys.just_pressed(KeyCode::H) || search.is_some() {
+        return;
+    }
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    match VisibleBoard::from_board(&board) {
+        Ok(visible) => {
+            commands.insert_resource(start_search(visible));
+            show_hint(
+                &mut commands,
+                &mut displays,
+                &board_assets,
+                "Hint: thinking...".to_string(),
+            );
+        }
+        Err(e) => {
+            log::warn!("No hint: {}", e);
+            show_hint(
+                &mut commands,
+                &mut displays,
+                &board_assets,
+                format!("No hint: {}", e),
+            );
+        }
+    }
+}
+
+fn start_search(visible: VisibleBoard) -> HintSearch {
+    let board = visible.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { solver::hint(&visible) });
+    HintSearch { board, task }
+}
+
+/// Shows the hint once the search is done. Every hint counts against the
+/// game and goes into its replay. A board that changed in the meantime is
+/// searched again.
+#[allow(clippy::too_many_arguments)]
+pub fn show_found_hint(
+    mut commands: Commands,
+    board: Res<Board>,
+    board_assets: Res<BoardAssets>,
+    mut search: ResMut<HintSearch>,
+    mut hints: ResMut<HintCounter>,
+    recorder: Option<ResMut<ReplayRecorder>>,
+    mut displays: Query<&mut Text, With<HintDisplay>>,
+    display_entities: Query<Entity, With<HintDisplay>>,
+) {
+    let result = match future::block_on(future::poll_once(&mut search.task)) {
+        Some(result) => result,
+        None => return,
+    };
+    match VisibleBoard::from_board(&board) {
+        Ok(visible) if visible != search.board => {
+            *search = start_search(visible);
+            return;
+        }
+        _ => commands.remove_resource::<HintSearch>(),
+    }
+
+    let hint = match result {
+        Ok(Some(hint)) => hint,
+        Ok(None) => {
+            for entity in display_entities.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+        Err(e) => {
+            log::warn!("No hint: {}", e);
+            show_hint(
+                &mut commands,
+                &mut displays,
+                &board_assets,
+                format!("No hint: {}", e),
+            );
+            return;
+        }
+    };
+    hints.used += 1;
+    hints.target = Some(hint.coordinates());
+    if let Some(mut recorder) = recorder {
+        recorder.record(ActionKind::Hint, hint.coordinates(), None);
+    }
+    log::info!("Hint {}: {:?}", hints.used, hint);
+
+    let line = match hint {
+        Hint::Safe {
+            reason: Some(reason),
+            ..
+        } => {
+            for number in reason.numbers() {
+                highlight(&mut commands, &board, &board_assets, number, NUMBER_COLOR);
+            }
+            format!("Hint: the green tile is safe, {}", reason)
+        }
+        Hint::Safe { reason: None, .. } => {
+            "Hint: the green tile is safe, no layout of mines fits one there".to_string()
+        }
+        Hint::Guess { probability, .. } => format!(
+            "Hint: no tile is surely safe, the yellow one has a {:.0}% chance of a mine",
+            probability * 100.
+        ),
+    };
+    let color = match hint {
+        Hint::Safe { .. } => SAFE_COLOR,
+        Hint::Guess { .. } => GUESS_COLOR,
+    };
+    highlight(
+        &mut commands,
+        &board,
+        &board_assets,
+        hint.coordinates(),
+        color,
+    );
+    show_hint(&mut commands, &mut displays, &board_assets, line);
+}
+
+/// Takes the hint away once its tile is uncovered or flagged
+pub fn clear_hint(
+    mut commands: Commands,
+    board: Res<Board>,
+    mut hints: ResMut<HintCounter>,
+    highlights: Query<Entity, With<HintHighlight>>,
+    displays: Query<Entity, With<HintDisplay>>,
+) {
+    let target = match hints.target {
+        Some(target) => target,
+        None => return,
+    };
+    if !board.coordinates_discovered.contains(&target) && !board.is_flag_at(&target) {
+        return;
+    }
+    hints.target = None;
+    for entity in highlights.iter().chain(displays.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn highlight(
+    commands: &mut Commands,
+    board: &Board,
+    board_assets: &BoardAssets,
+    coordinates: Coordinates,
+    color: Color,
+) {
+    let entity = match board.get_tile_entity(coordinates) {
+        Some(&entity) => entity,
+        None => return,
+    };
+    let topology = board.tile_map.topology;
+    let (tile_material, _) = board_assets.tile_materials(topology);
+    let size = topology.topology().tile_sprite_size(board.tile_size);
+    commands.entity(entity).with_children(|parent| {
+        parent
+            .spawn(SpriteBundle {
+                texture: tile_material.texture.clone(),
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    color,
+                    ..default()
+                },
+                // Above the cover and the flags
+                transform: Transform::from_xyz(0., 0., 5.),
+                ..default()
+            })
+            .insert(Name::new("Hint"))
+            .insert(HintHighlight);
+    });
+}
+
+fn show_hint(
+    commands: &mut Commands,
+    displays: &mut Query<&mut Text, With<HintDisplay>>,
+    board_assets: &BoardAssets,
+    line: String,
+) {
+    if let Some(mut text) = displays.iter_mut().next() {
+        text.sections[0].value = line;
+        return;
+    }
+    commands
+        .spawn(
+            TextBundle::from_section(
+                line,
+                TextStyle {
+                    font: board_assets.font.clone(),
+                    font_size: 18.,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.75)),
+        )
+        .insert(Name::new("Hint text"))
+        .insert(HintDisplay);
+}
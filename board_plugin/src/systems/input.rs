@@ -1,15 +1,19 @@
 use bevy::log;
 use bevy::prelude::*;
 
-use crate::events::{TileDiscoverEvent, TileMarkEvent};
+use crate::events::{TileChordEvent, TileDiscoverEvent, TileMarkEvent};
+use crate::replay::ActionKind;
+use crate::resources::clicks::ClickCounter;
 use crate::Board;
 
 pub fn handle_input(
     windows: Query<&Window>,
     board: Res<Board>,
+    mut clicks: Option<ResMut<ClickCounter>>,
     mouse_button_input: Res<Input<MouseButton>>,
     mut tile_discover_event_writer: EventWriter<TileDiscoverEvent>,
     mut tile_mark_event_writer: EventWriter<TileMarkEvent>,
+    mut tile_chord_event_writer: EventWriter<TileChordEvent>,
 ) {
     let window = match windows.get_single() {
         Ok(w) => w,
@@ -27,8 +31,15 @@ pub fn handle_input(
     };
 
     if let Some(coordinates) = board.mouse_position(window, cursor_position) {
+        let mut count = |kind| {
+            if let Some(clicks) = clicks.as_deref_mut() {
+                clicks.count(kind, coordinates, &board);
+            }
+        };
+
         if mouse_button_input.just_released(MouseButton::Left) {
             if let Some(tile) = board.get_tile_entity(coordinates) {
+                count(ActionKind::Reveal);
                 tile_discover_event_writer.send(TileDiscoverEvent(*tile));
                 // log::info!("Trying to uncover tile on {}", coordinates);
             }
@@ -36,9 +47,17 @@ pub fn handle_input(
 
         if mouse_button_input.just_released(MouseButton::Right) {
             if let Some(tile) = board.get_tile_entity(coordinates) {
+                count(ActionKind::Flag);
                 tile_mark_event_writer.send(TileMarkEvent(*tile));
                 // log::info!("Trying to mark tile on {}", coordinates);
             }
         }
+
+        if mouse_button_input.just_released(MouseButton::Middle) {
+            if let Some(tile) = board.get_tile_entity(coordinates) {
+                count(ActionKind::Chord);
+                tile_chord_event_writer.send(TileChordEvent(*tile));
+            }
+        }
     }
 }
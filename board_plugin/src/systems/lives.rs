@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+
+use crate::components::LivesDisplay;
+use crate::resources::board::{Board, HIT_PENALTY};
+use crate::resources::board_assets::BoardAssets;
+
+pub(crate) fn spawn_lives_display(commands: &mut Commands, lives: u8, board_assets: &BoardAssets) {
+    commands
+        .spawn(
+            TextBundle::from_section(
+                lives_text(lives, 0),
+                TextStyle {
+                    font: board_assets.font.clone(),
+                    font_size: 18.,
+                    color: Color::WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(10.),
+                    bottom: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.75)),
+        )
+        .insert(Name::new("Lives"))
+        .insert(LivesDisplay);
+}
+
+pub fn update_lives_display(board: Res<Board>, mut displays: Query<&mut Text, With<LivesDisplay>>) {
+    let line = lives_text(board.lives_left(), board.detonated.len());
+    for mut text in displays.iter_mut() {
+        if text.sections[0].value != line {
+            text.sections[0].value = line.clone();
+        }
+    }
+}
+
+fn lives_text(lives_left: u8, hits: usize) -> String {
+    let mut line = format!("Lives: {}", lives_left);
+    if hits > 0 {
+        let penalty = HIT_PENALTY.as_secs() * hits as u64;
+        line += &format!(", {} mines hit (+{}s)", hits, penalty);
+    }
+    line
+}
@@ -1,16 +1,23 @@
+use bevy::log;
 use bevy::prelude::*;
 
-use crate::components::Flag;
-use crate::events::TileMarkEvent;
+use crate::components::{Flag, PlayerId};
+use crate::events::{BoardCompletedEvent, TileMarkEvent};
 use crate::resources::board_assets::BoardAssets;
+use crate::resources::history::{Change, UndoHistory};
+use crate::resources::versus::VersusState;
 use crate::{components::Coordinates, resources::board::Board};
 
+#[allow(clippy::too_many_arguments)]
 pub fn mark_tiles(
     mut commands: Commands,
     mut board: ResMut<Board>,
+    versus: Option<Res<VersusState>>,
+    mut history: Option<ResMut<UndoHistory>>,
     board_assets: Res<BoardAssets>,
 
     mut tile_trigger_event_reader: EventReader<TileMarkEvent>,
+    mut board_completed_event_writer: EventWriter<BoardCompletedEvent>,
 
     mut q_tiles: Query<(&Coordinates, &Children)>,
     mut q_children: Query<(Entity, With<Flag>), With<Parent>>,
@@ -19,31 +26,84 @@ pub fn mark_tiles(
         let entity = event.0;
 
         if let Ok((coordinates, children)) = q_tiles.get_mut(entity) {
-            if board.try_toggle_mark(*coordinates) {
-                commands.entity(entity).with_children(|parent| {
-                    parent
-                        .spawn(SpriteBundle {
-                            texture: board_assets.flag_material.texture.clone(),
-                            sprite: Sprite {
-                                custom_size: Some(Vec2::splat(board.tile_size)),
-                                color: board_assets.flag_material.color,
-                                ..Default::default()
-                            },
-                            transform: Transform::from_xyz(0., 0., 4.),
-                            ..default()
-                        })
-                        .insert(Flag {})
-                        .insert(Name::new("Flag"));
-                });
-
-                return;
-            }
-
+            // Flags are redrawn at every level
             for &child in children.iter() {
                 if let Ok((flag_entity, _)) = q_children.get_mut(child) {
                     commands.entity(flag_entity).despawn_recursive();
                 }
             }
+
+            let from = board.flag_mines(coordinates);
+            let to = board.try_toggle_mark(*coordinates, PlayerId(0));
+            if let Some(history) = history.as_deref_mut().filter(|_| from != to.unwrap_or(0)) {
+                history.record(Change::Flag {
+                    coordinates: *coordinates,
+                    player: PlayerId(0),
+                    from,
+                    to: to.unwrap_or(0),
+                });
+                history.commit();
+            }
+
+            if let Some(mines) = to {
+                let color = board_assets.flag_material.color;
+                spawn_flag(
+                    &mut commands,
+                    entity,
+                    &board_assets,
+                    board.tile_size,
+                    color,
+                    mines,
+                );
+
+                // Boards whose cells hold several mines are won by their flags
+                if board.tile_map.cell_mines > 1 && versus.is_none() && board.is_completed() {
+                    log::info!("Board cleared");
+                    board_completed_event_writer.send(BoardCompletedEvent);
+                }
+            }
         }
     }
 }
+
+/// Flag standing for `mines` mines, with the count written under it when
+/// there are several
+pub(crate) fn spawn_flag(
+    commands: &mut Commands,
+    tile_entity: Entity,
+    board_assets: &BoardAssets,
+    tile_size: f32,
+    color: Color,
+    mines: u8,
+) {
+    commands.entity(tile_entity).with_children(|parent| {
+        let mut flag = parent.spawn(SpriteBundle {
+            texture: board_assets.flag_material.texture.clone(),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(tile_size)),
+                color,
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(0., 0., 4.),
+            ..default()
+        });
+        flag.insert(Flag {}).insert(Name::new("Flag"));
+        if mines > 1 {
+            flag.with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        mines.to_string(),
+                        TextStyle {
+                            font: board_assets.font.clone(),
+                            font_size: tile_size * 0.5,
+                            color: *board_assets.bomb_counter_color(mines),
+                        },
+                    )
+                    .with_alignment(TextAlignment::Center),
+                    transform: Transform::from_xyz(tile_size / 4., -tile_size / 4., 1.),
+                    ..default()
+                });
+            });
+        }
+    });
+}
@@ -1,3 +1,15 @@
+pub mod daily;
+pub mod endless;
+pub mod hint;
 pub mod input;
+pub mod lives;
 pub mod mark;
+pub mod network;
+pub mod playback;
+pub mod record;
+pub mod share;
+pub mod stats;
+pub mod summary;
 pub mod uncover;
+pub mod undo;
+pub mod versus;
@@ -0,0 +1,256 @@
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::components::{Coordinates, Covered, Flag, PlayerCursor};
+use crate::events::{
+    BombExplosionEvent, PlayerCursorEvent, TileChordEvent, TileDiscoverEvent, TileMarkEvent,
+    VersusEndEvent,
+};
+use crate::net::{BoardDiff, ClientMessage, GameOutcome, NetworkClient, ServerMessage};
+use crate::resources::board::Board;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::versus::{VersusOutcome, VersusState};
+use crate::resources::BoardOptions;
+use crate::systems::mark::spawn_flag;
+
+/// Shares the hovered tile with the other players
+pub fn send_cursor(
+    mut client: ResMut<NetworkClient>,
+    board: Res<Board>,
+    windows: Query<&Window>,
+    mut last_sent: Local<Option<Coordinates>>,
+) {
+    let hovered = windows.get_single().ok().and_then(|window| {
+        window
+            .cursor_position()
+            .and_then(|position| board.mouse_position(window, position))
+    });
+    if hovered == *last_sent || !client.is_connected() {
+        return;
+    }
+
+    *last_sent = hovered;
+    if let Err(e) = client.send(ClientMessage::Cursor(hovered)) {
+        log::warn!("Lost connection to the server: {}", e);
+    }
+}
+
+pub fn update_player_cursors(
+    mut commands: Commands,
+    board: Res<Board>,
+    board_assets: Res<BoardAssets>,
+    mut player_cursor_event_reader: EventReader<PlayerCursorEvent>,
+    mut cursors: Query<(&PlayerCursor, &mut Transform, &mut Visibility)>,
+) {
+    for event in player_cursor_event_reader.iter() {
+        let cursor = cursors
+            .iter_mut()
+            .find(|(cursor, _, _)| cursor.0 == event.player);
+
+        match (cursor, event.coordinates) {
+            (Some((_, _, mut visibility)), None) => *visibility = Visibility::Hidden,
+            (Some((_, mut transform, mut visibility)), Some(coordinates)) => {
+                transform.translation = board.tile_center(coordinates).extend(6.);
+                *visibility = Visibility::Visible;
+            }
+            (None, Some(coordinates)) => {
+                let color = board_assets.player_color(event.player).with_a(0.4);
+                let cursor = commands
+                    .spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(Vec2::splat(board.tile_size)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(
+                            board.tile_center(coordinates).extend(6.),
+                        ),
+                        ..default()
+                    })
+                    .insert(Name::new(format!("Cursor: Player {}", event.player.0 + 1)))
+                    .insert(PlayerCursor(event.player))
+                    .id();
+                commands.entity(board.entity).add_child(cursor);
+            }
+            (None, None) => (),
+        }
+    }
+}
+
+pub fn request_sync(mut client: ResMut<NetworkClient>) {
+    if let Err(e) = client.send(ClientMessage::Sync) {
+        log::warn!("Failed to request board sync: {}", e);
+    }
+}
+
+/// Forwards local clicks to the server instead of resolving them locally
+pub fn send_commands(
+    mut client: ResMut<NetworkClient>,
+    mut tile_discover_event_reader: EventReader<TileDiscoverEvent>,
+    mut tile_mark_event_reader: EventReader<TileMarkEvent>,
+    mut tile_chord_event_reader: EventReader<TileChordEvent>,
+    tiles: Query<&Coordinates>,
+) {
+    let reveals = tile_discover_event_reader
+        .iter()
+        .filter_map(|event| tiles.get(event.0).ok())
+        .map(|coordinates| ClientMessage::Reveal(*coordinates));
+    let marks = tile_mark_event_reader
+        .iter()
+        .filter_map(|event| tiles.get(event.0).ok())
+        .map(|coordinates| ClientMessage::ToggleFlag(*coordinates));
+    let chords = tile_chord_event_reader
+        .iter()
+        .filter_map(|event| tiles.get(event.0).ok())
+        .map(|coordinates| ClientMessage::Chord(*coordinates));
+    let messages: Vec<ClientMessage> = reveals.chain(marks).chain(chords).collect();
+
+    if !client.is_connected() {
+        return;
+    }
+    for message in messages {
+        if let Err(e) = client.send(message) {
+            log::warn!("Lost connection to the server: {}", e);
+            return;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn apply_server_diffs(
+    mut commands: Commands,
+    mut client: ResMut<NetworkClient>,
+    mut board: ResMut<Board>,
+    mut versus: Option<ResMut<VersusState>>,
+    board_options: Option<Res<BoardOptions>>,
+    board_assets: Res<BoardAssets>,
+    mut bomb_explosion_event_writer: EventWriter<BombExplosionEvent>,
+    mut versus_end_event_writer: EventWriter<VersusEndEvent>,
+    mut player_cursor_event_writer: EventWriter<PlayerCursorEvent>,
+
+    mut tiles: Query<(&mut Covered, &mut Sprite, &Children)>,
+    flags: Query<Entity, With<Flag>>,
+) {
+    if !client.is_connected() {
+        return;
+    }
+    let messages = match client.poll() {
+        Ok(messages) => messages,
+        Err(e) => {
+            log::warn!("Lost connection to the server: {}", e);
+            return;
+        }
+    };
+    let padding = board_options.map_or(0., |options| options.tile_padding);
+
+    for message in messages {
+        let diff: BoardDiff = match message {
+            ServerMessage::Diff(diff) => diff,
+            ServerMessage::Rejected(reason) => {
+                log::info!("Server rejected command: {}", reason);
+                continue;
+            }
+            ServerMessage::Cursor {
+                player,
+                coordinates,
+            } => {
+                player_cursor_event_writer.send(PlayerCursorEvent {
+                    player,
+                    coordinates,
+                });
+                continue;
+            }
+            ServerMessage::Summary(summary) => {
+                commands.insert_resource(summary);
+                continue;
+            }
+            ServerMessage::Welcome(_) => continue,
+        };
+
+        for reveal in diff.revealed {
+            let coordinates = reveal.coordinates;
+            if !board.tile_map.contains(coordinates)
+                || !board.coordinates_discovered.insert(coordinates)
+            {
+                continue;
+            }
+            board.tile_map.set_tile(coordinates, reveal.tile);
+
+            let entity = match board.get_tile_entity(coordinates) {
+                Some(entity) => *entity,
+                None => continue,
+            };
+            crate::spawn_tile_face(
+                &mut commands.entity(entity),
+                reveal.tile,
+                board.tile_size,
+                padding,
+                &board_assets,
+            );
+            if let Ok((mut covered, mut sprite, _)) = tiles.get_mut(entity) {
+                covered.is_covered = false;
+                // Found mines are colored after their finder in versus games
+                if reveal.tile.is_bomb() && versus.is_some() {
+                    if let Some(player) = reveal.player {
+                        sprite.color = *board_assets.player_color(player);
+                    }
+                }
+            }
+        }
+
+        for (coordinates, player) in diff.marked {
+            if board
+                .coordinates_marked
+                .insert(coordinates, player)
+                .is_some()
+            {
+                continue;
+            }
+            if let Some(&entity) = board.get_tile_entity(coordinates) {
+                let color = *board_assets.player_color(player);
+                spawn_flag(&mut commands, entity, &board_assets, board.tile_size, color, 1);
+            }
+        }
+
+        for coordinates in diff.unmarked {
+            board.coordinates_marked.remove(&coordinates);
+            let entity = match board.get_tile_entity(coordinates) {
+                Some(entity) => *entity,
+                None => continue,
+            };
+            if let Ok((_, _, children)) = tiles.get(entity) {
+                for flag in flags.iter_many(children) {
+                    commands.entity(flag).despawn_recursive();
+                }
+            }
+        }
+
+        if let (Some(versus), Some(state)) = (versus.as_deref_mut(), diff.versus) {
+            *versus = state;
+        }
+
+        match diff.outcome {
+            Some(GameOutcome::Exploded {
+                player,
+                coordinates,
+            }) => {
+                log::info!(
+                    "Boom! Player {} hit a mine at {}",
+                    player.0 + 1,
+                    coordinates
+                );
+                bomb_explosion_event_writer.send(BombExplosionEvent);
+            }
+            Some(GameOutcome::VersusWon(player)) => {
+                log::info!("Player {} wins", player.0 + 1);
+                versus_end_event_writer.send(VersusEndEvent(VersusOutcome::Won(player)));
+            }
+            Some(GameOutcome::VersusDraw) => {
+                log::info!("Draw");
+                versus_end_event_writer.send(VersusEndEvent(VersusOutcome::Draw));
+            }
+            Some(GameOutcome::Cleared) => log::info!("Board cleared"),
+            None => (),
+        }
+    }
+}
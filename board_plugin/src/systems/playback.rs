@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+
+use crate::components::{GhostCursor, PlaybackControls, PlaybackLabel, ScrubBar, ScrubProgress};
+use crate::events::{TileChordEvent, TileDiscoverEvent, TileMarkEvent};
+use crate::replay::{ActionKind, ReplayPlayback};
+use crate::resources::board::Board;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::hints::HintCounter;
+use crate::AppState;
+
+/// Feeds the recorded actions to the board in place of `handle_input`
+#[allow(clippy::too_many_arguments)]
+pub fn play_replay(
+    time: Res<Time>,
+    board: Res<Board>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut tile_discover_event_writer: EventWriter<TileDiscoverEvent>,
+    mut tile_mark_event_writer: EventWriter<TileMarkEvent>,
+    mut tile_chord_event_writer: EventWriter<TileChordEvent>,
+    mut hints: Option<ResMut<HintCounter>>,
+) {
+    // Seeking backwards replays the game on a fresh board
+    if playback.is_rebuilding() {
+        next_state.set(AppState::InGame);
+        return;
+    }
+
+    playback.advance(time.delta_seconds());
+    for action in playback.due_actions() {
+        let tile = match board.get_tile_entity(action.coordinates) {
+            Some(tile) => *tile,
+            None => continue,
+        };
+        match action.kind {
+            ActionKind::Reveal => tile_discover_event_writer.send(TileDiscoverEvent(tile)),
+            ActionKind::Flag => tile_mark_event_writer.send(TileMarkEvent(tile)),
+            ActionKind::Chord => tile_chord_event_writer.send(TileChordEvent(tile)),
+            // Counted so the end screen shows the game as assisted
+            ActionKind::Hint => {
+                if let Some(hints) = hints.as_deref_mut() {
+                    hints.used += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Space toggles pause, up/down change the speed, left/right step through
+/// the actions
+pub fn handle_playback_keys(keys: Res<Input<KeyCode>>, mut playback: ResMut<ReplayPlayback>) {
+    if keys.just_pressed(KeyCode::Space) {
+        playback.toggle_pause();
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        playback.faster();
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        playback.slower();
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        playback.step_forward();
+    }
+    if keys.just_pressed(KeyCode::Left) {
+        playback.step_back();
+    }
+}
+
+/// Dragging on the scrub bar previews the position, releasing seeks to it
+pub fn scrub(
+    windows: Query<&Window>,
+    mut playback: ResMut<ReplayPlayback>,
+    bars: Query<(&Interaction, &Node, &GlobalTransform), With<ScrubBar>>,
+    mut progress: Query<&mut Style, With<ScrubProgress>>,
+    mut dragged: Local<Option<f32>>,
+) {
+    let (interaction, node, transform) = match bars.get_single() {
+        Ok(bar) => bar,
+        Err(_) => return,
+    };
+
+    if *interaction != Interaction::Clicked {
+        if let Some(dragged) = dragged.take() {
+            playback.seek_progress(dragged);
+        }
+        return;
+    }
+
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position());
+    if let Some(cursor) = cursor {
+        let left = transform.translation().x - node.size().x / 2.;
+        let position = ((cursor.x - left) / node.size().x).clamp(0., 1.);
+        *dragged = Some(position);
+        for mut style in progress.iter_mut() {
+            style.size.width = Val::Percent(position * 100.);
+        }
+    }
+}
+
+pub fn update_playback_controls(
+    playback: Res<ReplayPlayback>,
+    bars: Query<&Interaction, With<ScrubBar>>,
+    mut progress: Query<&mut Style, With<ScrubProgress>>,
+    mut labels: Query<&mut Text, With<PlaybackLabel>>,
+) {
+    if bars.iter().all(|interaction| *interaction != Interaction::Clicked) {
+        for mut style in progress.iter_mut() {
+            style.size.width = Val::Percent(playback.progress() * 100.);
+        }
+    }
+
+    let state = if playback.is_finished() {
+        "finished"
+    } else if playback.is_paused() {
+        "paused"
+    } else {
+        "playing"
+    };
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = format!(
+            "{:.1}s / {:.1}s  {}x  {}",
+            playback.position() as f32 / 1000.,
+            playback.replay.duration() as f32 / 1000.,
+            playback.speed(),
+            state
+        );
+    }
+}
+
+pub fn update_ghost_cursor(
+    mut commands: Commands,
+    board: Res<Board>,
+    playback: Res<ReplayPlayback>,
+    mut cursors: Query<(&mut Transform, &mut Visibility), With<GhostCursor>>,
+) {
+    let position = playback.cursor().map(|cursor| (cursor * board.tile_size).extend(7.));
+
+    match (cursors.get_single_mut(), position) {
+        (Ok((_, mut visibility)), None) => *visibility = Visibility::Hidden,
+        (Ok((mut transform, mut visibility)), Some(position)) => {
+            transform.translation = position;
+            *visibility = Visibility::Visible;
+        }
+        (Err(_), Some(position)) => {
+            let cursor = commands
+                .spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgba(1., 1., 1., 0.8),
+                        custom_size: Some(Vec2::splat(board.tile_size / 3.)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position),
+                    ..default()
+                })
+                .insert(Name::new("Ghost cursor"))
+                .insert(GhostCursor)
+                .id();
+            commands.entity(board.entity).add_child(cursor);
+        }
+        (Err(_), None) => (),
+    }
+}
+
+pub fn spawn_playback_controls(mut commands: Commands, board_assets: Res<BoardAssets>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: Val::Px(10.),
+                    left: Val::Percent(10.),
+                    ..default()
+                },
+                size: Size::width(Val::Percent(80.)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("Playback controls"))
+        .insert(PlaybackControls)
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: board_assets.font.clone(),
+                        font_size: 16.,
+                        color: Color::WHITE,
+                    },
+                ))
+                .insert(PlaybackLabel);
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Px(12.)),
+                        margin: UiRect::top(Val::Px(4.)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                    ..default()
+                })
+                .insert(ScrubBar)
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                                ..default()
+                            },
+                            background_color: Color::WHITE.into(),
+                            ..default()
+                        })
+                        .insert(ScrubProgress);
+                });
+            parent.spawn(TextBundle::from_section(
+                "Space: play/pause  Up/Down: speed  Left/Right: step",
+                TextStyle {
+                    font: board_assets.font.clone(),
+                    font_size: 12.,
+                    color: Color::GRAY,
+                },
+            ));
+        });
+}
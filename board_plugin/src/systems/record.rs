@@ -0,0 +1,55 @@
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::components::Coordinates;
+use crate::events::{TileChordEvent, TileDiscoverEvent, TileMarkEvent, VersusEndEvent};
+use crate::replay::{ActionKind, ReplayRecorder};
+use crate::resources::board::Board;
+use crate::resources::game_summary::GameSummary;
+
+/// Records the same events `handle_input` feeds the board with
+pub fn record_actions(
+    windows: Query<&Window>,
+    board: Res<Board>,
+    mut recorder: ResMut<ReplayRecorder>,
+    tiles: Query<&Coordinates>,
+    mut tile_discover_event_reader: EventReader<TileDiscoverEvent>,
+    mut tile_mark_event_reader: EventReader<TileMarkEvent>,
+    mut tile_chord_event_reader: EventReader<TileChordEvent>,
+) {
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| Some(board.board_position(window, window.cursor_position()?)));
+
+    let actions = tile_discover_event_reader
+        .iter()
+        .map(|event| (ActionKind::Reveal, event.0))
+        .chain(tile_mark_event_reader.iter().map(|event| (ActionKind::Flag, event.0)))
+        .chain(tile_chord_event_reader.iter().map(|event| (ActionKind::Chord, event.0)));
+    for (kind, entity) in actions {
+        if let Ok(coordinates) = tiles.get(entity) {
+            recorder.record(kind, *coordinates, cursor);
+        }
+    }
+}
+
+/// Writes the replay once the game is over
+pub fn save_replay(
+    mut commands: Commands,
+    recorder: Res<ReplayRecorder>,
+    summary: Option<Res<GameSummary>>,
+    mut versus_end_event_reader: EventReader<VersusEndEvent>,
+) {
+    let versus_ended = versus_end_event_reader.iter().count() > 0;
+    if !versus_ended && !summary.is_some_and(|summary| summary.is_added()) {
+        return;
+    }
+
+    let path = ReplayRecorder::default_path();
+    match recorder.replay.save(&path) {
+        Ok(()) => log::info!("Replay saved to {}", path.display()),
+        Err(e) => log::error!("Failed to save replay: {}", e),
+    }
+    commands.remove_resource::<ReplayRecorder>();
+}
@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::components::ShareCodeDialog;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::share_code::{ShareCode, ShareCodeInput};
+use crate::resources::BoardOptions;
+use crate::AppState;
+
+/// P opens the dialog to type or paste a board code
+pub fn open_share_code_input(mut commands: Commands, keys: Res<Input<KeyCode>>) {
+    if keys.just_pressed(KeyCode::P) {
+        commands.insert_resource(ShareCodeInput::default());
+    }
+}
+
+pub fn spawn_share_code_dialog(mut commands: Commands, board_assets: Res<BoardAssets>) {
+    commands
+        .spawn(
+            TextBundle::from_sections([
+                TextSection::new(
+                    "Board code: ",
+                    TextStyle {
+                        font: board_assets.font.clone(),
+                        font_size: 18.,
+                        color: Color::WHITE,
+                    },
+                ),
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font: board_assets.font.clone(),
+                        font_size: 18.,
+                        color: Color::YELLOW,
+                    },
+                ),
+                TextSection::new(
+                    "",
+                    TextStyle {
+                        font: board_assets.font.clone(),
+                        font_size: 14.,
+                        color: Color::GRAY,
+                    },
+                ),
+            ])
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.),
+                    left: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_background_color(Color::rgba(0., 0., 0., 0.85)),
+        )
+        .insert(Name::new("Share code dialog"))
+        .insert(ShareCodeDialog);
+}
+
+/// Enter starts the coded board, escape closes the dialog
+pub fn edit_share_code(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut received_characters: EventReader<ReceivedCharacter>,
+    mut input: ResMut<ShareCodeInput>,
+    mut board_options: ResMut<BoardOptions>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut dialogs: Query<(Entity, &mut Text), With<ShareCodeDialog>>,
+) {
+    // The key that opened the dialog is not part of the code
+    if input.is_added() {
+        received_characters.clear();
+        return;
+    }
+
+    input.text.extend(
+        received_characters
+            .iter()
+            .map(|event| event.char)
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_'),
+    );
+    if keys.just_pressed(KeyCode::Back) {
+        input.text.pop();
+    }
+
+    let mut close = keys.just_pressed(KeyCode::Escape);
+    if keys.just_pressed(KeyCode::Return) {
+        match input.text.parse::<ShareCode>() {
+            Ok(code) => {
+                code.apply_to(&mut board_options);
+                next_state.set(AppState::InGame);
+                close = true;
+            }
+            Err(e) => input.error = Some(e.to_string()),
+        }
+    }
+
+    for (entity, mut text) in dialogs.iter_mut() {
+        if close {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        text.sections[1].value = input.text.clone();
+        text.sections[2].value = match &input.error {
+            Some(error) => format!("\n{}", error),
+            None => "\nEnter to play, Esc to cancel".to_string(),
+        };
+    }
+    if close {
+        commands.remove_resource::<ShareCodeInput>();
+    }
+}
@@ -0,0 +1,138 @@
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::components::StatsScreen;
+use crate::events::{BoardCompletedEvent, BombExplosionEvent};
+use crate::resources::board::Board;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::clicks::ClickCounter;
+use crate::resources::hints::HintCounter;
+use crate::resources::stats::{BoardConfig, GameRecord, GameStats};
+
+/// Adds every finished single player game to the statistics
+pub fn record_game_stats(
+    time: Res<Time>,
+    board: Res<Board>,
+    clicks: Option<Res<ClickCounter>>,
+    hints: Option<Res<HintCounter>>,
+    mut stats: ResMut<GameStats>,
+    mut bomb_explosion_event_reader: EventReader<BombExplosionEvent>,
+    mut board_completed_event_reader: EventReader<BoardCompletedEvent>,
+) {
+    let exploded = bomb_explosion_event_reader.iter().count() > 0;
+    let completed = board_completed_event_reader.iter().count() > 0;
+    if !exploded && !completed {
+        return;
+    }
+
+    stats.record(
+        BoardConfig::of(&board.tile_map, board.lives),
+        GameRecord {
+            won: completed && !exploded,
+            time: board.score_time(&time).as_millis() as u32,
+            bbbv: board.tile_map.bbbv(),
+            clicks: clicks.map(|clicks| *clicks),
+            hints: hints.map_or(0, |hints| hints.used),
+        },
+    );
+    if let Err(e) = stats.save() {
+        log::error!("Failed to save the statistics: {}", e);
+    }
+}
+
+/// S shows or hides the statistics of every board configuration played
+pub fn toggle_stats_screen(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    stats: Res<GameStats>,
+    board_assets: Res<BoardAssets>,
+    screens: Query<Entity, With<StatsScreen>>,
+) {
+    if !keys.just_pressed(KeyCode::S) {
+        return;
+    }
+    if !screens.is_empty() {
+        for entity in screens.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let style = |font_size: f32, color: Color| TextStyle {
+        font: board_assets.font.clone(),
+        font_size,
+        color,
+    };
+    let seconds = |time: Option<u32>| match time {
+        Some(time) => format!("{:.1}s", time as f32 / 1000.),
+        None => "-".to_string(),
+    };
+    let percent = |ratio: Option<f32>| match ratio {
+        Some(ratio) => format!("{:.0}%", ratio * 100.),
+        None => "-".to_string(),
+    };
+
+    let mut sections = vec![TextSection::new("Statistics\n", style(24., Color::WHITE))];
+    if stats.configs.is_empty() {
+        sections.push(TextSection::new("No games played yet\n", style(16., Color::GRAY)));
+    }
+    for config in stats.configs.iter() {
+        sections.push(TextSection::new(
+            format!("{}\n", config.config),
+            style(18., Color::YELLOW),
+        ));
+        sections.push(TextSection::new(
+            format!(
+                "  {} played, {} won, {} lost ({:.0}%)\n  best {}, average {}, 3BV/s {} (best {})\n  efficiency {} (best {}), {} wasted clicks per win\n  streak {} (longest {})\n",
+                config.played(),
+                config.wins,
+                config.losses,
+                config.win_rate() * 100.,
+                seconds(config.best_time),
+                seconds(config.average_time()),
+                config.bbbv_per_second().map_or("-".to_string(), |v| format!("{:.2}", v)),
+                config.best_bbbv_per_second.map_or("-".to_string(), |v| format!("{:.2}", v)),
+                percent(config.efficiency()),
+                percent(config.best_efficiency),
+                config.wasted_per_win().map_or("-".to_string(), |v| format!("{:.1}", v)),
+                config.current_streak,
+                config.longest_streak
+            ),
+            style(14., Color::WHITE),
+        ));
+        if config.assisted_games > 0 {
+            sections.push(TextSection::new(
+                format!(
+                    "  {} assisted games, {} won\n",
+                    config.assisted_games, config.assisted_wins
+                ),
+                style(14., Color::GRAY),
+            ));
+        }
+    }
+    sections.push(TextSection::new("Press S to close", style(14., Color::GRAY)));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.),
+                    ..default()
+                },
+                size: Size::width(Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(1),
+            ..default()
+        })
+        .insert(Name::new("Stats screen"))
+        .insert(StatsScreen)
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_sections(sections)
+                    .with_background_color(Color::rgba(0., 0., 0., 0.9)),
+            );
+        });
+}
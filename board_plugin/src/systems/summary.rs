@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+
+use crate::components::{EndScreen, PlayerId};
+use crate::events::{BoardCompletedEvent, BombExplosionEvent};
+use crate::resources::board::{Board, HIT_PENALTY};
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::clicks::ClickCounter;
+use crate::resources::daily::{date_string, DailyChallenge, DailyHistory};
+use crate::resources::game_summary::GameSummary;
+use crate::resources::hints::HintCounter;
+use crate::resources::history::UndoHistory;
+
+/// Wraps up single player games, networked games get their summary from
+/// the server
+pub fn summarize_local_game(
+    mut commands: Commands,
+    time: Res<Time>,
+    board: Res<Board>,
+    clicks: Option<Res<ClickCounter>>,
+    hints: Option<Res<HintCounter>>,
+    mut bomb_explosion_event_reader: EventReader<BombExplosionEvent>,
+    mut board_completed_event_reader: EventReader<BoardCompletedEvent>,
+) {
+    let exploded = bomb_explosion_event_reader.iter().count() > 0;
+    let completed = board_completed_event_reader.iter().count() > 0;
+    if !exploded && !completed {
+        return;
+    }
+
+    let player = PlayerId(0);
+    let summary = GameSummary::new(
+        &board.tile_map,
+        board
+            .coordinates_discovered
+            .iter()
+            .map(|coordinates| (coordinates, player)),
+        board.coordinates_marked.iter(),
+        exploded.then_some(player),
+    );
+    commands.insert_resource(GameSummary {
+        time: Some(board.score_time(&time).as_millis() as u32),
+        clicks: clicks.map(|clicks| *clicks),
+        hints: hints.map_or(0, |hints| hints.used),
+        ..summary
+    });
+}
+
+pub fn spawn_end_screen(
+    mut commands: Commands,
+    summary: Res<GameSummary>,
+    board: Res<Board>,
+    board_assets: Res<BoardAssets>,
+    daily: Option<Res<DailyChallenge>>,
+    daily_history: Option<Res<DailyHistory>>,
+    practice: Option<Res<UndoHistory>>,
+) {
+    let style = |font_size: f32, color: Color| TextStyle {
+        font: board_assets.font.clone(),
+        font_size,
+        color,
+    };
+
+    let headline = match summary.mine_hit_by() {
+        Some(player) => format!("Player {} hit a mine!\n", player.0 + 1),
+        None => "Board cleared!\n".to_string(),
+    };
+    let mut sections = vec![TextSection::new(headline, style(28., Color::WHITE))];
+    for stats in summary.players.iter() {
+        sections.push(TextSection::new(
+            format!(
+                "Player {}: {} tiles revealed, {} correct flags\n",
+                stats.player.0 + 1,
+                stats.tiles_revealed,
+                stats.correct_flags
+            ),
+            style(18., *board_assets.player_color(stats.player)),
+        ));
+    }
+    // The layout of networked games is only partly known to clients
+    if let Some(time) = summary.time {
+        let bbbv = board.tile_map.bbbv();
+        let seconds = time as f32 / 1000.;
+        let mut line = format!(
+            "3BV {} ({} openings, {} islands), {:.1}s",
+            bbbv,
+            board.tile_map.openings(),
+            board.tile_map.islands(),
+            seconds
+        );
+        if summary.cleared && seconds > 0. {
+            line += &format!(", {:.2} 3BV/s", bbbv as f32 / seconds);
+        }
+        if let Some(clicks) = summary.clicks {
+            line += &format!(
+                "\n{} clicks: {} useful, {} wasted",
+                clicks.total(),
+                clicks.useful,
+                clicks.wasted
+            );
+            if let Some(efficiency) = clicks.efficiency(bbbv).filter(|_| summary.cleared) {
+                line += &format!(", efficiency {:.0}%", efficiency * 100.);
+            }
+        }
+        if !board.detonated.is_empty() {
+            let hits = board.detonated.len() as u32;
+            line += &format!(
+                "\n{} mines hit, {}s penalty included",
+                hits,
+                (HIT_PENALTY * hits).as_secs()
+            );
+        }
+        sections.push(TextSection::new(line + "\n", style(16., Color::WHITE)));
+    }
+    let daily = daily.filter(|daily| daily.is_daily_board(&board.tile_map));
+    if let (Some(daily), Some(history)) = (daily, daily_history) {
+        let line = match history.result(daily.day).filter(|_| daily.scored) {
+            Some(result) => format!(
+                "Daily {}: {} in {:.1}s, streak {} (best {})\n",
+                date_string(daily.day),
+                match (result.won, result.is_assisted()) {
+                    (true, true) => "won with hints",
+                    (true, false) => "won",
+                    (false, _) => "lost",
+                },
+                result.time as f32 / 1000.,
+                history.current_streak(daily.day),
+                history.longest_streak
+            ),
+            None => format!("Daily {}: practice, not scored\n", date_string(daily.day)),
+        };
+        sections.push(TextSection::new(line, style(16., Color::GOLD)));
+    }
+    if summary.is_assisted() {
+        sections.push(TextSection::new(
+            format!(
+                "Assisted: {} {} used, not ranked\n",
+                summary.hints,
+                if summary.hints == 1 { "hint" } else { "hints" }
+            ),
+            style(16., Color::GOLD),
+        ));
+    }
+    if practice.is_some() {
+        sections.push(TextSection::new(
+            "Practice game, not ranked. Press Z to undo\n",
+            style(16., Color::GOLD),
+        ));
+    }
+    sections.push(TextSection::new(
+        "Press C to leave the board",
+        style(14., Color::GRAY),
+    ));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(10.),
+                    ..default()
+                },
+                size: Size::width(Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Name::new("End screen"))
+        .insert(EndScreen)
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_sections(sections)
+                    .with_text_alignment(TextAlignment::Center)
+                    .with_background_color(Color::rgba(0., 0., 0., 0.75)),
+            );
+        });
+}
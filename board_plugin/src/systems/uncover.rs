@@ -3,60 +3,144 @@ use bevy::prelude::*;
 
 use crate::components::Covered;
 use crate::components::TileCover;
+use crate::events::{BoardCompletedEvent, TileChordEvent, VersusEndEvent};
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::history::{Change, UndoHistory};
+use crate::resources::versus::{VersusOutcome, VersusState};
 use crate::{
     components::Coordinates,
     events::{BombExplosionEvent, TileDiscoverEvent},
     resources::board::Board,
 };
 
+/// Tint of the mines uncovered in games with several lives
+pub(crate) const DETONATED_COLOR: Color = Color::rgb(0.85, 0.2, 0.2);
+
+#[allow(clippy::too_many_arguments)]
 pub fn handle_discover_event(
     mut board: ResMut<Board>,
+    mut versus: Option<ResMut<VersusState>>,
+    mut history: Option<ResMut<UndoHistory>>,
+    board_assets: Res<BoardAssets>,
     mut bomb_explosion_event_writer: EventWriter<BombExplosionEvent>,
+    mut versus_end_event_writer: EventWriter<VersusEndEvent>,
+    mut board_completed_event_writer: EventWriter<BoardCompletedEvent>,
     mut tile_trigger_event_reader: EventReader<TileDiscoverEvent>,
+    mut tile_chord_event_reader: EventReader<TileChordEvent>,
 
     mut tiles: Query<(&mut Covered, &Coordinates, Entity)>,
+    mut sprites: Query<&mut Sprite>,
 ) {
-    for trigger_event in tile_trigger_event_reader.iter() {
-        let entity = trigger_event.0;
+    // Chording is left out of versus games, every reveal there is a turn
+    let chorded: Vec<Entity> = tile_chord_event_reader
+        .iter()
+        .filter(|_| versus.is_none())
+        .filter_map(|event| tiles.get(event.0).ok())
+        .flat_map(|(_, coordinates, _)| board.chord_targets(*coordinates))
+        .filter_map(|coordinates| board.get_tile_entity(coordinates).copied())
+        .collect();
+    let triggered = tile_trigger_event_reader.iter().map(|event| event.0);
 
-        if let Ok((mut covered, coordinates, _)) = tiles.get_mut(entity) {
-            if board.is_flag_at(coordinates) {
+    for entity in triggered.chain(chorded) {
+        if let Ok((mut covered, &coordinates, _)) = tiles.get_mut(entity) {
+            if board.is_flag_at(&coordinates) {
                 continue;
             }
-            board.coordinates_discovered.insert(*coordinates);
+            if let Some(versus) = versus.as_deref() {
+                // Revealed tiles don't consume a turn
+                if versus.is_over() || board.coordinates_discovered.contains(&coordinates) {
+                    continue;
+                }
+            }
+            if board.coordinates_discovered.insert(coordinates) {
+                if let Some(history) = history.as_deref_mut() {
+                    history.record(Change::Reveal(coordinates));
+                }
+            }
             covered.is_covered = false;
 
-            if board.tile_map.is_bomb_at(*coordinates) {
-                log::info!("Boom!");
-                bomb_explosion_event_writer.send(BombExplosionEvent);
+            if board.tile_map.is_bomb_at(coordinates) {
+                match versus.as_deref_mut() {
+                    Some(versus) => {
+                        let player = versus.current;
+                        if let Ok(mut sprite) = sprites.get_mut(entity) {
+                            sprite.color = *board_assets.player_color(player);
+                        }
+                        if let Some(outcome) = versus.score_mine(board.tile_map.mine_cells) {
+                            match outcome {
+                                VersusOutcome::Won(player) => log::info!(
+                                    "Player {} wins with {} mines",
+                                    player.0 + 1,
+                                    versus.score(player)
+                                ),
+                                VersusOutcome::Draw => log::info!("Every mine found, draw"),
+                            }
+                            versus_end_event_writer.send(VersusEndEvent(outcome));
+                        }
+                    }
+                    None if board.lives > 1 => {
+                        if let Ok(mut sprite) = sprites.get_mut(entity) {
+                            sprite.color = DETONATED_COLOR;
+                        }
+                        if let Some(history) = history.as_deref_mut() {
+                            if !board.detonated.contains(&coordinates) {
+                                history.record(Change::Detonate(coordinates));
+                            }
+                        }
+                        if board.hit_mine(coordinates) {
+                            log::info!("Mine hit, {} lives left", board.lives_left());
+                        } else {
+                            log::info!("Boom! No lives left");
+                            bomb_explosion_event_writer.send(BombExplosionEvent);
+                        }
+                    }
+                    None => {
+                        log::info!("Boom!");
+                        bomb_explosion_event_writer.send(BombExplosionEvent);
+                    }
+                }
+            } else if let Some(versus) = versus.as_deref_mut() {
+                versus.pass_turn();
             }
 
-            if !board.tile_map.is_empty_at(*coordinates) {
-                continue;
+            if board.tile_map.is_empty_at(coordinates) {
+                for (coordinates, entity) in board.flood_discovery(&coordinates) {
+                    if let Ok((mut covered, _, _)) = tiles.get_mut(entity) {
+                        covered.is_covered = false;
+                    }
+                    if let Some(history) = history.as_deref_mut() {
+                        history.record(Change::Reveal(coordinates));
+                    }
+                }
             }
 
-            let discovered_entities = board.flood_discovery(&coordinates).clone();
-            for (mut covered, _, entity) in tiles.iter_mut() {
-                if discovered_entities.contains(&entity) {
-                    covered.is_covered = false;
-                }
+            if versus.is_none() && !board.tile_map.is_bomb_at(coordinates) && board.is_completed()
+            {
+                log::info!("Board cleared");
+                board_completed_event_writer.send(BoardCompletedEvent);
             }
         }
     }
+    // Everything revealed by the clicks of a frame is taken back at once
+    if let Some(history) = history.as_deref_mut() {
+        history.commit();
+    }
 }
 
+/// Shows or hides the covers of tiles, undone moves cover tiles again
 pub fn discover_tiles(
     query: Query<(&Covered, &Children), Changed<Covered>>,
     mut q_children: Query<(&mut Visibility, With<TileCover>)>,
 ) {
-    query
-        .iter()
-        .filter(|(covered, _)| !covered.is_covered)
-        .for_each(|(_, children)| {
-            children.iter().for_each(|&child| {
-                if let Ok((mut visibility, _)) = q_children.get_mut(child) {
-                    *visibility = Visibility::Hidden;
-                }
-            });
+    query.iter().for_each(|(covered, children)| {
+        let cover = match covered.is_covered {
+            true => Visibility::Visible,
+            false => Visibility::Hidden,
+        };
+        children.iter().for_each(|&child| {
+            if let Ok((mut visibility, _)) = q_children.get_mut(child) {
+                *visibility = cover;
+            }
         });
+    });
 }
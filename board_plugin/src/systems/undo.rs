@@ -0,0 +1,106 @@
+use bevy::log;
+use bevy::prelude::*;
+
+use crate::components::{Covered, EndScreen, Flag};
+use crate::events::{BoardCompletedEvent, BombExplosionEvent};
+use crate::resources::board::Board;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::game_summary::GameSummary;
+use crate::resources::history::{Change, UndoHistory};
+use crate::systems::mark::spawn_flag;
+use crate::systems::uncover::DETONATED_COLOR;
+
+/// Z takes back the last move of a practice game, fatal ones included, and
+/// Y plays it again
+#[allow(clippy::too_many_arguments)]
+pub fn undo_moves(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    mut board: ResMut<Board>,
+    mut history: ResMut<UndoHistory>,
+    board_assets: Res<BoardAssets>,
+    end_screens: Query<Entity, With<EndScreen>>,
+    mut tiles: Query<(&mut Covered, &mut Sprite, Option<&Children>)>,
+    flags: Query<Entity, With<Flag>>,
+    mut bomb_explosion_event_writer: EventWriter<BombExplosionEvent>,
+    mut board_completed_event_writer: EventWriter<BoardCompletedEvent>,
+) {
+    let redo = keys.just_pressed(KeyCode::Y);
+    let changes = if keys.just_pressed(KeyCode::Z) {
+        history.undo(&mut board)
+    } else if redo {
+        history.redo(&mut board)
+    } else {
+        return;
+    };
+    let changes = match changes {
+        Some(changes) => changes,
+        None => return,
+    };
+    log::info!(
+        "{} {} changes",
+        if redo { "Redoing" } else { "Undoing" },
+        changes.len()
+    );
+
+    // Play goes on from the restored position
+    commands.remove_resource::<GameSummary>();
+    for entity in end_screens.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let (tile_material, _) = board_assets.tile_materials(board.tile_map.topology);
+    for change in changes.iter() {
+        let coordinates = change.coordinates();
+        let entity = match board.get_tile_entity(coordinates) {
+            Some(&entity) => entity,
+            None => continue,
+        };
+        let (mut covered, mut sprite, children) = match tiles.get_mut(entity) {
+            Ok(tile) => tile,
+            Err(_) => continue,
+        };
+        match change {
+            Change::Reveal(_) => {
+                covered.is_covered = !board.coordinates_discovered.contains(&coordinates);
+            }
+            Change::Detonate(_) => {
+                sprite.color = match board.detonated.contains(&coordinates) {
+                    true => DETONATED_COLOR,
+                    false => tile_material.color,
+                };
+            }
+            Change::Flag { .. } => {
+                for &child in children.into_iter().flatten() {
+                    if flags.contains(child) {
+                        commands.entity(child).despawn_recursive();
+                    }
+                }
+                let mines = board.flag_mines(&coordinates);
+                if mines > 0 {
+                    let color = board_assets.flag_material.color;
+                    spawn_flag(
+                        &mut commands,
+                        entity,
+                        &board_assets,
+                        board.tile_size,
+                        color,
+                        mines,
+                    );
+                }
+            }
+        }
+    }
+
+    // A replayed move ends the game the way it did the first time
+    if redo {
+        let mine_revealed = changes.iter().any(|change| {
+            matches!(change, Change::Reveal(coordinates) if board.tile_map.is_bomb_at(*coordinates))
+        });
+        if mine_revealed && (board.lives == 1 || board.lives_left() == 0) {
+            bomb_explosion_event_writer.send(BombExplosionEvent);
+        } else if board.is_completed() {
+            board_completed_event_writer.send(BoardCompletedEvent);
+        }
+    }
+}
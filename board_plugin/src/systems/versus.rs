@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+use crate::components::BoardBackground;
+use crate::resources::board_assets::BoardAssets;
+use crate::resources::versus::{VersusOutcome, VersusState};
+
+/// Tints the board background with the color of the player whose turn it is
+pub fn update_turn_indicator(
+    versus: Res<VersusState>,
+    board_assets: Res<BoardAssets>,
+    mut q_background: Query<&mut Sprite, With<BoardBackground>>,
+) {
+    if !versus.is_changed() {
+        return;
+    }
+
+    let color = match versus.outcome {
+        Some(VersusOutcome::Won(winner)) => *board_assets.player_color(winner),
+        Some(VersusOutcome::Draw) => Color::GRAY,
+        None => *board_assets.player_color(versus.current),
+    };
+    for mut sprite in q_background.iter_mut() {
+        sprite.color = color;
+    }
+}
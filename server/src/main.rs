@@ -0,0 +1,392 @@
+//! Headless authority server for networked games.
+//!
+//! Owns the board layout, validates the commands sent by clients and
+//! broadcasts the resulting diffs to everyone connected.
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::thread;
+
+use board_plugin::components::PlayerId;
+use board_plugin::net::{
+    encode_message, send_message, Authority, ClientMessage, MessageReader, ServerMessage,
+    DEFAULT_PORT, MAX_COMMAND_LINE,
+};
+use board_plugin::resources::generator::MineGenerators;
+use board_plugin::resources::mask::BoardMask;
+use board_plugin::resources::tile_map::TileMap;
+use board_plugin::resources::{BoardOptions, BoardShape, GameRules, Neighborhood};
+
+const USAGE: &str = "Usage: minesweeper_server [--bind ADDRESS] [--size WIDTHxHEIGHT] \
+                     [--mines COUNT] [--generator NAME] [--board FILE] [--mask FILE] \
+                     [--hex] [--wrap] [--neighborhood NAME] [--layers COUNT] [--versus]";
+
+/// Messages waiting for a client's writer thread. A client that falls this
+/// far behind is disconnected rather than holding up the game.
+const OUTGOING_QUEUE: usize = 4096;
+
+enum ServerEvent {
+    Connected(TcpStream),
+    Command(PlayerId, ClientMessage),
+    Disconnected(PlayerId),
+}
+
+fn main() -> io::Result<()> {
+    let (address, options) = match parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    let listener = TcpListener::bind(&address)?;
+    println!(
+        "Serving a {}x{} board with {} mines ({:?}) on {}",
+        options.map_size.0, options.map_size.1, options.bomb_count, options.rules, address
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    let acceptor = sender.clone();
+    thread::spawn(move || accept_connections(listener, acceptor));
+    run(Authority::new(&options), sender, receiver);
+    Ok(())
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(String, BoardOptions), String> {
+    let mut address = format!("127.0.0.1:{}", DEFAULT_PORT);
+    let mut options = BoardOptions {
+        map_size: (16, 16),
+        bomb_count: 40,
+        safe_start: true,
+        ..Default::default()
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--bind" => address = value()?,
+            "--size" => {
+                let size = value()?;
+                options.map_size = size
+                    .split_once('x')
+                    .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                    .ok_or(format!("Invalid board size: {}", size))?;
+            }
+            "--mines" => {
+                let mines = value()?;
+                options.bomb_count = mines
+                    .parse()
+                    .map_err(|_| format!("Invalid mine count: {}", mines))?;
+            }
+            "--generator" => {
+                let name = value()?;
+                if MineGenerators::default().get(&name).is_none() {
+                    return Err(format!("Unknown mine generator: {}", name));
+                }
+                options.generator = name;
+            }
+            "--board" => {
+                let path = value()?;
+                let tile_map = TileMap::load(&path)
+                    .map_err(|e| format!("Failed to load board {}: {}", path, e))?;
+                options.set_layout(&tile_map);
+            }
+            "--mask" => {
+                let path = value()?;
+                let mask = BoardMask::load(&path)
+                    .map_err(|e| format!("Failed to load mask {}: {}", path, e))?;
+                options.set_mask(mask);
+            }
+            "--hex" => options.shape = BoardShape::Hex,
+            "--wrap" => options.wrap = true,
+            "--neighborhood" => {
+                let name = value()?;
+                options.neighborhood = Neighborhood::from_name(&name).ok_or(format!(
+                    "Unknown neighborhood {}, expected one of {}",
+                    name,
+                    Neighborhood::NAMES.join(", ")
+                ))?;
+            }
+            "--layers" => {
+                let layers = value()?;
+                options.layers = layers
+                    .parse()
+                    .ok()
+                    .filter(|layers| *layers > 0)
+                    .ok_or(format!("Invalid layer count: {}", layers))?;
+            }
+            "--versus" => options.rules = GameRules::Versus,
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    if let Some(mask) = &options.mask {
+        mask.check_size(options.map_size)
+            .map_err(|e| format!("Invalid mask: {}", e))?;
+    }
+    let (width, height) = options.map_size;
+    if width.checked_mul(options.layers).is_none() {
+        return Err("Too many layers for the board width".to_string());
+    }
+    let cells = match &options.mask {
+        Some(mask) => mask.cell_count() as u32,
+        None => width as u32 * height as u32,
+    } * options.layers as u32;
+    if options.bomb_count as u32 >= cells {
+        return Err("There must be fewer mines than tiles".to_string());
+    }
+    Ok((address, options))
+}
+
+fn accept_connections(listener: TcpListener, sender: Sender<ServerEvent>) {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if sender.send(ServerEvent::Connected(stream)).is_err() {
+            return;
+        }
+    }
+}
+
+fn read_commands(player: PlayerId, mut stream: TcpStream, sender: Sender<ServerEvent>) {
+    // A client that never ends its line is cut off
+    let mut reader = MessageReader::with_max_line(MAX_COMMAND_LINE);
+    loop {
+        let event = match reader.next_blocking(&mut stream) {
+            Ok(message) => ServerEvent::Command(player, message),
+            Err(_) => ServerEvent::Disconnected(player),
+        };
+        let disconnected = matches!(event, ServerEvent::Disconnected(_));
+        if sender.send(event).is_err() || disconnected {
+            return;
+        }
+    }
+}
+
+/// Player ids in use. An id stays taken until the thread reading its
+/// commands is done, so late events of a player never reach the next one
+/// to get the id
+#[derive(Default)]
+struct PlayerIds {
+    taken: BTreeSet<u8>,
+}
+
+impl PlayerIds {
+    /// The lowest id nobody holds, if any is left
+    fn next_free(&self) -> Option<PlayerId> {
+        (0..=u8::MAX)
+            .find(|id| !self.taken.contains(id))
+            .map(PlayerId)
+    }
+
+    fn take(&mut self, player: PlayerId) {
+        self.taken.insert(player.0);
+    }
+
+    fn release(&mut self, player: PlayerId) {
+        self.taken.remove(&player.0);
+    }
+}
+
+/// A connected player: messages go through a queue to a thread of its own,
+/// so a slow connection only stalls itself
+struct Client {
+    outgoing: SyncSender<Vec<u8>>,
+    stream: TcpStream,
+}
+
+impl Client {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        let writer = stream.try_clone()?;
+        let (outgoing, queue) = mpsc::sync_channel(OUTGOING_QUEUE);
+        thread::spawn(move || write_messages(writer, queue));
+        Ok(Self { outgoing, stream })
+    }
+
+    /// Queues `line`, fails if the writer is gone or too far behind
+    fn send(&self, line: Vec<u8>) -> Result<(), TrySendError<Vec<u8>>> {
+        self.outgoing.try_send(line)
+    }
+}
+
+/// Writes queued messages until the queue is dropped or the connection
+/// fails, whatever piled up meanwhile goes out in one write
+fn write_messages(mut stream: TcpStream, queue: Receiver<Vec<u8>>) {
+    while let Ok(mut lines) = queue.recv() {
+        for line in queue.try_iter() {
+            lines.extend(line);
+        }
+        if stream.write_all(&lines).is_err() {
+            disconnect(stream);
+            return;
+        }
+    }
+}
+
+fn run(mut authority: Authority, sender: Sender<ServerEvent>, receiver: Receiver<ServerEvent>) {
+    let mut clients: HashMap<PlayerId, Client> = HashMap::new();
+    let mut ids = PlayerIds::default();
+
+    for event in receiver {
+        match event {
+            ServerEvent::Connected(mut stream) => {
+                let player = match ids.next_free() {
+                    Some(player) => player,
+                    None => {
+                        eprintln!("Refused a player, every player id is taken");
+                        let full = ServerMessage::Rejected("the server is full".to_string());
+                        let _ = send_message(&mut stream, &full);
+                        continue;
+                    }
+                };
+                let (reader, client) = match stream
+                    .try_clone()
+                    .and_then(|reader| Ok((reader, Client::new(stream)?)))
+                {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        eprintln!("Failed to set up connection: {}", e);
+                        continue;
+                    }
+                };
+                ids.take(player);
+                let sender = sender.clone();
+                thread::spawn(move || read_commands(player, reader, sender));
+
+                println!("Player {} joined", player.0 + 1);
+                clients.insert(player, client);
+                let welcome = ServerMessage::Welcome(authority.welcome(player));
+                send_to(&mut clients, player, &welcome);
+            }
+            ServerEvent::Disconnected(player) => {
+                println!("Player {} left", player.0 + 1);
+                ids.release(player);
+                clients.remove(&player);
+                let cursor = ServerMessage::Cursor {
+                    player,
+                    coordinates: None,
+                };
+                broadcast(&mut clients, &cursor);
+            }
+            ServerEvent::Command(player, command) => {
+                let result = match command {
+                    ClientMessage::Reveal(coordinates) => authority.reveal(player, coordinates),
+                    ClientMessage::ToggleFlag(coordinates) => {
+                        authority.toggle_flag(player, coordinates)
+                    }
+                    ClientMessage::Chord(coordinates) => authority.chord(player, coordinates),
+                    ClientMessage::Sync => {
+                        let snapshot = ServerMessage::Diff(authority.snapshot());
+                        send_to(&mut clients, player, &snapshot);
+                        if let Some(summary) = authority.summary() {
+                            send_to(&mut clients, player, &ServerMessage::Summary(summary));
+                        }
+                        continue;
+                    }
+                    ClientMessage::Cursor(coordinates) => {
+                        let cursor = ServerMessage::Cursor {
+                            player,
+                            coordinates,
+                        };
+                        broadcast(&mut clients, &cursor);
+                        continue;
+                    }
+                };
+                match result {
+                    Ok(diff) => {
+                        let game_over = diff.outcome.is_some();
+                        broadcast(&mut clients, &ServerMessage::Diff(diff));
+                        if game_over {
+                            println!("Game over: {:?}", authority.outcome);
+                            if let Some(summary) = authority.summary() {
+                                broadcast(&mut clients, &ServerMessage::Summary(summary));
+                            }
+                        }
+                    }
+                    Err(e) => send_to(
+                        &mut clients,
+                        player,
+                        &ServerMessage::Rejected(e.to_string()),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+fn send_to(clients: &mut HashMap<PlayerId, Client>, player: PlayerId, message: &ServerMessage) {
+    let line = match encode_message(message) {
+        Ok(line) => line,
+        Err(e) => return eprintln!("Failed to encode a message: {}", e),
+    };
+    if let Some(client) = clients.get(&player) {
+        if client.send(line).is_err() {
+            if let Some(client) = clients.remove(&player) {
+                disconnect(client.stream);
+            }
+        }
+    }
+}
+
+fn broadcast(clients: &mut HashMap<PlayerId, Client>, message: &ServerMessage) {
+    let line = match encode_message(message) {
+        Ok(line) => line,
+        Err(e) => return eprintln!("Failed to encode a message: {}", e),
+    };
+    let failed: Vec<PlayerId> = clients
+        .iter()
+        .filter_map(|(player, client)| client.send(line.clone()).is_err().then_some(*player))
+        .collect();
+    for player in failed {
+        if let Some(client) = clients.remove(&player) {
+            disconnect(client.stream);
+        }
+    }
+}
+
+/// Closes a connection, its reader thread then reports the player gone
+/// and their id is freed
+fn disconnect(stream: TcpStream) {
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn released_ids_are_reused_lowest_first() {
+        let mut ids = PlayerIds::default();
+        for expected in 0..3 {
+            let player = ids.next_free().unwrap();
+            assert_eq!(player, PlayerId(expected));
+            ids.take(player);
+        }
+        ids.release(PlayerId(2));
+        ids.release(PlayerId(0));
+        assert_eq!(ids.next_free(), Some(PlayerId(0)));
+        ids.take(PlayerId(0));
+        assert_eq!(ids.next_free(), Some(PlayerId(2)));
+    }
+
+    #[test]
+    fn no_id_is_left_once_all_are_taken() {
+        let mut ids = PlayerIds::default();
+        for id in 0..=u8::MAX {
+            ids.take(PlayerId(id));
+        }
+        assert_eq!(ids.next_free(), None);
+        ids.release(PlayerId(200));
+        assert_eq!(ids.next_free(), Some(PlayerId(200)));
+    }
+}
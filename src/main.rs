@@ -1,15 +1,74 @@
+use std::process;
+
 use bevy::{prelude::*, window::WindowResolution};
 
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use board_plugin::{
-    resources::{BoardAssets, BoardOptions, SpriteMaterial},
+    net::NetworkClient,
+    replay::{Replay, ReplayPlayback},
+    resources::{
+        daily::{date_string, DailyChallenge, DailyHistory},
+        endless::EndlessRecord,
+        generator::{self, FromFile, MineGenerators},
+        mask::BoardMask,
+        share_code::{ShareCode, ShareCodeInput},
+        stats::GameStats,
+        tile_map::TileMap,
+        BoardAssets, BoardOptions, BoardShape, GameRules, Neighborhood, SpriteMaterial,
+    },
     AppState, BoardPlugin,
 };
 
+const USAGE: &str = "Usage: minesweeper [--connect ADDRESS] [--replay FILE [--export FILE]] \
+                     [--board FILE] [--mask FILE] [--code CODE] [--generator NAME] \
+                     [--generator-file FILE] [--hex] [--wrap] [--neighborhood NAME] \
+                     [--layers COUNT] [--cell-mines COUNT] [--lives COUNT] [--practice]";
+
 fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
     let mut app = App::new();
 
+    if let Some(address) = args.connect {
+        let client = NetworkClient::connect(&address).unwrap_or_else(|e| {
+            eprintln!("Failed to connect to {}: {}", address, e);
+            process::exit(1);
+        });
+        println!(
+            "Joined {} as player {}",
+            address,
+            client.welcome.player.0 + 1
+        );
+        app.insert_resource(client);
+    }
+
+    if let Some((path, replay)) = args.replay {
+        if let Some(export) = args.export {
+            if let Err(e) = replay.save(&export) {
+                eprintln!("Failed to export replay to {}: {}", export, e);
+                process::exit(1);
+            }
+            println!("Exported {} to {}", path, export);
+            return;
+        }
+        app.insert_resource(ReplayPlayback::new(replay));
+    }
+
+    // Inserted before the board plugin, which would add the built-in ones
+    let mut generators = MineGenerators::default();
+    if let Some(from_file) = args.generator_file {
+        generators.register(generator::FROM_FILE, from_file);
+    }
+    app.insert_resource(generators)
+        .insert_resource(StartingOptions(args.options.clone()))
+        .insert_resource(args.options);
+
     let window_resolution = WindowResolution::new(700., 800.);
 
     app.add_state::<AppState>()
@@ -26,7 +85,8 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
         )
-        .add_system(state_handler);
+        // Keys are being typed into the board code dialog
+        .add_system(state_handler.run_if(not(resource_exists::<ShareCodeInput>())));
 
     app.add_startup_systems((camera_setup, board_setup));
 
@@ -39,19 +99,175 @@ fn main() {
     app.run();
 }
 
-fn camera_setup(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+/// Command line arguments, read once at startup
+struct Args {
+    options: BoardOptions,
+    generator_file: Option<FromFile>,
+    connect: Option<String>,
+    replay: Option<(String, Replay)>,
+    export: Option<String>,
 }
 
-fn board_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(BoardOptions {
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut options = BoardOptions {
         map_size: (12, 12),
         tile_size: board_plugin::resources::TileSize::Fixed(32.0),
         bomb_count: 12,
         safe_start: true,
         tile_padding: 2.0,
         ..default()
-    });
+    };
+    let mut generator_file = None;
+    let (mut connect, mut replay, mut export) = (None, None, None);
+    // Applied after the loop, in this order, whatever the order of the
+    // arguments: layouts and codes override the board size and mine count
+    let (mut layers, mut mask, mut board, mut code) = (None, None, None, None);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            // Joins a game hosted by the authority server
+            "--connect" => connect = Some(value()?),
+            // Plays back a recorded game
+            "--replay" => {
+                let path = value()?;
+                let loaded = Replay::load(&path)
+                    .map_err(|e| format!("Failed to load replay {}: {}", path, e))?;
+                replay = Some((path, loaded));
+            }
+            // Converts the replay instead, e.g. to `.avf` or `.rawvf`
+            "--export" => export = Some(value()?),
+            // How mines are placed, e.g. `no-guess`
+            "--generator" => {
+                let name = value()?;
+                if MineGenerators::default().get(&name).is_none() {
+                    return Err(format!("Unknown mine generator: {}", name));
+                }
+                options.generator = name;
+            }
+            // Places the mines of a board file on boards of any size,
+            // dropping those that don't fit
+            "--generator-file" => {
+                let path = value()?;
+                let from_file = FromFile::load(&path)
+                    .map_err(|e| format!("Failed to load board {}: {}", path, e))?;
+                generator_file = Some(from_file);
+                options.generator = generator::FROM_FILE.to_string();
+            }
+            "--hex" => options.shape = BoardShape::Hex,
+            // Joins opposite edges of square boards
+            "--wrap" => options.wrap = true,
+            // The tiles numbers count on square boards: `adjacent`,
+            // `orthogonal`, `knight` or `extended` (5x5)
+            "--neighborhood" => {
+                let name = value()?;
+                options.neighborhood = Neighborhood::from_name(&name).ok_or(format!(
+                    "Unknown neighborhood {}, expected one of {}",
+                    name,
+                    Neighborhood::NAMES.join(", ")
+                ))?;
+            }
+            // Stacks square layers, keeping the mine density of one layer
+            "--layers" => {
+                let count = value()?;
+                layers = Some(
+                    count
+                        .parse::<u16>()
+                        .ok()
+                        .filter(|layers| (1..=8).contains(layers))
+                        .ok_or(format!("Invalid layer count {}, expected 1 to 8", count))?,
+                );
+            }
+            // Cells hold up to 3 mines, flags cycle through as many levels
+            "--cell-mines" => {
+                let count = value()?;
+                options.cell_mines = count
+                    .parse()
+                    .ok()
+                    .filter(|cell_mines| (1..=3).contains(cell_mines))
+                    .ok_or(format!("Invalid mines per cell {}, expected 1 to 3", count))?;
+            }
+            // Keeps playing after hitting a mine until the lives run out,
+            // each hit adds a time penalty
+            "--lives" => {
+                let count = value()?;
+                options.lives = count
+                    .parse()
+                    .ok()
+                    .filter(|lives| (1..=9).contains(lives))
+                    .ok_or(format!("Invalid lives {}, expected 1 to 9", count))?;
+            }
+            // Moves can be taken back with Z and replayed with Y, the game is
+            // left out of the statistics
+            "--practice" => options.practice = true,
+            // Plays on the cells of a text grid or PNG silhouette, e.g.
+            // `assets/masks/heart.png`
+            "--mask" => {
+                let path = value()?;
+                let loaded = BoardMask::load(&path)
+                    .map_err(|e| format!("Failed to load mask {}: {}", path, e))?;
+                mask = Some(loaded);
+            }
+            // A fixed layout, either `.mbf` or a text grid
+            "--board" => {
+                let path = value()?;
+                let tile_map = TileMap::load(&path)
+                    .map_err(|e| format!("Failed to load board {}: {}", path, e))?;
+                board = Some(tile_map);
+            }
+            // A board shared by someone else
+            "--code" => {
+                let text = value()?;
+                let shared: ShareCode = text
+                    .parse()
+                    .map_err(|e| format!("Invalid board code {}: {}", text, e))?;
+                code = Some(shared);
+            }
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    if export.is_some() && replay.is_none() {
+        return Err("--export needs a --replay to convert".to_string());
+    }
+    if let Some(layers) = layers {
+        options.layers = layers;
+        options.bomb_count *= layers;
+    }
+    if let Some(mask) = mask {
+        options.set_mask(mask);
+    }
+    if let Some(tile_map) = board {
+        options.set_layout(&tile_map);
+    }
+    if let Some(mask) = &options.mask {
+        mask.check_size(options.map_size)
+            .map_err(|e| format!("Invalid mask: {}", e))?;
+    }
+    if let Some(code) = code {
+        code.apply_to(&mut options);
+    }
+    Ok(Args {
+        options,
+        generator_file,
+        connect,
+        replay,
+        export,
+    })
+}
+
+/// Options the app started with, restored when starting a regular game
+#[derive(Resource)]
+struct StartingOptions(BoardOptions);
+
+fn camera_setup(mut commands: Commands) {
+    commands.spawn(Camera2dBundle::default());
+}
+
+fn board_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(DailyHistory::load());
+    commands.insert_resource(GameStats::load());
+    commands.insert_resource(EndlessRecord::load());
 
     commands.insert_resource(BoardAssets {
         label: String::from("Default"),
@@ -67,6 +283,14 @@ fn board_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             texture: asset_server.load("sprites/tile.png"),
             ..default()
         },
+        hex_tile_material: SpriteMaterial {
+            color: Color::hex("#c6c6c6").unwrap(),
+            texture: asset_server.load("sprites/hex_tile.png"),
+        },
+        hex_covered_tile_material: SpriteMaterial {
+            texture: asset_server.load("sprites/hex.png"),
+            ..default()
+        },
         bomb_counter_colors: BoardAssets::default_colors(),
         flag_material: SpriteMaterial {
             texture: asset_server.load("sprites/flag.png"),
@@ -88,17 +312,23 @@ fn board_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             texture: asset_server.load("sprites/three.png"),
             ..default()
         },
+        player_colors: BoardAssets::default_player_colors(),
+        font: asset_server.load("fonts/DejaVuSansMono.ttf"),
     })
 }
 
 fn state_handler(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<AppState>>,
     state: ResMut<State<AppState>>,
+    mut board_options: ResMut<BoardOptions>,
+    starting_options: Res<StartingOptions>,
+    daily_history: Res<DailyHistory>,
     keys: Res<Input<KeyCode>>,
 ) {
     if keys.just_pressed(KeyCode::C) {
         debug!("clearing detected");
-        if state.0 == AppState::InGame {
+        if state.0 != AppState::Out {
             info!("clearing game");
             next_state.set(AppState::Out);
         }
@@ -108,7 +338,46 @@ fn state_handler(
         debug!("loading detected");
         if state.0 == AppState::Out {
             info!("loading game");
+            *board_options = starting_options.0.clone();
+            board_options.rules = GameRules::Classic;
+            commands.remove_resource::<DailyChallenge>();
             next_state.set(AppState::InGame);
         }
     }
+
+    if keys.just_pressed(KeyCode::V) {
+        debug!("versus loading detected");
+        if state.0 == AppState::Out {
+            info!("loading versus game");
+            *board_options = starting_options.0.clone();
+            board_options.rules = GameRules::Versus;
+            commands.remove_resource::<DailyChallenge>();
+            next_state.set(AppState::InGame);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::D) {
+        debug!("daily loading detected");
+        if state.0 == AppState::Out {
+            let daily = DailyChallenge::today(&daily_history);
+            info!(
+                "loading daily challenge {} ({})",
+                date_string(daily.day),
+                if daily.scored { "scored" } else { "practice" }
+            );
+            *board_options = starting_options.0.clone();
+            daily.apply_to(&mut board_options);
+            commands.insert_resource(daily);
+            next_state.set(AppState::InGame);
+        }
+    }
+
+    if keys.just_pressed(KeyCode::E) {
+        debug!("endless loading detected");
+        if state.0 == AppState::Out {
+            info!("loading endless game");
+            *board_options = starting_options.0.clone();
+            next_state.set(AppState::Endless);
+        }
+    }
 }